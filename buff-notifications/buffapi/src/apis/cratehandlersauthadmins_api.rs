@@ -13,12 +13,36 @@ use reqwest;
 use super::{configuration, Error};
 use crate::{apis::ResponseContent, models};
 
+/// struct for typed successes of method [`login`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginSuccess {
+    Status200(models::AdminLoginResponse),
+    Status202(models::TwoFactorChallenge),
+    UnknownValue(serde_json::Value),
+}
+
 /// struct for typed errors of method [`login`]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum LoginError {
     Status400(models::Details),
     Status401(models::Details),
+    Status403(models::Details),
+    Status429(models::Details),
+    Status500(models::Details),
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`login_totp`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginTotpError {
+    Status400(models::Details),
+    Status401(models::Details),
+    Status403(models::Details),
+    Status409(models::Details),
+    Status429(models::Details),
     Status500(models::Details),
     UnknownValue(serde_json::Value),
 }
@@ -26,7 +50,7 @@ pub enum LoginError {
 pub async fn login(
     configuration: &configuration::Configuration,
     credentials: models::Credentials,
-) -> Result<models::AdminLoginResponse, Error<LoginError>> {
+) -> Result<ResponseContent<LoginSuccess>, Error<LoginError>> {
     let local_var_configuration = configuration;
 
     let local_var_client = &local_var_configuration.client;
@@ -48,7 +72,13 @@ pub async fn login(
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
-        serde_json::from_str(&local_var_content).map_err(Error::from)
+        let local_var_entity: Option<LoginSuccess> = serde_json::from_str(&local_var_content).ok();
+        let local_var_result = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Ok(local_var_result)
     } else {
         let local_var_entity: Option<LoginError> = serde_json::from_str(&local_var_content).ok();
         let local_var_error = ResponseContent {
@@ -59,3 +89,44 @@ pub async fn login(
         Err(Error::ResponseError(local_var_error))
     }
 }
+
+pub async fn login_totp(
+    configuration: &configuration::Configuration,
+    two_factor_login_request: models::TwoFactorLoginRequest,
+) -> Result<models::AdminLoginResponse, Error<LoginTotpError>> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!(
+        "{}/api/auth/admin/login/totp",
+        local_var_configuration.base_path
+    );
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::POST, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+    local_var_req_builder = local_var_req_builder.json(&two_factor_login_request);
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<LoginTotpError> =
+            serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
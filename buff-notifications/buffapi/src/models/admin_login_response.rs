@@ -12,12 +12,17 @@ use crate::models;
 
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct AdminLoginResponse {
+    #[serde(rename = "refresh_token")]
+    pub refresh_token: String,
     #[serde(rename = "token")]
     pub token: String,
 }
 
 impl AdminLoginResponse {
-    pub fn new(token: String) -> AdminLoginResponse {
-        AdminLoginResponse { token }
+    pub fn new(refresh_token: String, token: String) -> AdminLoginResponse {
+        AdminLoginResponse {
+            refresh_token,
+            token,
+        }
     }
 }
@@ -78,6 +78,10 @@ pub mod top_user;
 pub use self::top_user::TopUser;
 pub mod trade_url_form;
 pub use self::trade_url_form::TradeUrlForm;
+pub mod two_factor_challenge;
+pub use self::two_factor_challenge::TwoFactorChallenge;
+pub mod two_factor_login_request;
+pub use self::two_factor_login_request::TwoFactorLoginRequest;
 pub mod unassign_moderator_request;
 pub use self::unassign_moderator_request::UnassignModeratorRequest;
 pub mod unblacklist_user_request;
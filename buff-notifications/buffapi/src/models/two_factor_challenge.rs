@@ -0,0 +1,28 @@
+/*
+ * buff
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TwoFactorChallenge {
+    #[serde(rename = "challenge_token")]
+    pub challenge_token: String,
+    #[serde(rename = "enrolment_required")]
+    pub enrolment_required: bool,
+}
+
+impl TwoFactorChallenge {
+    pub fn new(challenge_token: String, enrolment_required: bool) -> TwoFactorChallenge {
+        TwoFactorChallenge {
+            challenge_token,
+            enrolment_required,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+/*
+ * buff
+ *
+ * No description provided (generated by Openapi Generator https://github.com/openapitools/openapi-generator)
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+use crate::models;
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    #[serde(rename = "challenge_token")]
+    pub challenge_token: String,
+    #[serde(rename = "code")]
+    pub code: String,
+}
+
+impl TwoFactorLoginRequest {
+    pub fn new(challenge_token: String, code: String) -> TwoFactorLoginRequest {
+        TwoFactorLoginRequest {
+            challenge_token,
+            code,
+        }
+    }
+}
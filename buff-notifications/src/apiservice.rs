@@ -1,17 +1,56 @@
 use buffapi::{
     apis::{
-        self, configuration::Configuration, cratehandlersadminmoderators_api::SelfInfoError,
-        cratehandlersauthadmins_api::LoginError,
+        self,
+        configuration::Configuration,
+        cratehandlersadminmoderators_api::SelfInfoError,
+        cratehandlersauthadmins_api::{LoginError, LoginSuccess, LoginTotpError},
+        ResponseContent,
+    },
+    models::{
+        AdminLoginResponse, Credentials, ModeratorOrAdminInfo, TwoFactorChallenge,
+        TwoFactorLoginRequest,
     },
-    models::{AdminLoginResponse, Credentials, ModeratorOrAdminInfo},
 };
 
+const BOT_API_KEY_HEADER: &str = "X-Bot-Key";
+
 pub struct ApiService {
     configuration: Configuration,
 }
 
+pub enum Login {
+    Authenticated(AdminLoginResponse),
+    //? Password was accepted, the code is sent with login_totp
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+fn bot_client(bot_api_key: &str) -> Result<api_reqwest::Client, Box<dyn std::error::Error>> {
+    let mut headers = api_reqwest::header::HeaderMap::new();
+    let mut bot_api_key = api_reqwest::header::HeaderValue::from_str(bot_api_key)?;
+    bot_api_key.set_sensitive(true);
+    headers.insert(BOT_API_KEY_HEADER, bot_api_key);
+
+    Ok(api_reqwest::Client::builder()
+        .default_headers(headers)
+        .build()?)
+}
+
 impl ApiService {
-    pub fn new(configuration: Configuration) -> Self {
+    pub fn new(mut configuration: Configuration, bot_api_key: Option<&str>) -> Self {
+        configuration.user_agent = Some(format!(
+            "{}/{}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ));
+
+        //? Server tells bot logins apart by this key, not by the address
+        if let Some(bot_api_key) = bot_api_key {
+            match bot_client(bot_api_key) {
+                Ok(client) => configuration.client = client,
+                Err(cause) => tracing::warn!(%cause, "Failed to set up bot api key!"),
+            }
+        }
+
         Self { configuration }
     }
 
@@ -25,7 +64,7 @@ impl ApiService {
         &mut self,
         login: impl Into<String>,
         password: impl Into<String>,
-    ) -> Result<AdminLoginResponse, apis::Error<LoginError>> {
+    ) -> Result<Login, apis::Error<LoginError>> {
         let credentials = Credentials {
             login: login.into(),
             password: password.into(),
@@ -33,10 +72,41 @@ impl ApiService {
 
         let response =
             apis::cratehandlersauthadmins_api::login(&self.configuration, credentials).await?;
+
+        match response.entity {
+            Some(LoginSuccess::Status200(tokens)) => {
+                self.authenticate(&tokens);
+                Ok(Login::Authenticated(tokens))
+            }
+            Some(LoginSuccess::Status202(challenge)) => Ok(Login::TwoFactorRequired(challenge)),
+            _ => Err(apis::Error::ResponseError(ResponseContent {
+                status: response.status,
+                content: response.content,
+                entity: None,
+            })),
+        }
+    }
+
+    pub async fn login_totp(
+        &mut self,
+        challenge_token: impl Into<String>,
+        code: impl Into<String>,
+    ) -> Result<AdminLoginResponse, apis::Error<LoginTotpError>> {
+        let request = TwoFactorLoginRequest {
+            challenge_token: challenge_token.into(),
+            code: code.into(),
+        };
+
+        let tokens =
+            apis::cratehandlersauthadmins_api::login_totp(&self.configuration, request).await?;
+        self.authenticate(&tokens);
+        Ok(tokens)
+    }
+
+    fn authenticate(&mut self, tokens: &AdminLoginResponse) {
         self.configuration.api_key = Some(apis::configuration::ApiKey {
             prefix: Some("Bearer".to_owned()),
-            key: response.token.clone(),
+            key: tokens.token.clone(),
         });
-        Ok(response)
     }
 }
@@ -11,11 +11,23 @@ pub mod reader;
 pub struct Configuration {
     admin_id: ChatId,
     redis_url: String,
-    new_orders_channel_name: String,
+    //? Deployments configured before order events still set NEW_ORDERS_CHANNEL_NAME
+    #[serde(
+        alias = "new_orders_channel_name",
+        default = "default_order_events_channel_name"
+    )]
+    order_events_channel_name: String,
     bot_token: String,
     repository_storage: PathBuf,
     states_storage: PathBuf,
     site_url: Url,
+    //? Same as BOT_API_KEY of the server, staff logins through the bot
+    //? are not locked out by the address the bot shares between all of them
+    bot_api_key: Option<String>,
+}
+
+fn default_order_events_channel_name() -> String {
+    "order_events".to_owned()
 }
 
 impl Configuration {
@@ -27,8 +39,8 @@ impl Configuration {
         &self.redis_url
     }
 
-    pub fn new_orders_channel_name(&self) -> &str {
-        &self.new_orders_channel_name
+    pub fn order_events_channel_name(&self) -> &str {
+        &self.order_events_channel_name
     }
 
     pub fn bot_token(&self) -> &str {
@@ -46,4 +58,8 @@ impl Configuration {
     pub fn site_url(&self) -> Url {
         self.site_url.clone()
     }
+
+    pub fn bot_api_key(&self) -> Option<&str> {
+        self.bot_api_key.as_deref()
+    }
 }
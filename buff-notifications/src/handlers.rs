@@ -1,8 +1,8 @@
-use crate::apiservice::ApiService;
+use crate::apiservice::{ApiService, Login};
 use crate::Config;
 use crate::{repository::Repository, state::State, HandlerResult, ModeratorId, MyDialogue};
-use buffapi::apis::configuration::Configuration;
-use buffapi::models::ModeratorOrAdminInfo;
+use buffapi::apis::{self, configuration::Configuration};
+use buffapi::models::{ModeratorOrAdminInfo, TwoFactorChallenge};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::sync::Arc;
@@ -318,8 +318,49 @@ pub async fn handle_user_login(bot: Bot, dialogue: MyDialogue, message: Message)
     Ok(())
 }
 
-//? Yeah this one is a bit big
-//? But well commented )
+const BAD_CREDENTIALS_NOTIFICATION: &str =
+    "🔒 Корректность данных не подтверждена.\n🔴 Вход запрещен.\nПожалуйста, отправьте сначала корректный логин и затем пароль!";
+
+const LOCKED_OUT_NOTIFICATION: &str =
+    "⛔ Слишком много неудачных попыток входа.\n⏳ Попробуйте позже.";
+
+const ENROLMENT_REQUIRED_NOTIFICATION: &str =
+    "🔐 Для входа требуется двухфакторная аутентификация.\n🌐 Подключите её в панели управления и затем войдите снова.";
+
+const BAD_TWO_FACTOR_CODE_NOTIFICATION: &str =
+    "🔒 Код не подтвержден или истек.\n🔴 Вход запрещен.\nПожалуйста, отправьте сначала корректный логин и затем пароль!";
+
+//* Sends user back to the login prompt
+async fn deny_login(
+    bot: &Bot,
+    dialogue: &MyDialogue,
+    chat_id: ChatId,
+    bot_message_id: MessageId,
+    notification: &str,
+) {
+    bot.edit_message_text(chat_id, bot_message_id, notification)
+        .reply_markup(InlineKeyboardMarkup::default())
+        .await
+        .ok();
+
+    dialogue
+        .update(State::ReceiveLogin {
+            message_id: bot_message_id,
+        })
+        .await
+        .ok();
+}
+
+fn is_locked_out<T>(cause: &apis::Error<T>) -> bool {
+    matches!(cause, apis::Error::ResponseError(response) if response.status.as_u16() == 429)
+}
+
+fn api_service(config: &Config) -> ApiService {
+    let mut configuration = Configuration::new();
+    configuration.base_path = config.server_url.to_string();
+    ApiService::new(configuration, config.bot_api_key())
+}
+
 pub async fn handle_user_password(
     bot: Bot,
     dialogue: MyDialogue,
@@ -351,97 +392,208 @@ pub async fn handle_user_password(
             .await
             .ok();
 
-        //* Helper closure to avoid unnecessary code writing
-        let access_denied_notification = || async {
-            let bad_credentials_notification =
-            "🔒 Корректность данных не подтверждена.\n🔴 Вход запрещен.\nПожалуйста, отправьте сначала корректный логин и затем пароль!";
-
-            bot.edit_message_text(chat_id, bot_message_id, bad_credentials_notification)
-                .reply_markup(InlineKeyboardMarkup::default())
-                .await
-                .ok();
-
-            dialogue
-                .update(State::ReceiveLogin {
-                    message_id: bot_message_id,
-                })
-                .await
-                .ok();
-        };
+        let mut api = api_service(&config);
 
-        //* Setting up api client
-        let mut configuration = Configuration::new();
-        configuration.base_path = config.server_url.to_string();
+        //* Trying to login with credentials
+        match api.login(&login, password).await {
+            Ok(Login::Authenticated(_)) => {}
+            Ok(Login::TwoFactorRequired(TwoFactorChallenge {
+                enrolment_required: true,
+                ..
+            })) => {
+                //? Secret and recovery codes must not end up in the chat history
+                deny_login(
+                    &bot,
+                    &dialogue,
+                    chat_id,
+                    bot_message_id,
+                    ENROLMENT_REQUIRED_NOTIFICATION,
+                )
+                .await;
+                return Ok(());
+            }
+            Ok(Login::TwoFactorRequired(TwoFactorChallenge {
+                challenge_token, ..
+            })) => {
+                let code_prompt =
+                    "🔢 Пожалуйста, отправьте код из приложения-аутентификатора или резервный код!";
 
-        let mut api = ApiService::new(configuration);
+                bot.edit_message_text(chat_id, bot_message_id, code_prompt)
+                    .reply_markup(InlineKeyboardMarkup::default())
+                    .await
+                    .ok();
 
-        //* Trying to login with credentials
-        let _token = match api.login(&login, password).await {
-            Ok(token) => token,
+                dialogue
+                    .update(State::ReceiveTwoFactorCode {
+                        message_id: bot_message_id,
+                        challenge_token,
+                    })
+                    .await?;
+                return Ok(());
+            }
+            Err(cause) if is_locked_out(&cause) => {
+                tracing::warn!("Login is locked out after too many attempts!");
+                deny_login(
+                    &bot,
+                    &dialogue,
+                    chat_id,
+                    bot_message_id,
+                    LOCKED_OUT_NOTIFICATION,
+                )
+                .await;
+                return Ok(());
+            }
             Err(cause) => {
                 tracing::warn!(%cause, "Failed to login!");
-                access_denied_notification().await;
+                deny_login(
+                    &bot,
+                    &dialogue,
+                    chat_id,
+                    bot_message_id,
+                    BAD_CREDENTIALS_NOTIFICATION,
+                )
+                .await;
                 return Ok(());
             }
         };
 
-        //* Trying to get moderator info
-        let ModeratorOrAdminInfo {
-            id: moderator_id,
-            login: moderator_login,
-            role: _,
-        } = match api.moderator_info().await {
-            Ok(info) => info,
+        request_moderator_approval(&bot, &dialogue, &mut api, chat_id, bot_message_id, &config)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn handle_user_two_factor_code(
+    bot: Bot,
+    dialogue: MyDialogue,
+    message: Message,
+    config: Arc<Config>,
+) -> HandlerResult {
+    let current_state = dialogue.get().await?;
+    let message_text = message.text();
+
+    if let (
+        Some(State::ReceiveTwoFactorCode {
+            message_id: bot_message_id,
+            challenge_token,
+        }),
+        Some(code),
+    ) = (current_state, message_text)
+    {
+        let chat_id = message.chat.id;
+
+        //* Delete message from user containing code
+        bot.delete_message(chat_id, message.id).await.ok();
+
+        let mut api = api_service(&config);
+
+        //? Challenge is used up by the first attempt so any failure starts over
+        match api.login_totp(challenge_token, code.trim()).await {
+            Ok(_) => {}
+            Err(cause) if is_locked_out(&cause) => {
+                tracing::warn!("Login is locked out after too many attempts!");
+                deny_login(
+                    &bot,
+                    &dialogue,
+                    chat_id,
+                    bot_message_id,
+                    LOCKED_OUT_NOTIFICATION,
+                )
+                .await;
+                return Ok(());
+            }
             Err(cause) => {
-                tracing::warn!(%cause, "Failed to get moderation info!");
-                access_denied_notification().await;
+                tracing::warn!(%cause, "Failed to confirm two-factor code!");
+                deny_login(
+                    &bot,
+                    &dialogue,
+                    chat_id,
+                    bot_message_id,
+                    BAD_TWO_FACTOR_CODE_NOTIFICATION,
+                )
+                .await;
                 return Ok(());
             }
-        };
+        }
+
+        request_moderator_approval(&bot, &dialogue, &mut api, chat_id, bot_message_id, &config)
+            .await?;
+    }
+    Ok(())
+}
 
-        //* Notify user that he needs to wait for admin approval
-        let wait_for_admin_approval_notification = "🔓 Корректность данных подтверждена.\n🟢 Вход разрешен.\n⏳ Дождитесь, когда администратор примет Вашу заявку.";
+//* Called once the api accepted the staff account
+async fn request_moderator_approval(
+    bot: &Bot,
+    dialogue: &MyDialogue,
+    api: &mut ApiService,
+    chat_id: ChatId,
+    bot_message_id: MessageId,
+    config: &Config,
+) -> HandlerResult {
+    //* Trying to get moderator info
+    let ModeratorOrAdminInfo {
+        id: moderator_id,
+        login: moderator_login,
+        role: _,
+    } = match api.moderator_info().await {
+        Ok(info) => info,
+        Err(cause) => {
+            tracing::warn!(%cause, "Failed to get moderation info!");
+            deny_login(
+                bot,
+                dialogue,
+                chat_id,
+                bot_message_id,
+                BAD_CREDENTIALS_NOTIFICATION,
+            )
+            .await;
+            return Ok(());
+        }
+    };
 
-        bot.edit_message_text(
-            chat_id,
-            bot_message_id,
-            wait_for_admin_approval_notification,
-        )
-        .reply_markup(InlineKeyboardMarkup::default())
-        .await
-        .ok();
+    //* Notify user that he needs to wait for admin approval
+    let wait_for_admin_approval_notification = "🔓 Корректность данных подтверждена.\n🟢 Вход разрешен.\n⏳ Дождитесь, когда администратор примет Вашу заявку.";
 
-        //* Notify admin about new request
-        let new_request_notification = format!("➕ Запрос на добавление модератора\n👤 ID модератора: {moderator_id}\n👤 Логин модератора: {moderator_login}\n👤 ID пользователя: {chat_id}");
-
-        let reply_markup = {
-            let options = [
-                CallbackData::AcceptModerator {
-                    moderator_id: ModeratorId(moderator_id.parse().unwrap()), // Safe by api
-                    user_id: chat_id,
-                    message_id: bot_message_id,
-                },
-                CallbackData::DeclineModerator {
-                    moderator_id: ModeratorId(moderator_id.parse().unwrap()), // Safe by api
-                    user_id: chat_id,
-                    message_id: bot_message_id,
-                },
-            ]
-            .map(|option| {
-                InlineKeyboardButton::callback(
-                    option.to_string(),
-                    serde_json::to_string(&option).unwrap(), //? Safe as I trust serde for simple enums :)
-                )
-            });
+    bot.edit_message_text(
+        chat_id,
+        bot_message_id,
+        wait_for_admin_approval_notification,
+    )
+    .reply_markup(InlineKeyboardMarkup::default())
+    .await
+    .ok();
 
-            InlineKeyboardMarkup::new([options])
-        };
-        bot.send_message(config.admin_id(), new_request_notification)
-            .reply_markup(reply_markup)
-            .await
-            .ok();
+    //* Notify admin about new request
+    let new_request_notification = format!("➕ Запрос на добавление модератора\n👤 ID модератора: {moderator_id}\n👤 Логин модератора: {moderator_login}\n👤 ID пользователя: {chat_id}");
 
-        dialogue.exit().await?;
-    }
+    let reply_markup = {
+        let options = [
+            CallbackData::AcceptModerator {
+                moderator_id: ModeratorId(moderator_id.parse().unwrap()), // Safe by api
+                user_id: chat_id,
+                message_id: bot_message_id,
+            },
+            CallbackData::DeclineModerator {
+                moderator_id: ModeratorId(moderator_id.parse().unwrap()), // Safe by api
+                user_id: chat_id,
+                message_id: bot_message_id,
+            },
+        ]
+        .map(|option| {
+            InlineKeyboardButton::callback(
+                option.to_string(),
+                serde_json::to_string(&option).unwrap(), //? Safe as I trust serde for simple enums :)
+            )
+        });
+
+        InlineKeyboardMarkup::new([options])
+    };
+    bot.send_message(config.admin_id(), new_request_notification)
+        .reply_markup(reply_markup)
+        .await
+        .ok();
+
+    dialogue.exit().await?;
     Ok(())
 }
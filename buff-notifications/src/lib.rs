@@ -38,13 +38,15 @@ pub struct ModeratorId(pub i64);
 pub struct Config {
     admin_id: ChatId,
     server_url: Url,
+    bot_api_key: Option<String>,
 }
 
 impl Config {
-    pub fn new(admin_id: ChatId, server_url: Url) -> Self {
+    pub fn new(admin_id: ChatId, server_url: Url, bot_api_key: Option<String>) -> Self {
         Self {
             admin_id,
             server_url,
+            bot_api_key,
         }
     }
 
@@ -55,6 +57,10 @@ impl Config {
     pub fn server_url(&self) -> Url {
         self.server_url.clone()
     }
+
+    pub fn bot_api_key(&self) -> Option<&str> {
+        self.bot_api_key.as_deref()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -82,7 +88,69 @@ impl Display for Model {
 
         write!(
             f,
-            "✉️ - Новое сообщение!\n📕  - Номер заказа: {id}\n💰  - {amount}{currency_symbol}\n💳  - Способ оплаты: {payment_method}\n💴  - Курс: {fixed_currency_rate}"
+            "📕  - Номер заказа: {id}\n💰  - {amount}{currency_symbol}\n💳  - Способ оплаты: {payment_method}\n💴  - Курс: {fixed_currency_rate}"
         )
     }
 }
+
+//? Must be equal to the version published by the server
+pub const SUPPORTED_ORDER_EVENT_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderEventKind {
+    Created,
+    Maybepayed,
+    Succeeded,
+    Cancelled,
+    Flagged,
+    ModeratorAssigned { moderator_id: i64 },
+    ModeratorUnassigned { moderator_id: i64 },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrderEvent {
+    pub version: u16,
+    pub kind: OrderEventKind,
+    pub order: Model,
+    pub emitted_at: DateTime,
+}
+
+impl OrderEvent {
+    //? Returns None for malformed payloads and unknown versions
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(payload)
+            .ok()
+            .filter(|event| event.version == SUPPORTED_ORDER_EVENT_VERSION)
+    }
+
+    //? Moderator who should be notified about this event
+    pub fn recipient(&self) -> Option<ModeratorId> {
+        match self.kind {
+            OrderEventKind::Created | OrderEventKind::Maybepayed | OrderEventKind::Flagged => {
+                self.order.moderator_id.map(ModeratorId)
+            }
+            OrderEventKind::ModeratorAssigned { moderator_id }
+            | OrderEventKind::ModeratorUnassigned { moderator_id } => {
+                Some(ModeratorId(moderator_id))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for OrderEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let header = match self.kind {
+            OrderEventKind::Created => "✉️ - Новый заказ!",
+            OrderEventKind::Maybepayed => "💸 - Заказ отмечен как оплаченный!",
+            OrderEventKind::Succeeded => "✅ - Заказ выполнен!",
+            OrderEventKind::Cancelled => "❌ - Заказ отменен!",
+            OrderEventKind::Flagged => "⏰ - Оплаченный заказ слишком долго ждет проверки!",
+            OrderEventKind::ModeratorAssigned { .. } => "📌 - Вы назначены на заказ!",
+            OrderEventKind::ModeratorUnassigned { .. } => "📤 - Вы сняты с заказа!",
+        };
+
+        write!(f, "{header}\n{}", self.order)
+    }
+}
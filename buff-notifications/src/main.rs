@@ -3,7 +3,7 @@ use buff_notifications::configuration::{self, Configuration};
 use buff_notifications::repository::Repository;
 use buff_notifications::schema::schema;
 
-use buff_notifications::{Config, Model, ModeratorId, RedisTaskStatus, RedisTaskStatusError};
+use buff_notifications::{Config, ModeratorId, OrderEvent, RedisTaskStatus, RedisTaskStatusError};
 use dotenvy::dotenv;
 use futures::StreamExt as _;
 use std::fs::File;
@@ -80,7 +80,7 @@ async fn initialize_redis_event_listener(
 
         //? Using if let here makes reference for RedisError
         let subscribe_result = pubsub
-            .subscribe(configuration_for_notification_listener_task.new_orders_channel_name())
+            .subscribe(configuration_for_notification_listener_task.order_events_channel_name())
             .await;
 
         //* Subscribing to redis channel
@@ -110,13 +110,11 @@ async fn initialize_redis_event_listener(
         //* Start listening for new events
         while let Some(event) = pubsub_into_message.next().await {
             if let Ok(payload) = event.get_payload::<String>() {
-                if let Ok(new_order) = serde_json::from_str::<Model>(&payload) {
-                    if let Some(moderator_id) = new_order.moderator_id {
-                        if let Some(chat_id) =
-                            repository.read().await.get(ModeratorId(moderator_id)).await
-                        {
+                if let Some(order_event) = OrderEvent::from_payload(&payload) {
+                    if let Some(moderator_id) = order_event.recipient() {
+                        if let Some(chat_id) = repository.read().await.get(moderator_id).await {
                             cloned_bot
-                                .send_message(chat_id, new_order.to_string())
+                                .send_message(chat_id, order_event.to_string())
                                 .await
                                 .ok(); //? In case user blocked the bot
                         }
@@ -207,6 +205,7 @@ async fn main() {
     let config = Arc::new(Config::new(
         configuration.admin_id(),
         configuration.site_url(),
+        configuration.bot_api_key().map(ToOwned::to_owned),
     ));
 
     Dispatcher::builder(bot, schema())
@@ -1,7 +1,8 @@
 use crate::{
     commands::Command,
     handlers::{
-        handle_user_login, handle_user_password, logout, process_all_callback_queries, start,
+        handle_user_login, handle_user_password, handle_user_two_factor_code, logout,
+        process_all_callback_queries, start,
     },
     state::State,
 };
@@ -24,7 +25,14 @@ pub fn schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
     let message_handler = Update::filter_message()
         .branch(command_handler)
         .branch(case![State::ReceiveLogin { message_id }].endpoint(handle_user_login))
-        .branch(case![State::ReceivePassword { login, message_id }].endpoint(handle_user_password));
+        .branch(case![State::ReceivePassword { login, message_id }].endpoint(handle_user_password))
+        .branch(
+            case![State::ReceiveTwoFactorCode {
+                message_id,
+                challenge_token
+            }]
+            .endpoint(handle_user_two_factor_code),
+        );
 
     let callback_query_handler =
         Update::filter_callback_query().endpoint(process_all_callback_queries);
@@ -13,6 +13,10 @@ pub enum State {
         message_id: MessageId,
         login: String,
     },
+    ReceiveTwoFactorCode {
+        message_id: MessageId,
+        challenge_token: String,
+    },
 }
 
 impl Default for State {
@@ -27,6 +31,7 @@ impl State {
             State::Start { message_id } => message_id.clone(),
             State::ReceiveLogin { message_id } => Some(message_id.clone()),
             State::ReceivePassword { message_id, .. } => Some(message_id.clone()),
+            State::ReceiveTwoFactorCode { message_id, .. } => Some(message_id.clone()),
         }
     }
 }
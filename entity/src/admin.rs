@@ -4,22 +4,59 @@ use super::sea_orm_active_enums::Role;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "admin")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
+    #[sea_orm(unique)]
     pub login: String,
     pub password: String,
     pub role: Role,
+    pub capacity: Option<i32>,
+    pub on_shift: bool,
+    pub last_assigned_at: Option<DateTime>,
+    pub disabled_at: Option<DateTime>,
+    pub last_login_at: Option<DateTime>,
+    pub last_activity_at: Option<DateTime>,
+    pub password_reset_token: Option<String>,
+    pub password_reset_expires_at: Option<DateTime>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime>,
+    pub totp_recovery_codes: Vec<String>,
+    pub totp_last_used_step: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::admin_permission::Entity")]
+    AdminPermission,
+    #[sea_orm(has_many = "super::blacklisted::Entity")]
+    Blacklisted,
     #[sea_orm(has_many = "super::chat::Entity")]
     Chat,
+    #[sea_orm(has_many = "super::currency_rate_history::Entity")]
+    CurrencyRateHistory,
     #[sea_orm(has_many = "super::order::Entity")]
     Order,
+    #[sea_orm(has_many = "super::review::Entity")]
+    Review,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_many = "super::staff_login_audit::Entity")]
+    StaffLoginAudit,
+}
+
+impl Related<super::admin_permission::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AdminPermission.def()
+    }
+}
+
+impl Related<super::blacklisted::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Blacklisted.def()
+    }
 }
 
 impl Related<super::chat::Entity> for Entity {
@@ -28,10 +65,53 @@ impl Related<super::chat::Entity> for Entity {
     }
 }
 
+impl Related<super::currency_rate_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CurrencyRateHistory.def()
+    }
+}
+
 impl Related<super::order::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Order.def()
     }
 }
 
+impl Related<super::review::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Review.def()
+    }
+}
+
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::staff_login_audit::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StaffLoginAudit.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
+
+//? This is manual for hiding the password hash, reset token and totp secrets in logs
+impl std::fmt::Debug for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Model")
+            .field("id", &self.id)
+            .field("login", &self.login)
+            .field("role", &self.role)
+            .field("capacity", &self.capacity)
+            .field("on_shift", &self.on_shift)
+            .field("last_assigned_at", &self.last_assigned_at)
+            .field("disabled_at", &self.disabled_at)
+            .field("last_login_at", &self.last_login_at)
+            .field("last_activity_at", &self.last_activity_at)
+            .field("password_reset_expires_at", &self.password_reset_expires_at)
+            .field("totp_enabled_at", &self.totp_enabled_at)
+            .finish_non_exhaustive()
+    }
+}
@@ -9,10 +9,26 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub steam_id: i64,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub reason: Option<String>,
+    pub expires_at: Option<DateTime>,
+    pub blocks_ordering: bool,
+    pub blocks_chatting: bool,
+    pub blocks_reviewing: bool,
+    pub admin_id: Option<i64>,
+    pub created_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::admin::Entity",
+        from = "Column::AdminId",
+        to = "super::admin::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Admin,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::SteamId",
@@ -23,6 +39,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::admin::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Admin.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
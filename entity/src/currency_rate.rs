@@ -11,6 +11,17 @@ pub struct Model {
     #[sea_orm(unique)]
     pub symbol: String,
     pub rate: Decimal,
+    pub auto_update: bool,
+    pub markup: Decimal,
+    pub max_jump: Option<Decimal>,
+    pub pending_rate: Option<Decimal>,
+    pub manual_override: bool,
+    pub stale: bool,
+    pub updated_at: DateTime,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub scale: Option<i32>,
+    pub daily_limit: Option<Decimal>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
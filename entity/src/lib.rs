@@ -3,15 +3,23 @@
 pub mod prelude;
 
 pub mod admin;
+pub mod admin_permission;
 pub mod blacklisted;
 pub mod chat;
 pub mod currency_rate;
+pub mod currency_rate_history;
 pub mod image;
 pub mod message;
 pub mod order;
+pub mod order_status_history;
 pub mod requisites;
 pub mod review;
+pub mod role_permission;
 pub mod sea_orm_active_enums;
+pub mod seed_version;
+pub mod session;
 pub mod social;
+pub mod staff_login_audit;
+pub mod staff_policy;
 pub mod user;
 pub mod video_review;
@@ -12,6 +12,7 @@ pub struct Model {
     pub chat_id: i64,
     pub text: String,
     pub sender: Sender,
+    pub sender_id: Option<i64>,
     pub created_at: DateTime,
 }
 
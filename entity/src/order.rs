@@ -20,6 +20,8 @@ pub struct Model {
     pub fixed_currency_rate: Decimal,
     #[sea_orm(column_type = "Text")]
     pub currency_symbol: String,
+    pub flagged_at: Option<DateTime>,
+    pub quote_jti: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -34,6 +36,8 @@ pub enum Relation {
     Admin,
     #[sea_orm(has_many = "super::chat::Entity")]
     Chat,
+    #[sea_orm(has_many = "super::order_status_history::Entity")]
+    OrderStatusHistory,
     #[sea_orm(
         belongs_to = "super::requisites::Entity",
         from = "Column::RequisitesId",
@@ -42,6 +46,8 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Requisites,
+    #[sea_orm(has_one = "super::review::Entity")]
+    Review,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::SteamId",
@@ -64,12 +70,24 @@ impl Related<super::chat::Entity> for Entity {
     }
 }
 
+impl Related<super::order_status_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderStatusHistory.def()
+    }
+}
+
 impl Related<super::requisites::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Requisites.def()
     }
 }
 
+impl Related<super::review::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Review.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -1,14 +1,22 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
 pub use super::admin::Entity as Admin;
+pub use super::admin_permission::Entity as AdminPermission;
 pub use super::blacklisted::Entity as Blacklisted;
 pub use super::chat::Entity as Chat;
 pub use super::currency_rate::Entity as CurrencyRate;
+pub use super::currency_rate_history::Entity as CurrencyRateHistory;
 pub use super::image::Entity as Image;
 pub use super::message::Entity as Message;
 pub use super::order::Entity as Order;
+pub use super::order_status_history::Entity as OrderStatusHistory;
 pub use super::requisites::Entity as Requisites;
 pub use super::review::Entity as Review;
+pub use super::role_permission::Entity as RolePermission;
+pub use super::seed_version::Entity as SeedVersion;
+pub use super::session::Entity as Session;
 pub use super::social::Entity as Social;
+pub use super::staff_login_audit::Entity as StaffLoginAudit;
+pub use super::staff_policy::Entity as StaffPolicy;
 pub use super::user::Entity as User;
 pub use super::video_review::Entity as VideoReview;
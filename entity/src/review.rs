@@ -1,5 +1,6 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+use super::sea_orm_active_enums::ReviewStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -12,10 +13,36 @@ pub struct Model {
     pub review: String,
     pub stars: i16,
     pub created_at: DateTime,
+    pub order_id: Option<i64>,
+    pub updated_at: Option<DateTime>,
+    pub status: ReviewStatus,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub rejection_reason: Option<String>,
+    pub moderated_by: Option<i64>,
+    pub moderated_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub reply: Option<String>,
+    pub replied_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::admin::Entity",
+        from = "Column::ModeratedBy",
+        to = "super::admin::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Admin,
+    #[sea_orm(
+        belongs_to = "super::order::Entity",
+        from = "Column::OrderId",
+        to = "super::order::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Order,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::SteamId",
@@ -26,6 +53,18 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::admin::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Admin.def()
+    }
+}
+
+impl Related<super::order::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Order.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
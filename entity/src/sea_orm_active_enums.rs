@@ -3,6 +3,80 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "actor")]
+pub enum Actor {
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    #[sea_orm(string_value = "moderator")]
+    Moderator,
+    #[sea_orm(string_value = "system")]
+    System,
+    #[sea_orm(string_value = "user")]
+    User,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "login_outcome")]
+pub enum LoginOutcome {
+    #[sea_orm(string_value = "bad_credentials")]
+    BadCredentials,
+    #[sea_orm(string_value = "bad_two_factor_code")]
+    BadTwoFactorCode,
+    #[sea_orm(string_value = "challenged")]
+    Challenged,
+    #[sea_orm(string_value = "disabled")]
+    Disabled,
+    #[sea_orm(string_value = "locked_out")]
+    LockedOut,
+    #[sea_orm(string_value = "password_reset_required")]
+    PasswordResetRequired,
+    #[sea_orm(string_value = "succeeded")]
+    Succeeded,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "login_source")]
+pub enum LoginSource {
+    #[sea_orm(string_value = "bot")]
+    Bot,
+    #[sea_orm(string_value = "web")]
+    Web,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "permission")]
+pub enum Permission {
+    #[sea_orm(string_value = "blacklist_users")]
+    BlacklistUsers,
+    #[sea_orm(string_value = "handle_orders")]
+    HandleOrders,
+    #[sea_orm(string_value = "manage_currency")]
+    ManageCurrency,
+    #[sea_orm(string_value = "manage_reviews")]
+    ManageReviews,
+    #[sea_orm(string_value = "manage_staff")]
+    ManageStaff,
+    #[sea_orm(string_value = "view_reports")]
+    ViewReports,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "rate_source")]
+pub enum RateSource {
+    #[sea_orm(string_value = "approval")]
+    Approval,
+    #[sea_orm(string_value = "manual")]
+    Manual,
+    #[sea_orm(string_value = "provider")]
+    Provider,
+}
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "review_status")]
+pub enum ReviewStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "published")]
+    Published,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "role")]
 pub enum Role {
@@ -12,6 +12,27 @@ mod m20240129_121009_create_requisites;
 mod m20240207_212222_create_chat;
 mod m20240207_221530_create_messages;
 mod m20240209_094155_create_images;
+mod m20240301_101500_create_order_status_history;
+mod m20240302_120000_add_order_flagged_at;
+mod m20240303_090000_create_currency_rate_history;
+mod m20240304_100000_add_currency_rate_provider_settings;
+mod m20240305_093000_add_currency_amount_limits;
+mod m20240306_110000_add_blacklist_restrictions;
+mod m20240307_140000_add_moderator_assignment_settings;
+mod m20240308_120000_add_review_orders;
+mod m20240309_100000_add_review_moderation;
+mod m20240310_100000_create_permissions;
+mod m20240311_100000_add_staff_lifecycle;
+mod m20240312_100000_create_sessions;
+mod m20240313_100000_add_two_factor;
+mod m20240314_100000_create_staff_login_audit;
+mod m20240315_100000_create_seed_version;
+mod m20240316_100000_add_session_previous_refresh_token;
+mod m20240317_100000_add_totp_last_used_step;
+mod m20240318_100000_take_admins_off_shift;
+mod m20240319_100000_add_admin_login_index;
+mod m20240320_100000_add_message_sender_id;
+mod m20240321_100000_add_order_quote_jti;
 
 pub struct Migrator;
 
@@ -31,6 +52,27 @@ impl MigratorTrait for Migrator {
             Box::new(m20240207_212222_create_chat::Migration),
             Box::new(m20240207_221530_create_messages::Migration),
             Box::new(m20240209_094155_create_images::Migration),
+            Box::new(m20240301_101500_create_order_status_history::Migration),
+            Box::new(m20240302_120000_add_order_flagged_at::Migration),
+            Box::new(m20240303_090000_create_currency_rate_history::Migration),
+            Box::new(m20240304_100000_add_currency_rate_provider_settings::Migration),
+            Box::new(m20240305_093000_add_currency_amount_limits::Migration),
+            Box::new(m20240306_110000_add_blacklist_restrictions::Migration),
+            Box::new(m20240307_140000_add_moderator_assignment_settings::Migration),
+            Box::new(m20240308_120000_add_review_orders::Migration),
+            Box::new(m20240309_100000_add_review_moderation::Migration),
+            Box::new(m20240310_100000_create_permissions::Migration),
+            Box::new(m20240311_100000_add_staff_lifecycle::Migration),
+            Box::new(m20240312_100000_create_sessions::Migration),
+            Box::new(m20240313_100000_add_two_factor::Migration),
+            Box::new(m20240314_100000_create_staff_login_audit::Migration),
+            Box::new(m20240315_100000_create_seed_version::Migration),
+            Box::new(m20240316_100000_add_session_previous_refresh_token::Migration),
+            Box::new(m20240317_100000_add_totp_last_used_step::Migration),
+            Box::new(m20240318_100000_take_admins_off_shift::Migration),
+            Box::new(m20240319_100000_add_admin_login_index::Migration),
+            Box::new(m20240320_100000_add_message_sender_id::Migration),
+            Box::new(m20240321_100000_add_order_quote_jti::Migration),
         ]
     }
 }
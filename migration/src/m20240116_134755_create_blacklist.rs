@@ -45,7 +45,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum Blacklisted {
+pub enum Blacklisted {
     Table,
     //? We need this because
     //*    Entity crashes on derive without primary key
@@ -60,7 +60,7 @@ pub enum Admin {
 }
 
 #[derive(Iden, EnumIter)]
-enum Role {
+pub enum Role {
     #[iden = "role"]
     Enum,
     #[iden = "moderator"]
@@ -52,7 +52,7 @@ impl MigrationTrait for Migration {
 
 #[allow(clippy::enum_variant_names)]
 #[derive(DeriveIden)]
-enum Review {
+pub enum Review {
     Table,
     Id,
     Review,
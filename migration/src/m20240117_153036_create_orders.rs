@@ -116,7 +116,7 @@ pub enum Order {
 }
 
 #[derive(Iden, EnumIter)]
-enum Status {
+pub enum Status {
     #[iden = "status"]
     Enum,
     #[iden = "created"]
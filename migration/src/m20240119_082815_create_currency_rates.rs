@@ -38,7 +38,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum CurrencyRate {
+pub enum CurrencyRate {
     Table,
     Id,
     Symbol,
@@ -0,0 +1,139 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+use crate::m20240117_153036_create_orders::{Order, Status};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(Actor::Enum)
+                    .values(Actor::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderStatusHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::OrderId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_order_status_history_order")
+                            .from(OrderStatusHistory::Table, OrderStatusHistory::OrderId)
+                            .to(Order::Table, Order::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    //? Null when the order has just been created
+                    .col(ColumnDef::new(OrderStatusHistory::FromStatus).enumeration(
+                        Status::Enum,
+                        [
+                            Status::Cancelled,
+                            Status::Created,
+                            Status::Succeeded,
+                            Status::MayBePayed,
+                        ],
+                    ))
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::ToStatus)
+                            .enumeration(
+                                Status::Enum,
+                                [
+                                    Status::Cancelled,
+                                    Status::Created,
+                                    Status::Succeeded,
+                                    Status::MayBePayed,
+                                ],
+                            )
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::Actor)
+                            .enumeration(
+                                Actor::Enum,
+                                [Actor::User, Actor::Moderator, Actor::Admin, Actor::System],
+                            )
+                            .not_null(),
+                    )
+                    //? Steam id for users, admin id for moderators and admins
+                    .col(ColumnDef::new(OrderStatusHistory::ActorId).big_integer())
+                    .col(ColumnDef::new(OrderStatusHistory::Reason).string())
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_order_status_history_order")
+                    .table(OrderStatusHistory::Table)
+                    .col(OrderStatusHistory::OrderId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderStatusHistory::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(Actor::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum OrderStatusHistory {
+    Table,
+    Id,
+    OrderId,
+    FromStatus,
+    ToStatus,
+    Actor,
+    ActorId,
+    Reason,
+    CreatedAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub enum Actor {
+    #[iden = "actor"]
+    Enum,
+    #[iden = "user"]
+    User,
+    #[iden = "moderator"]
+    Moderator,
+    #[iden = "admin"]
+    Admin,
+    #[iden = "system"]
+    System,
+}
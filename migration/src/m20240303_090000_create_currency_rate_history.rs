@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CurrencyRateHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CurrencyRateHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    //? Symbol is not a foreign key so history survives currency deletion
+                    .col(
+                        ColumnDef::new(CurrencyRateHistory::Symbol)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CurrencyRateHistory::Rate)
+                            .decimal()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CurrencyRateHistory::AdminId).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_currency_rate_history_admin")
+                            .from(CurrencyRateHistory::Table, CurrencyRateHistory::AdminId)
+                            .to(Admin::Table, Admin::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .col(
+                        ColumnDef::new(CurrencyRateHistory::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_currency_rate_history_symbol_created_at")
+                    .table(CurrencyRateHistory::Table)
+                    .col(CurrencyRateHistory::Symbol)
+                    .col(CurrencyRateHistory::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        //* Current rates become the first points of history
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO currency_rate_history (symbol, rate) \
+                 SELECT symbol, rate FROM currency_rate",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CurrencyRateHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum CurrencyRateHistory {
+    Table,
+    Id,
+    Symbol,
+    Rate,
+    AdminId,
+    CreatedAt,
+}
@@ -0,0 +1,144 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+use crate::{
+    m20240119_082815_create_currency_rates::CurrencyRate,
+    m20240303_090000_create_currency_rate_history::CurrencyRateHistory,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(RateSource::Enum)
+                    .values(RateSource::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRate::Table)
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::AutoUpdate)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    //? Percent added on top of provider rate
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::Markup)
+                            .decimal()
+                            .not_null()
+                            .default(0),
+                    )
+                    //? Percent of change which requires manual approval
+                    .add_column(ColumnDef::new(ProviderSettings::MaxJump).decimal())
+                    .add_column(ColumnDef::new(ProviderSettings::PendingRate).decimal())
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::ManualOverride)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::Stale)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::UpdatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRateHistory::Table)
+                    .add_column(
+                        ColumnDef::new(ProviderSettings::Source)
+                            .enumeration(
+                                RateSource::Enum,
+                                [
+                                    RateSource::Manual,
+                                    RateSource::Provider,
+                                    RateSource::Approval,
+                                ],
+                            )
+                            .not_null()
+                            .default("manual"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRateHistory::Table)
+                    .drop_column(ProviderSettings::Source)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRate::Table)
+                    .drop_column(ProviderSettings::AutoUpdate)
+                    .drop_column(ProviderSettings::Markup)
+                    .drop_column(ProviderSettings::MaxJump)
+                    .drop_column(ProviderSettings::PendingRate)
+                    .drop_column(ProviderSettings::ManualOverride)
+                    .drop_column(ProviderSettings::Stale)
+                    .drop_column(ProviderSettings::UpdatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(RateSource::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProviderSettings {
+    AutoUpdate,
+    Markup,
+    MaxJump,
+    PendingRate,
+    ManualOverride,
+    Stale,
+    UpdatedAt,
+    Source,
+}
+
+#[derive(Iden, EnumIter)]
+enum RateSource {
+    #[iden = "rate_source"]
+    Enum,
+    #[iden = "manual"]
+    Manual,
+    #[iden = "provider"]
+    Provider,
+    #[iden = "approval"]
+    Approval,
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240119_082815_create_currency_rates::CurrencyRate;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Every limit is optional so existing currencies keep working as before
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRate::Table)
+                    .add_column(ColumnDef::new(AmountLimits::MinAmount).decimal())
+                    .add_column(ColumnDef::new(AmountLimits::MaxAmount).decimal())
+                    //? Maximum number of digits after the decimal point
+                    .add_column(ColumnDef::new(AmountLimits::Scale).integer())
+                    //? Sum of amounts of one user during the last 24 hours
+                    .add_column(ColumnDef::new(AmountLimits::DailyLimit).decimal())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CurrencyRate::Table)
+                    .drop_column(AmountLimits::MinAmount)
+                    .drop_column(AmountLimits::MaxAmount)
+                    .drop_column(AmountLimits::Scale)
+                    .drop_column(AmountLimits::DailyLimit)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AmountLimits {
+    MinAmount,
+    MaxAmount,
+    Scale,
+    DailyLimit,
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{
+    m20240116_134755_create_blacklist::Blacklisted, m20240116_141203_create_admins::Admin,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Existing records keep behaving as permanent full bans
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blacklisted::Table)
+                    .add_column(ColumnDef::new(Restriction::Reason).text())
+                    //? Null means permanent ban
+                    .add_column(ColumnDef::new(Restriction::ExpiresAt).date_time())
+                    .add_column(
+                        ColumnDef::new(Restriction::BlocksOrdering)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(Restriction::BlocksChatting)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(Restriction::BlocksReviewing)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(ColumnDef::new(Restriction::AdminId).big_integer())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("FK_blacklisted_admin")
+                            .from_tbl(Blacklisted::Table)
+                            .from_col(Restriction::AdminId)
+                            .to_tbl(Admin::Table)
+                            .to_col(Admin::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .add_column(
+                        ColumnDef::new(Restriction::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blacklisted::Table)
+                    .drop_foreign_key(Alias::new("FK_blacklisted_admin"))
+                    .drop_column(Restriction::Reason)
+                    .drop_column(Restriction::ExpiresAt)
+                    .drop_column(Restriction::BlocksOrdering)
+                    .drop_column(Restriction::BlocksChatting)
+                    .drop_column(Restriction::BlocksReviewing)
+                    .drop_column(Restriction::AdminId)
+                    .drop_column(Restriction::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Restriction {
+    Reason,
+    ExpiresAt,
+    BlocksOrdering,
+    BlocksChatting,
+    BlocksReviewing,
+    AdminId,
+    CreatedAt,
+}
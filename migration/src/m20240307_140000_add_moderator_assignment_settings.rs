@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    //? Maximum of open orders. Null means unlimited
+                    .add_column(ColumnDef::new(Assignment::Capacity).integer())
+                    .add_column(
+                        ColumnDef::new(Assignment::OnShift)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    //? Used by round robin strategy
+                    .add_column(ColumnDef::new(Assignment::LastAssignedAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    .drop_column(Assignment::Capacity)
+                    .drop_column(Assignment::OnShift)
+                    .drop_column(Assignment::LastAssignedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Assignment {
+    Capacity,
+    OnShift,
+    LastAssignedAt,
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{m20240116_222821_create_reviews::Review, m20240117_153036_create_orders::Order};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Reviews written before this migration have no order
+        //? and are shown as not verified
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Review::Table)
+                    .add_column(ColumnDef::new(ReviewOrder::OrderId).big_integer())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("FK_review_order")
+                            .from_tbl(Review::Table)
+                            .from_col(ReviewOrder::OrderId)
+                            .to_tbl(Order::Table)
+                            .to_col(Order::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .add_column(ColumnDef::new(ReviewOrder::UpdatedAt).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        //? One review per order, nulls do not collide
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_review_order_id")
+                    .table(Review::Table)
+                    .col(ReviewOrder::OrderId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX_review_order_id")
+                    .table(Review::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Review::Table)
+                    .drop_foreign_key(Alias::new("FK_review_order"))
+                    .drop_column(ReviewOrder::OrderId)
+                    .drop_column(ReviewOrder::UpdatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReviewOrder {
+    OrderId,
+    UpdatedAt,
+}
@@ -0,0 +1,116 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+use crate::{m20240116_141203_create_admins::Admin, m20240116_222821_create_reviews::Review};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(ReviewStatus::Enum)
+                    .values(ReviewStatus::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Review::Table)
+                    .add_column(
+                        ColumnDef::new(Moderation::Status)
+                            .enumeration(
+                                ReviewStatus::Enum,
+                                [
+                                    ReviewStatus::Pending,
+                                    ReviewStatus::Published,
+                                    ReviewStatus::Rejected,
+                                ],
+                            )
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .add_column(ColumnDef::new(Moderation::RejectionReason).text())
+                    .add_column(ColumnDef::new(Moderation::ModeratedBy).big_integer())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("FK_review_moderated_by")
+                            .from_tbl(Review::Table)
+                            .from_col(Moderation::ModeratedBy)
+                            .to_tbl(Admin::Table)
+                            .to_col(Admin::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .add_column(ColumnDef::new(Moderation::ModeratedAt).date_time())
+                    //? Public answer of the staff shown under the review
+                    .add_column(ColumnDef::new(Moderation::Reply).text())
+                    .add_column(ColumnDef::new(Moderation::RepliedAt).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        //? Reviews were live before moderation existed
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Review::Table)
+                    .value(
+                        Moderation::Status,
+                        Expr::val("published").as_enum(ReviewStatus::Enum),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Review::Table)
+                    .drop_foreign_key(Alias::new("FK_review_moderated_by"))
+                    .drop_column(Moderation::Status)
+                    .drop_column(Moderation::RejectionReason)
+                    .drop_column(Moderation::ModeratedBy)
+                    .drop_column(Moderation::ModeratedAt)
+                    .drop_column(Moderation::Reply)
+                    .drop_column(Moderation::RepliedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(ReviewStatus::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Moderation {
+    Status,
+    RejectionReason,
+    ModeratedBy,
+    ModeratedAt,
+    Reply,
+    RepliedAt,
+}
+
+#[derive(Iden, EnumIter)]
+enum ReviewStatus {
+    #[iden = "review_status"]
+    Enum,
+    #[iden = "pending"]
+    Pending,
+    #[iden = "published"]
+    Published,
+    #[iden = "rejected"]
+    Rejected,
+}
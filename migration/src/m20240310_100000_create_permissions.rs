@@ -0,0 +1,147 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+use crate::m20240116_141203_create_admins::{Admin, Role};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(Permission::Enum)
+                    .values(Permission::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RolePermission::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RolePermission::Role)
+                            .enumeration(Role::Enum, [Role::Admin, Role::Moderator])
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RolePermission::Permission)
+                            .enumeration(Permission::Enum, Permission::iter().skip(1))
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(RolePermission::Role)
+                            .col(RolePermission::Permission),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AdminPermission::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AdminPermission::AdminId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_admin_permission_admin")
+                            .from(AdminPermission::Table, AdminPermission::AdminId)
+                            .to(Admin::Table, Admin::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(AdminPermission::Permission)
+                            .enumeration(Permission::Enum, Permission::iter().skip(1))
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(AdminPermission::AdminId)
+                            .col(AdminPermission::Permission),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        //? Keeps what admins and moderators could do before permissions existed
+        let mut grants = Query::insert()
+            .into_table(RolePermission::Table)
+            .columns([RolePermission::Role, RolePermission::Permission])
+            .to_owned();
+
+        for permission in Permission::iter().skip(1) {
+            grants.values_panic([
+                Expr::val(Role::Admin.to_string()).as_enum(Role::Enum),
+                Expr::val(permission.to_string()).as_enum(Permission::Enum),
+            ]);
+        }
+
+        for permission in [Permission::HandleOrders, Permission::ManageReviews] {
+            grants.values_panic([
+                Expr::val(Role::Moderator.to_string()).as_enum(Role::Enum),
+                Expr::val(permission.to_string()).as_enum(Permission::Enum),
+            ]);
+        }
+
+        manager.exec_stmt(grants).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AdminPermission::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RolePermission::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(Permission::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum RolePermission {
+    Table,
+    Role,
+    Permission,
+}
+
+#[derive(DeriveIden)]
+pub enum AdminPermission {
+    Table,
+    AdminId,
+    Permission,
+}
+
+#[derive(Iden, EnumIter)]
+pub enum Permission {
+    #[iden = "permission"]
+    Enum,
+    #[iden = "manage_currency"]
+    ManageCurrency,
+    #[iden = "manage_reviews"]
+    ManageReviews,
+    #[iden = "blacklist_users"]
+    BlacklistUsers,
+    #[iden = "view_reports"]
+    ViewReports,
+    #[iden = "manage_staff"]
+    ManageStaff,
+    #[iden = "handle_orders"]
+    HandleOrders,
+}
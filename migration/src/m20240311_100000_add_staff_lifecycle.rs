@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    //? Disabled accounts keep their orders and chats but can not sign in
+                    .add_column(ColumnDef::new(Lifecycle::DisabledAt).timestamp())
+                    .add_column(ColumnDef::new(Lifecycle::LastLoginAt).timestamp())
+                    .add_column(ColumnDef::new(Lifecycle::LastActivityAt).timestamp())
+                    //? Argon2 hash of the one time token issued by an admin
+                    .add_column(ColumnDef::new(Lifecycle::PasswordResetToken).string())
+                    .add_column(ColumnDef::new(Lifecycle::PasswordResetExpiresAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    .drop_column(Lifecycle::DisabledAt)
+                    .drop_column(Lifecycle::LastLoginAt)
+                    .drop_column(Lifecycle::LastActivityAt)
+                    .drop_column(Lifecycle::PasswordResetToken)
+                    .drop_column(Lifecycle::PasswordResetExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Lifecycle {
+    DisabledAt,
+    LastLoginAt,
+    LastActivityAt,
+    PasswordResetToken,
+    PasswordResetExpiresAt,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{m20240116_115527_create_users::User, m20240116_141203_create_admins::Admin};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Session::Id)
+                            .big_integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    //? Exactly one of steam_id and admin_id is set
+                    .col(ColumnDef::new(Session::SteamId).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_session_user")
+                            .from(Session::Table, Session::SteamId)
+                            .to(User::Table, User::SteamId)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(Session::AdminId).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_session_admin")
+                            .from(Session::Table, Session::AdminId)
+                            .to(Admin::Table, Admin::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    //? Hash of the secret part of the current refresh token
+                    .col(ColumnDef::new(Session::RefreshToken).string().not_null())
+                    .col(
+                        ColumnDef::new(Session::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Session::RefreshedAt).date_time())
+                    .col(ColumnDef::new(Session::ExpiresAt).date_time().not_null())
+                    .col(ColumnDef::new(Session::RevokedAt).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_session_user")
+                    .table(Session::Table)
+                    .col(Session::SteamId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_session_admin")
+                    .table(Session::Table)
+                    .col(Session::AdminId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Session::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Session {
+    Table,
+    Id,
+    SteamId,
+    AdminId,
+    RefreshToken,
+    CreatedAt,
+    RefreshedAt,
+    ExpiresAt,
+    RevokedAt,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    //? Base32 secret, set on enrolment and kept while 2fa is enabled
+                    .add_column(ColumnDef::new(Totp::Secret).string())
+                    //? Empty until the first code is confirmed
+                    .add_column(ColumnDef::new(Totp::EnabledAt).timestamp())
+                    //? Argon2 hashes of the unused recovery codes
+                    .add_column(
+                        ColumnDef::new(Totp::RecoveryCodes)
+                            .array(ColumnType::String(None))
+                            .not_null()
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        //? Holds exactly one row
+        manager
+            .create_table(
+                Table::create()
+                    .table(StaffPolicy::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StaffPolicy::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StaffPolicy::RequireTwoFactor)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let policy = Query::insert()
+            .into_table(StaffPolicy::Table)
+            .columns([StaffPolicy::Id, StaffPolicy::RequireTwoFactor])
+            .values_panic([1.into(), false.into()])
+            .to_owned();
+
+        manager.exec_stmt(policy).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StaffPolicy::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Admin::Table)
+                    .drop_column(Totp::Secret)
+                    .drop_column(Totp::EnabledAt)
+                    .drop_column(Totp::RecoveryCodes)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Totp {
+    #[sea_orm(iden = "totp_secret")]
+    Secret,
+    #[sea_orm(iden = "totp_enabled_at")]
+    EnabledAt,
+    #[sea_orm(iden = "totp_recovery_codes")]
+    RecoveryCodes,
+}
+
+#[derive(DeriveIden)]
+pub enum StaffPolicy {
+    Table,
+    Id,
+    RequireTwoFactor,
+}
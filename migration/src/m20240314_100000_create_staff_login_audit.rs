@@ -0,0 +1,146 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(LoginOutcome::Enum)
+                    .values(LoginOutcome::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(LoginSource::Enum)
+                    .values(LoginSource::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(StaffLoginAudit::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StaffLoginAudit::Id)
+                            .big_integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    //? Empty when the login does not belong to any account
+                    .col(ColumnDef::new(StaffLoginAudit::AdminId).big_integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK_staff_login_audit_admin")
+                            .from(StaffLoginAudit::Table, StaffLoginAudit::AdminId)
+                            .to(Admin::Table, Admin::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    //? As it was typed, kept even if the account is renamed later
+                    .col(ColumnDef::new(StaffLoginAudit::Login).string().not_null())
+                    .col(
+                        ColumnDef::new(StaffLoginAudit::Outcome)
+                            .enumeration(LoginOutcome::Enum, LoginOutcome::iter().skip(1))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StaffLoginAudit::Source)
+                            .enumeration(LoginSource::Enum, LoginSource::iter().skip(1))
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(StaffLoginAudit::Ip).string())
+                    .col(ColumnDef::new(StaffLoginAudit::UserAgent).string())
+                    .col(
+                        ColumnDef::new(StaffLoginAudit::CreatedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_staff_login_audit_created_at")
+                    .table(StaffLoginAudit::Table)
+                    .col(StaffLoginAudit::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StaffLoginAudit::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(LoginOutcome::Enum).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(LoginSource::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum StaffLoginAudit {
+    Table,
+    Id,
+    AdminId,
+    Login,
+    Outcome,
+    Source,
+    Ip,
+    UserAgent,
+    CreatedAt,
+}
+
+#[derive(Iden, EnumIter)]
+pub enum LoginOutcome {
+    #[iden = "login_outcome"]
+    Enum,
+    #[iden = "succeeded"]
+    Succeeded,
+    //? Password was accepted and a two-factor code was asked for
+    #[iden = "challenged"]
+    Challenged,
+    #[iden = "bad_credentials"]
+    BadCredentials,
+    #[iden = "bad_two_factor_code"]
+    BadTwoFactorCode,
+    #[iden = "disabled"]
+    Disabled,
+    #[iden = "password_reset_required"]
+    PasswordResetRequired,
+    #[iden = "locked_out"]
+    LockedOut,
+}
+
+#[derive(Iden, EnumIter)]
+pub enum LoginSource {
+    #[iden = "login_source"]
+    Enum,
+    #[iden = "web"]
+    Web,
+    #[iden = "bot"]
+    Bot,
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Every applied version of the seed file, rows deleted by staff
+        //? are not brought back until the file version is raised
+        manager
+            .create_table(
+                Table::create()
+                    .table(SeedVersion::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SeedVersion::Version)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SeedVersion::AppliedAt)
+                            .date_time()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SeedVersion::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum SeedVersion {
+    Table,
+    Version,
+    AppliedAt,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240312_100000_create_sessions::Session;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Hash of the token the current one replaced, only presenting it
+        //? again is treated as reuse of a stolen token
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .add_column(ColumnDef::new(SessionRotation::PreviousRefreshToken).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        //? Secrets are hashed with sha-256 from now on, argon2 hashes can not be checked
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Session::Table)
+                    .value(Session::RevokedAt, Expr::current_timestamp())
+                    .and_where(Expr::col(Session::RevokedAt).is_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .drop_column(SessionRotation::PreviousRefreshToken)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionRotation {
+    PreviousRefreshToken,
+}
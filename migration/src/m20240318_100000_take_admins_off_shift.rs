@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::{Admin, Role};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Admins hold the permission to handle orders through their role,
+        //? they only get orders assigned after starting a shift themselves
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Admin::Table)
+                    .value(Shift::OnShift, false)
+                    .and_where(
+                        Expr::col(Admin::Role)
+                            .eq(Expr::val(Role::Admin.to_string()).as_enum(Role::Enum)),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Shifts are not restored, admins can start them again
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Shift {
+    OnShift,
+}
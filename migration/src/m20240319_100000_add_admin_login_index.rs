@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240116_141203_create_admins::Admin;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Logins were only checked before insert or rename,
+        //? the index catches staff created or renamed concurrently
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_admin_login")
+                    .table(Admin::Table)
+                    .col(Admin::Login)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX_admin_login")
+                    .table(Admin::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
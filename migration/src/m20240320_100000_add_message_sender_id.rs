@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240207_221530_create_messages::Message;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        //? Staff id for moderator messages and steam id for user messages,
+        //? automessages are written by nobody
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(MessageSender::SenderId).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        //* Every chat belongs to a single moderator and user so older messages can be attributed
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE message SET sender_id = CASE message.sender \
+                     WHEN 'moderator' THEN chat.moderator_id \
+                     ELSE chat.steam_id \
+                 END \
+                 FROM chat \
+                 WHERE chat.id = message.chat_id \
+                 AND NOT (message.sender = 'moderator' AND message.text LIKE 'automessage-%')",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(MessageSender::SenderId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MessageSender {
+    SenderId,
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240117_153036_create_orders::Order;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .add_column(ColumnDef::new(OrderQuote::QuoteJti).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        //? A quote buys exactly one order, orders created without a quote are not limited
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX_order_quote_jti")
+                    .table(Order::Table)
+                    .col(OrderQuote::QuoteJti)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX_order_quote_jti")
+                    .table(Order::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .drop_column(OrderQuote::QuoteJti)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderQuote {
+    QuoteJti,
+}
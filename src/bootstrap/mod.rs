@@ -0,0 +1,212 @@
+use std::{io::Write, path::PathBuf};
+
+use entity::admin::Model as AdminModel;
+use sea_orm::{DatabaseConnection, TransactionTrait};
+
+use crate::{
+    config::Configuration,
+    services::{
+        admin::{
+            moderators::{
+                CreateAdminParameters, Service as ModeratorsService,
+                ServiceError as ModeratorsServiceError,
+            },
+            permissions::{Service as PermissionsService, ServiceError as PermissionsServiceError},
+        },
+        seed::{Applied, Service as SeedService, ServiceError as SeedServiceError},
+    },
+};
+
+//? Hash of the password the first admin was shipped with before bootstrapping existed
+const DEFAULT_ADMIN_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$ZKBXQv1LtIbVXKASHcIbYw$MYqUU8AI5K2OWN3b4QdkFP+g3Dh6IDnXo40EvFvYeYQ";
+
+pub const USAGE: &str = "\
+Usage: buff [COMMAND]
+
+Commands:
+  serve         Run the server, this is the default
+  create-admin  Create an admin from BOOTSTRAP_ADMIN_LOGIN and BOOTSTRAP_ADMIN_PASSWORD
+                or from the terminal when they are not set
+  seed          Apply the seed file and exit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    CreateAdmin,
+    Seed,
+}
+
+impl Command {
+    //? Arguments without the program name
+    pub fn parse(mut arguments: impl Iterator<Item = String>) -> Option<Self> {
+        let command = match arguments.next().as_deref() {
+            None | Some("serve") => Command::Serve,
+            Some("create-admin") => Command::CreateAdmin,
+            Some("seed") => Command::Seed,
+            Some(_) => return None,
+        };
+
+        match arguments.next() {
+            Some(_) => None,
+            None => Some(command),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BootstrapError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    Moderators(#[from] ModeratorsServiceError),
+    #[error(transparent)]
+    Permissions(#[from] PermissionsServiceError),
+    #[error("Failed to read seed file {path:?}: {cause}")]
+    SeedFile {
+        path: PathBuf,
+        cause: SeedServiceError,
+    },
+    #[error(transparent)]
+    Seed(#[from] SeedServiceError),
+    #[error(
+        "Nobody can manage staff! Run `buff create-admin` or set \
+        BOOTSTRAP_ADMIN_LOGIN and BOOTSTRAP_ADMIN_PASSWORD for the first run"
+    )]
+    NoAdmin,
+    #[error("Login and password must not be empty")]
+    EmptyCredentials,
+    #[error("Passwords do not match")]
+    PasswordsDoNotMatch,
+}
+
+//? Both have to be set, a half configured bootstrap is treated as none
+fn credentials_from_configuration(configuration: &Configuration) -> Option<CreateAdminParameters> {
+    let login = configuration.bootstrap_admin_login()?.trim();
+    let password = configuration.bootstrap_admin_password()?;
+
+    match login.is_empty() || password.is_empty() {
+        true => None,
+        false => Some(CreateAdminParameters {
+            login: login.to_owned(),
+            password: password.to_owned(),
+        }),
+    }
+}
+
+fn credentials_from_terminal() -> Result<CreateAdminParameters, BootstrapError> {
+    let mut login = String::new();
+    print!("Login: ");
+    std::io::stdout().flush()?;
+    std::io::stdin().read_line(&mut login)?;
+    let login = login.trim().to_owned();
+
+    let password = rpassword::prompt_password("Password: ")?;
+    if login.is_empty() || password.is_empty() {
+        return Err(BootstrapError::EmptyCredentials);
+    }
+
+    if rpassword::prompt_password("Repeat password: ")? != password {
+        return Err(BootstrapError::PasswordsDoNotMatch);
+    }
+
+    Ok(CreateAdminParameters { login, password })
+}
+
+async fn insert_admin(
+    parameters: CreateAdminParameters,
+    connection: &DatabaseConnection,
+) -> Result<AdminModel, BootstrapError> {
+    let transaction = connection.begin().await?;
+    let admin = ModeratorsService::create_admin(parameters, &transaction).await?;
+    transaction.commit().await?;
+
+    tracing::info!(admin.id, admin.login, "Admin was created");
+    Ok(admin)
+}
+
+//* create-admin command
+pub async fn create_admin(
+    configuration: &Configuration,
+    connection: &DatabaseConnection,
+) -> Result<AdminModel, BootstrapError> {
+    let parameters = match credentials_from_configuration(configuration) {
+        Some(parameters) => parameters,
+        None => tokio::task::spawn_blocking(credentials_from_terminal)
+            .await
+            .map_err(std::io::Error::other)??,
+    };
+
+    insert_admin(parameters, connection).await
+}
+
+//? Server refuses to start without an admin instead of shipping a default one
+pub async fn ensure_admin(
+    configuration: &Configuration,
+    connection: &DatabaseConnection,
+) -> Result<(), BootstrapError> {
+    let credentials = credentials_from_configuration(configuration);
+
+    let transaction = connection.begin().await?;
+    let disabled =
+        ModeratorsService::disable_by_password_hash(DEFAULT_ADMIN_PASSWORD_HASH, &transaction)
+            .await?;
+    transaction.commit().await?;
+
+    for staff in disabled {
+        tracing::warn!(
+            staff.id,
+            staff.login,
+            "Staff used the default password and was disabled, issue a password reset and enable it"
+        );
+    }
+
+    if PermissionsService::staff_managers(connection).await? > 0 {
+        if credentials.is_some() {
+            tracing::warn!("Admin already exists, bootstrap admin credentials can be removed");
+        }
+        return Ok(());
+    }
+
+    match credentials {
+        Some(parameters) => {
+            insert_admin(parameters, connection).await?;
+            tracing::warn!("First admin was created, remove bootstrap admin credentials now");
+            Ok(())
+        }
+        None => Err(BootstrapError::NoAdmin),
+    }
+}
+
+//* seed command, also applied on every start
+pub async fn seed(
+    configuration: &Configuration,
+    connection: &DatabaseConnection,
+) -> Result<(), BootstrapError> {
+    let seed =
+        SeedService::read(configuration.seed_file()).map_err(|cause| BootstrapError::SeedFile {
+            path: configuration.seed_file().to_owned(),
+            cause,
+        })?;
+
+    let transaction = connection.begin().await?;
+    let applied = SeedService::apply(seed, &transaction).await?;
+    transaction.commit().await?;
+
+    match applied {
+        Applied::UpToDate { version } => {
+            tracing::info!(version, "Seed is up to date");
+        }
+        Applied::Applied {
+            version,
+            requisites,
+            social,
+        } => {
+            tracing::info!(version, requisites, social, "Seed was applied");
+        }
+    }
+
+    Ok(())
+}
@@ -9,12 +9,236 @@ pub struct Configuration {
     redis_url: String,
     sqlx_logging: bool,
     port: u16,
+    //? Signs tokens when jwt_algorithm is hs256
+    //? Quotes are always signed with a key derived from it whatever the algorithm is,
+    //? changing it invalidates quotes which were not redeemed yet
     jwt_secret: String,
+    #[serde(default)]
+    jwt_algorithm: JwtAlgorithmKind,
+    //? Sent as kid in the header of every issued token
+    #[serde(default = "default_jwt_key_id")]
+    jwt_key_id: String,
+    //? PEM files of the signing key pair when jwt_algorithm is rs256 or eddsa
+    jwt_private_key_path: Option<PathBuf>,
+    jwt_public_key_path: Option<PathBuf>,
+    //? Keys which no longer sign but are still accepted until their tokens expire
+    //? Every entry is kid:algorithm:secret for hs256 or kid:algorithm:path to public PEM
+    #[serde(default)]
+    jwt_retired_keys: Vec<String>,
     status_expiration_seconds: u64,
     realm: String,
     upload_folder: PathBuf,
     jwt_ttl: i64,
-    new_orders_channel_name: String,
+    //? Deployments configured before order events still set NEW_ORDERS_CHANNEL_NAME
+    #[serde(
+        alias = "new_orders_channel_name",
+        default = "default_order_events_channel_name"
+    )]
+    order_events_channel_name: String,
+    #[serde(default = "default_created_order_ttl_seconds")]
+    created_order_ttl_seconds: u64,
+    #[serde(default = "default_maybepayed_order_ttl_seconds")]
+    maybepayed_order_ttl_seconds: u64,
+    #[serde(default = "default_orders_sweep_interval_seconds")]
+    orders_sweep_interval_seconds: u64,
+    #[serde(
+        default = "default_quote_ttl_seconds",
+        deserialize_with = "positive_seconds"
+    )]
+    quote_ttl_seconds: i64,
+    #[serde(default)]
+    rates_provider: RatesProviderKind,
+    //? Path for file provider, url for http provider
+    rates_provider_source: Option<String>,
+    #[serde(default = "default_rates_refresh_interval_seconds")]
+    rates_refresh_interval_seconds: u64,
+    #[serde(default = "default_rates_stale_after_seconds")]
+    rates_stale_after_seconds: i64,
+    #[serde(default = "default_order_rate_limit")]
+    order_rate_limit: u64,
+    #[serde(default = "default_rate_limit_window_seconds")]
+    order_rate_limit_window_seconds: u64,
+    #[serde(default = "default_quote_rate_limit")]
+    quote_rate_limit: u64,
+    #[serde(default = "default_rate_limit_window_seconds")]
+    quote_rate_limit_window_seconds: u64,
+    //? Created and maybepayed orders one user can have at the same time
+    #[serde(default = "default_max_open_orders")]
+    max_open_orders: u64,
+    #[serde(default)]
+    assignment_strategy: AssignmentStrategyKind,
+    //? How long after the order was finished its review can be edited
+    #[serde(default = "default_review_edit_window_seconds")]
+    review_edit_window_seconds: i64,
+    //? How long a password reset token issued by an admin stays valid
+    #[serde(default = "default_password_reset_ttl_seconds")]
+    password_reset_ttl_seconds: i64,
+    //? Session is revoked when it was not refreshed for this long
+    #[serde(default = "default_refresh_token_ttl_seconds")]
+    refresh_token_ttl_seconds: i64,
+    //? Shown by authenticator apps next to the staff login
+    #[serde(default = "default_totp_issuer")]
+    totp_issuer: String,
+    //? How long the second login step can be completed after the password was accepted
+    #[serde(default = "default_two_factor_challenge_ttl_seconds")]
+    two_factor_challenge_ttl_seconds: u64,
+    //? Failed staff logins before the login or the address is locked out
+    #[serde(default = "default_staff_login_attempts_per_login")]
+    staff_login_attempts_per_login: u64,
+    #[serde(default = "default_staff_login_attempts_per_ip")]
+    staff_login_attempts_per_ip: u64,
+    //? Failed attempts are forgotten after this much time without new ones
+    #[serde(default = "default_staff_login_attempts_window_seconds")]
+    staff_login_attempts_window_seconds: u64,
+    //? First lockout lasts this long, every next one twice as long up to the max
+    #[serde(default = "default_staff_login_lockout_base_seconds")]
+    staff_login_lockout_base_seconds: u64,
+    #[serde(default = "default_staff_login_lockout_max_seconds")]
+    staff_login_lockout_max_seconds: u64,
+    //? Take client address from X-Forwarded-For, only when running behind a proxy
+    #[serde(default)]
+    trust_forwarded_for: bool,
+    //? Shared with the telegram bot, its staff logins are only locked out per login
+    //? because all of them come from the bot address
+    bot_api_key: Option<String>,
+    //? Declarative data applied on startup and by the seed command
+    #[serde(default = "default_seed_file")]
+    seed_file: PathBuf,
+    //? First admin created on startup when nobody can manage staff yet
+    bootstrap_admin_login: Option<String>,
+    bootstrap_admin_password: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RatesProviderKind {
+    #[default]
+    None,
+    File,
+    Http,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JwtAlgorithmKind {
+    #[default]
+    Hs256,
+    Rs256,
+    Eddsa,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategyKind {
+    #[default]
+    LeastOpenOrders,
+    RoundRobin,
+    OnlineOnly,
+}
+
+fn default_jwt_key_id() -> String {
+    "primary".to_owned()
+}
+
+fn default_order_events_channel_name() -> String {
+    "order_events".to_owned()
+}
+
+fn default_created_order_ttl_seconds() -> u64 {
+    60 * 60 // 1 hour
+}
+
+fn default_maybepayed_order_ttl_seconds() -> u64 {
+    6 * 60 * 60 // 6 hours
+}
+
+fn default_orders_sweep_interval_seconds() -> u64 {
+    60
+}
+
+fn default_quote_ttl_seconds() -> i64 {
+    60
+}
+
+fn positive_seconds<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match <i64 as serde::Deserialize>::deserialize(deserializer)? {
+        seconds if seconds > 0 => Ok(seconds),
+        seconds => Err(serde::de::Error::custom(format!(
+            "{} seconds must be greater than zero",
+            seconds
+        ))),
+    }
+}
+
+fn default_rates_refresh_interval_seconds() -> u64 {
+    5 * 60 // 5 minutes
+}
+
+fn default_rates_stale_after_seconds() -> i64 {
+    60 * 60 // 1 hour
+}
+
+fn default_order_rate_limit() -> u64 {
+    5
+}
+
+fn default_quote_rate_limit() -> u64 {
+    30
+}
+
+fn default_rate_limit_window_seconds() -> u64 {
+    60 // 1 minute
+}
+
+fn default_max_open_orders() -> u64 {
+    3
+}
+
+fn default_review_edit_window_seconds() -> i64 {
+    7 * 24 * 60 * 60 // 1 week
+}
+
+fn default_password_reset_ttl_seconds() -> i64 {
+    24 * 60 * 60 // 1 day
+}
+
+fn default_refresh_token_ttl_seconds() -> i64 {
+    30 * 24 * 60 * 60 // 30 days
+}
+
+fn default_totp_issuer() -> String {
+    "Buff".to_owned()
+}
+
+fn default_two_factor_challenge_ttl_seconds() -> u64 {
+    5 * 60 // 5 minutes
+}
+
+fn default_staff_login_attempts_per_login() -> u64 {
+    5
+}
+
+fn default_staff_login_attempts_per_ip() -> u64 {
+    20
+}
+
+fn default_staff_login_attempts_window_seconds() -> u64 {
+    15 * 60 // 15 minutes
+}
+
+fn default_staff_login_lockout_base_seconds() -> u64 {
+    60 // 1 minute
+}
+
+fn default_staff_login_lockout_max_seconds() -> u64 {
+    24 * 60 * 60 // 1 day
+}
+
+fn default_seed_file() -> PathBuf {
+    PathBuf::from("seed.toml")
 }
 
 impl Configuration {
@@ -34,6 +258,26 @@ impl Configuration {
         self.jwt_secret.as_ref()
     }
 
+    pub fn jwt_algorithm(&self) -> JwtAlgorithmKind {
+        self.jwt_algorithm
+    }
+
+    pub fn jwt_key_id(&self) -> &str {
+        &self.jwt_key_id
+    }
+
+    pub fn jwt_private_key_path(&self) -> Option<&Path> {
+        self.jwt_private_key_path.as_deref()
+    }
+
+    pub fn jwt_public_key_path(&self) -> Option<&Path> {
+        self.jwt_public_key_path.as_deref()
+    }
+
+    pub fn jwt_retired_keys(&self) -> &[String] {
+        &self.jwt_retired_keys
+    }
+
     pub fn redis_url(&self) -> &str {
         self.redis_url.as_ref()
     }
@@ -54,8 +298,124 @@ impl Configuration {
         self.jwt_ttl
     }
 
-    pub fn new_orders_channel_name(&self) -> &str {
-        &self.new_orders_channel_name
+    pub fn order_events_channel_name(&self) -> &str {
+        &self.order_events_channel_name
+    }
+
+    pub fn created_order_ttl_seconds(&self) -> u64 {
+        self.created_order_ttl_seconds
+    }
+
+    pub fn maybepayed_order_ttl_seconds(&self) -> u64 {
+        self.maybepayed_order_ttl_seconds
+    }
+
+    pub fn orders_sweep_interval_seconds(&self) -> u64 {
+        self.orders_sweep_interval_seconds
+    }
+
+    pub fn quote_ttl_seconds(&self) -> i64 {
+        self.quote_ttl_seconds
+    }
+
+    pub fn rates_provider(&self) -> RatesProviderKind {
+        self.rates_provider
+    }
+
+    pub fn rates_provider_source(&self) -> Option<&str> {
+        self.rates_provider_source.as_deref()
+    }
+
+    pub fn rates_refresh_interval_seconds(&self) -> u64 {
+        self.rates_refresh_interval_seconds
+    }
+
+    pub fn rates_stale_after_seconds(&self) -> i64 {
+        self.rates_stale_after_seconds
+    }
+
+    pub fn order_rate_limit(&self) -> u64 {
+        self.order_rate_limit
+    }
+
+    pub fn order_rate_limit_window_seconds(&self) -> u64 {
+        self.order_rate_limit_window_seconds
+    }
+
+    pub fn quote_rate_limit(&self) -> u64 {
+        self.quote_rate_limit
+    }
+
+    pub fn quote_rate_limit_window_seconds(&self) -> u64 {
+        self.quote_rate_limit_window_seconds
+    }
+
+    pub fn max_open_orders(&self) -> u64 {
+        self.max_open_orders
+    }
+
+    pub fn assignment_strategy(&self) -> AssignmentStrategyKind {
+        self.assignment_strategy
+    }
+
+    pub fn review_edit_window_seconds(&self) -> i64 {
+        self.review_edit_window_seconds
+    }
+
+    pub fn password_reset_ttl_seconds(&self) -> i64 {
+        self.password_reset_ttl_seconds
+    }
+
+    pub fn refresh_token_ttl_seconds(&self) -> i64 {
+        self.refresh_token_ttl_seconds
+    }
+
+    pub fn totp_issuer(&self) -> &str {
+        &self.totp_issuer
+    }
+
+    pub fn two_factor_challenge_ttl_seconds(&self) -> u64 {
+        self.two_factor_challenge_ttl_seconds
+    }
+
+    pub fn staff_login_attempts_per_login(&self) -> u64 {
+        self.staff_login_attempts_per_login
+    }
+
+    pub fn staff_login_attempts_per_ip(&self) -> u64 {
+        self.staff_login_attempts_per_ip
+    }
+
+    pub fn staff_login_attempts_window_seconds(&self) -> u64 {
+        self.staff_login_attempts_window_seconds
+    }
+
+    pub fn staff_login_lockout_base_seconds(&self) -> u64 {
+        self.staff_login_lockout_base_seconds
+    }
+
+    pub fn staff_login_lockout_max_seconds(&self) -> u64 {
+        self.staff_login_lockout_max_seconds
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.trust_forwarded_for
+    }
+
+    pub fn bot_api_key(&self) -> Option<&str> {
+        self.bot_api_key.as_deref().filter(|key| !key.is_empty())
+    }
+
+    pub fn seed_file(&self) -> &Path {
+        &self.seed_file
+    }
+
+    pub fn bootstrap_admin_login(&self) -> Option<&str> {
+        self.bootstrap_admin_login.as_deref()
+    }
+
+    pub fn bootstrap_admin_password(&self) -> Option<&str> {
+        self.bootstrap_admin_password.as_deref()
     }
 }
 
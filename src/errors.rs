@@ -1,4 +1,9 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDateTime;
 use sea_orm::DbErr;
 use std::{
     error::Error,
@@ -34,7 +39,7 @@ pub enum AppError {
     EmptyCredentials,
     LoginOccupied,
     AdminNotFound,
-    ModeratorIsAdmin,
+    StaffCanNotHandleOrders,
     ModeratorAlreadyAssigned,
     ModeratorNotAssigned,
     OrderIsCompletedOrCancelled,
@@ -50,6 +55,38 @@ pub enum AppError {
     DbErr(#[from] DbErr),
     RequisitesWereNotFound,
     ChatWasNotFound,
+    OrderTransitionForbidden,
+    OrderStatusChanged,
+    QuoteExpired,
+    QuoteInvalid,
+    QuoteUsed,
+    NoPendingRate,
+    ValidationFailed(Vec<FieldError>),
+    TooManyRequests(u64),
+    TooManyOpenOrders {
+        limit: u64,
+        retry_after: u64,
+    },
+    UserBlacklisted {
+        reason: Option<String>,
+        expires_at: Option<NaiveDateTime>,
+    },
+    OrderNotSucceeded,
+    ReviewAlreadyExists,
+    ReviewEditWindowClosed,
+    InvalidCursor,
+    LastStaffManager,
+    StaffDisabled,
+    PasswordResetRequired,
+    ResetTokenExpired,
+    SessionRevoked,
+    TwoFactorAlreadyEnabled,
+    TwoFactorNotEnrolled,
+    BadTwoFactorCode,
+    TwoFactorRequired,
+    LoginChallengeExpired,
+    StaffOutranksManager,
+    StaffNotDisabled,
 }
 
 impl Display for AppError {
@@ -98,7 +135,7 @@ impl Debug for AppError {
             AppError::EmptyCredentials => write!(f, "Login and password cant be empty"),
             AppError::LoginOccupied => write!(f, "Provided login was already occupied"),
             AppError::AdminNotFound => write!(f, "Admin or moderator was not found"),
-            AppError::ModeratorIsAdmin => write!(f, "Moderator has admin role"),
+            AppError::StaffCanNotHandleOrders => write!(f, "Staff can not handle orders"),
             AppError::ModeratorAlreadyAssigned => {
                 write!(f, "Moderator has already been assigned to this order")
             }
@@ -115,6 +152,83 @@ impl Debug for AppError {
             AppError::DbErr(error) => write!(f, "{}", error),
             AppError::RequisitesWereNotFound => write!(f, "Requisites were not found"),
             AppError::ChatWasNotFound => write!(f, "Chat was not found"),
+            AppError::OrderTransitionForbidden => {
+                write!(f, "You are not allowed to change the status of this order")
+            }
+            AppError::OrderStatusChanged => {
+                write!(f, "Order status was changed meanwhile, reload the order")
+            }
+            AppError::QuoteExpired => write!(f, "Quote has expired, request a new one"),
+            AppError::QuoteUsed => write!(f, "Quote was already used, request a new one"),
+            AppError::QuoteInvalid => {
+                write!(f, "Quote is invalid or was issued for another order")
+            }
+            AppError::NoPendingRate => write!(f, "Currency has no rate waiting for approval"),
+            AppError::TooManyRequests(retry_after) => {
+                write!(f, "Too many requests, retry in {} seconds", retry_after)
+            }
+            AppError::TooManyOpenOrders { limit, .. } => write!(
+                f,
+                "You can not have more than {} open orders at the same time",
+                limit
+            ),
+            AppError::UserBlacklisted { reason, expires_at } => {
+                write!(f, "You are blacklisted")?;
+                if let Some(expires_at) = expires_at {
+                    write!(f, " until {}", expires_at)?;
+                }
+                match reason {
+                    Some(reason) => write!(f, ". Reason: {}", reason),
+                    None => Ok(()),
+                }
+            }
+            AppError::OrderNotSucceeded => write!(f, "Only succeeded orders can be reviewed"),
+            AppError::ReviewAlreadyExists => write!(f, "Order has already been reviewed"),
+            AppError::ReviewEditWindowClosed => write!(f, "Review can no longer be edited"),
+            AppError::InvalidCursor => {
+                write!(f, "Cursor is invalid or was issued for another sorting")
+            }
+            AppError::LastStaffManager => {
+                write!(f, "At least one staff account must be able to manage staff")
+            }
+            AppError::StaffDisabled => write!(f, "Account is disabled"),
+            AppError::PasswordResetRequired => {
+                write!(
+                    f,
+                    "Password has to be reset with the token issued by an admin"
+                )
+            }
+            AppError::ResetTokenExpired => write!(f, "Password reset token has expired"),
+            AppError::SessionRevoked => write!(f, "Session has expired or was revoked"),
+            AppError::TwoFactorAlreadyEnabled => {
+                write!(f, "Two-factor authentication is already enabled")
+            }
+            AppError::TwoFactorNotEnrolled => {
+                write!(f, "Two-factor authentication has to be enrolled first")
+            }
+            AppError::BadTwoFactorCode => write!(f, "Bad two-factor code"),
+            AppError::TwoFactorRequired => {
+                write!(f, "Two-factor authentication is required for all staff")
+            }
+            AppError::LoginChallengeExpired => {
+                write!(
+                    f,
+                    "Login challenge has expired, sign in with password again"
+                )
+            }
+            AppError::StaffOutranksManager => {
+                write!(f, "Staff holds permissions which you do not have")
+            }
+            AppError::StaffNotDisabled => write!(f, "Account is not disabled"),
+            AppError::ValidationFailed(errors) => write!(
+                f,
+                "Validation failed. {}",
+                errors
+                    .iter()
+                    .map(|error| error.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join(". ")
+            ),
         }
     }
 }
@@ -124,8 +238,42 @@ pub struct Details {
     pub details: String,
 }
 
+//? Machine readable description of one rejected field
+//? Code is stable, message is for humans
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+    pub limit: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct ValidationDetails {
+    pub details: String,
+    pub errors: Vec<FieldError>,
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
+        if let AppError::ValidationFailed(ref errors) = self {
+            let response = (
+                Into::<StatusCode>::into(&self),
+                Json(ValidationDetails {
+                    details: format!("{:?}", self),
+                    errors: errors.clone(),
+                }),
+            );
+            tracing::error!(cause = response.1.details, "Response with error!");
+            return response.into_response();
+        }
+
+        let retry_after = match self {
+            AppError::TooManyRequests(retry_after) => Some(retry_after),
+            AppError::TooManyOpenOrders { retry_after, .. } => Some(retry_after),
+            _ => None,
+        };
+
         let response = (
             Into::<StatusCode>::into(&self),
             Json(Details {
@@ -133,7 +281,13 @@ impl IntoResponse for AppError {
             }),
         );
         tracing::error!(cause = response.1.details, "Response with error!");
-        response.into_response()
+
+        match retry_after {
+            Some(retry_after) => {
+                ([(header::RETRY_AFTER, retry_after.to_string())], response).into_response()
+            }
+            None => response.into_response(),
+        }
     }
 }
 
@@ -163,7 +317,7 @@ impl From<&AppError> for StatusCode {
             AppError::EmptyCredentials => StatusCode::BAD_REQUEST,
             AppError::LoginOccupied => StatusCode::CONFLICT,
             AppError::AdminNotFound => StatusCode::NOT_FOUND,
-            AppError::ModeratorIsAdmin => StatusCode::FORBIDDEN,
+            AppError::StaffCanNotHandleOrders => StatusCode::FORBIDDEN,
             AppError::ModeratorAlreadyAssigned => StatusCode::CONFLICT,
             AppError::ModeratorNotAssigned => StatusCode::CONFLICT,
             AppError::OrderIsCompletedOrCancelled => StatusCode::CONFLICT,
@@ -176,6 +330,32 @@ impl From<&AppError> for StatusCode {
             AppError::DbErr(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::RequisitesWereNotFound => StatusCode::NOT_FOUND,
             AppError::ChatWasNotFound => StatusCode::NOT_FOUND,
+            AppError::OrderTransitionForbidden => StatusCode::FORBIDDEN,
+            AppError::QuoteExpired => StatusCode::GONE,
+            AppError::QuoteInvalid => StatusCode::BAD_REQUEST,
+            AppError::QuoteUsed => StatusCode::CONFLICT,
+            AppError::NoPendingRate => StatusCode::CONFLICT,
+            AppError::OrderStatusChanged => StatusCode::CONFLICT,
+            AppError::ValidationFailed(_) => StatusCode::BAD_REQUEST,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::TooManyOpenOrders { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::UserBlacklisted { .. } => StatusCode::FORBIDDEN,
+            AppError::OrderNotSucceeded => StatusCode::BAD_REQUEST,
+            AppError::ReviewAlreadyExists => StatusCode::CONFLICT,
+            AppError::ReviewEditWindowClosed => StatusCode::FORBIDDEN,
+            AppError::InvalidCursor => StatusCode::BAD_REQUEST,
+            AppError::LastStaffManager => StatusCode::CONFLICT,
+            AppError::StaffDisabled => StatusCode::FORBIDDEN,
+            AppError::PasswordResetRequired => StatusCode::FORBIDDEN,
+            AppError::ResetTokenExpired => StatusCode::GONE,
+            AppError::SessionRevoked => StatusCode::UNAUTHORIZED,
+            AppError::TwoFactorAlreadyEnabled => StatusCode::CONFLICT,
+            AppError::TwoFactorNotEnrolled => StatusCode::CONFLICT,
+            AppError::BadTwoFactorCode => StatusCode::UNAUTHORIZED,
+            AppError::TwoFactorRequired => StatusCode::FORBIDDEN,
+            AppError::LoginChallengeExpired => StatusCode::UNAUTHORIZED,
+            AppError::StaffOutranksManager => StatusCode::FORBIDDEN,
+            AppError::StaffNotDisabled => StatusCode::CONFLICT,
         }
     }
 }
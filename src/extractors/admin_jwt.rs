@@ -1,8 +1,12 @@
-use std::sync::Arc;
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
 
 use crate::{
     errors::AppError,
-    services::auth::{JwtCheckParams, Service as AuthService},
+    services::{
+        admin::{moderators::Service as StaffService, permissions::Service as PermissionsService},
+        auth::{JwtCheckParams, Service as AuthService},
+        sessions::{Audience, Service as SessionService, Subject},
+    },
     state::AppState,
 };
 use axum::{
@@ -12,14 +16,17 @@ use axum::{
 };
 use entity::{
     admin::{Entity as AdminEntity, Model as AdminModel},
-    sea_orm_active_enums::Role,
+    sea_orm_active_enums::Permission,
 };
 use sea_orm::prelude::*;
 
-pub struct AdminAuthJWT(pub AdminModel);
+//? Any staff account whatever its permissions are
+//? Used for routes every staff member needs such as their own profile
+#[derive(Debug)]
+pub struct StaffAuthJWT(pub AdminModel);
 
 #[async_trait]
-impl<S> FromRequestParts<S> for AdminAuthJWT
+impl<S> FromRequestParts<S> for StaffAuthJWT
 where
     Arc<AppState>: FromRef<S>,
     S: Send + Sync,
@@ -30,47 +37,69 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let app_state = Arc::from_ref(state);
 
-        let auth_header_value = parts
-            .headers
-            .get("X-AM-Authorization") // Admin-Moderator auth in another header
-            .ok_or(AppError::AuthorizationHeaderMissing)?
-            .to_str()
-            .map_err(|_| AppError::AuthorizationHeaderBadChars)?;
-
-        let token = match auth_header_value.split_once(' ') {
-            Some(("Bearer", contents)) => Ok(contents.to_string()),
-            _ => Err(AppError::AuthorizationHeaderBadSchema),
-        }?;
-
-        let params = JwtCheckParams {
-            token,
-            secret: app_state.configuration().jwt_secret(),
-        };
-
-        let claims = match AuthService::check(params) {
-            Ok(claims) => Ok(claims),
-            Err(cause) => Err(AppError::JwtError(Box::new(cause))),
-        }?;
-
-        match AdminEntity::find_by_id(claims.sub)
-            .one(app_state.database_connection())
-            .await
-        {
-            Ok(None) => Err(AppError::Unauthorized),
-            Ok(Some(admin)) if admin.role == Role::Moderator => Err(AppError::Forbidden),
-            Ok(Some(admin)) => Ok(Self(admin)),
-            Err(cause) => Err(AppError::InternalServerError(Box::new(cause))),
-        }
+        Ok(Self(authenticate(parts, &app_state).await?))
     }
 }
 
-pub struct ModeratorAuthJWT(pub AdminModel);
+pub trait Guarded {
+    const PERMISSION: Permission;
+}
+
+pub struct ManageCurrency;
+
+impl Guarded for ManageCurrency {
+    const PERMISSION: Permission = Permission::ManageCurrency;
+}
+
+pub struct ManageReviews;
+
+impl Guarded for ManageReviews {
+    const PERMISSION: Permission = Permission::ManageReviews;
+}
+
+pub struct BlacklistUsers;
+
+impl Guarded for BlacklistUsers {
+    const PERMISSION: Permission = Permission::BlacklistUsers;
+}
+
+pub struct ViewReports;
+
+impl Guarded for ViewReports {
+    const PERMISSION: Permission = Permission::ViewReports;
+}
+
+pub struct ManageStaff;
+
+impl Guarded for ManageStaff {
+    const PERMISSION: Permission = Permission::ManageStaff;
+}
+
+pub struct HandleOrders;
+
+impl Guarded for HandleOrders {
+    const PERMISSION: Permission = Permission::HandleOrders;
+}
+
+//? Same as StaffAuthJWT but also rejects staff which hold the permission
+//? neither through their role nor through an individual grant
+pub struct Permitted<P: Guarded>(pub AdminModel, pub PhantomData<P>);
+
+impl<P: Guarded> Debug for Permitted<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Permitted")
+            .field(&self.0)
+            .field(&P::PERMISSION)
+            .finish()
+    }
+}
 
 #[async_trait]
-impl<S> FromRequestParts<S> for ModeratorAuthJWT
+impl<S, P> FromRequestParts<S> for Permitted<P>
 where
     Arc<AppState>: FromRef<S>,
     S: Send + Sync,
+    P: Guarded,
 {
     type Rejection = AppError;
 
@@ -78,36 +107,70 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let app_state = Arc::from_ref(state);
 
-        let auth_header_value = parts
-            .headers
-            .get("X-AM-Authorization")
-            // Admin-Moderator auth token sits in another header from users
-            .ok_or(AppError::AuthorizationHeaderMissing)?
-            .to_str()
-            .map_err(|_| AppError::AuthorizationHeaderBadChars)?;
-
-        let token = match auth_header_value.split_once(' ') {
-            Some(("Bearer", contents)) => Ok(contents.to_string()),
-            _ => Err(AppError::AuthorizationHeaderBadSchema),
-        }?;
-
-        let params = JwtCheckParams {
-            token,
-            secret: app_state.configuration().jwt_secret(),
-        };
-
-        let claims = match AuthService::check(params) {
-            Ok(claims) => Ok(claims),
-            Err(cause) => Err(Into::<AppError>::into(cause)),
-        }?;
-
-        match AdminEntity::find_by_id(claims.sub)
-            .one(app_state.database_connection())
-            .await
+        let staff = authenticate(parts, &app_state).await?;
+
+        match PermissionsService::holds(&staff, P::PERMISSION, app_state.database_connection())
+            .await?
         {
-            Ok(Some(admin)) => Ok(Self(admin)),
-            Ok(None) => Err(AppError::Unauthorized),
-            Err(cause) => Err(AppError::InternalServerError(Box::new(cause))),
+            true => Ok(Self(staff, PhantomData)),
+            false => Err(AppError::Forbidden),
         }
     }
 }
+
+async fn authenticate(parts: &Parts, app_state: &AppState) -> Result<AdminModel, AppError> {
+    let auth_header_value = parts
+        .headers
+        .get("X-AM-Authorization")
+        // Admin-Moderator auth token sits in another header from users
+        .ok_or(AppError::AuthorizationHeaderMissing)?
+        .to_str()
+        .map_err(|_| AppError::AuthorizationHeaderBadChars)?;
+
+    let token = match auth_header_value.split_once(' ') {
+        Some(("Bearer", contents)) => Ok(contents.to_string()),
+        _ => Err(AppError::AuthorizationHeaderBadSchema),
+    }?;
+
+    staff(token, app_state).await
+}
+
+//? Shared with the chat websocket which passes the token in the query
+//? Disabled accounts and revoked sessions are rejected
+//? even if their token has not expired yet
+pub async fn staff(token: String, app_state: &AppState) -> Result<AdminModel, AppError> {
+    let params = JwtCheckParams {
+        token,
+        audience: Audience::Staff,
+        keys: app_state.keys(),
+    };
+
+    let claims = match AuthService::check(params) {
+        Ok(claims) => Ok(claims),
+        Err(cause) => Err(Into::<AppError>::into(cause)),
+    }?;
+
+    if !SessionService::is_active(
+        claims.sid,
+        Subject::Staff(claims.sub),
+        app_state.database_connection(),
+    )
+    .await?
+    {
+        return Err(AppError::SessionRevoked);
+    }
+
+    let staff = match AdminEntity::find_by_id(claims.sub)
+        .one(app_state.database_connection())
+        .await
+    {
+        Ok(Some(admin)) if admin.disabled_at.is_some() => Err(AppError::StaffDisabled),
+        Ok(Some(admin)) => Ok(admin),
+        Ok(None) => Err(AppError::Unauthorized),
+        Err(cause) => Err(AppError::InternalServerError(Box::new(cause))),
+    }?;
+
+    StaffService::touch(&staff, app_state.database_connection()).await?;
+
+    Ok(staff)
+}
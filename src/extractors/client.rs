@@ -0,0 +1,90 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use entity::sea_orm_active_enums::LoginSource;
+use sha2::{Digest, Sha256};
+
+use crate::{errors::AppError, state::AppState};
+
+//? Telegram bot proves itself with the configured key, user agent can be anything
+const BOT_API_KEY_HEADER: &str = "X-Bot-Key";
+
+//? Digests have the same length whatever was sent so comparing them leaks nothing useful
+fn is_bot_api_key(sent: &str, expected: &str) -> bool {
+    Sha256::digest(sent.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
+
+//? Who is on the other side of the request, used for staff login audit
+#[derive(Debug)]
+pub struct Client {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub source: LoginSource,
+}
+
+impl Client {
+    //? Every bot login shares the bot address, so only its per login counter applies
+    pub fn lockout_ip(&self) -> Option<&str> {
+        match self.source {
+            LoginSource::Bot => None,
+            LoginSource::Web => self.ip.as_deref(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Client
+where
+    Arc<AppState>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = Arc::from_ref(state);
+
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        //? Proxy appends the address it saw, everything before it is sent by the client
+        let forwarded = match app_state.configuration().trust_forwarded_for() {
+            true => header("X-Forwarded-For")
+                .and_then(|value| value.rsplit(',').next())
+                .map(|ip| ip.trim().to_owned()),
+            false => None,
+        };
+
+        let ip = forwarded.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        });
+
+        let source = match (
+            header(BOT_API_KEY_HEADER),
+            app_state.configuration().bot_api_key(),
+        ) {
+            (Some(sent), Some(expected)) if is_bot_api_key(sent, expected) => LoginSource::Bot,
+            _ => LoginSource::Web,
+        };
+
+        let user_agent = header("User-Agent");
+
+        Ok(Self {
+            ip,
+            user_agent: user_agent.map(ToOwned::to_owned),
+            source,
+        })
+    }
+}
@@ -1,2 +1,5 @@
 pub mod admin_jwt;
+pub mod client;
+pub mod pagination;
+pub mod rate_limit;
 pub mod user_jwt;
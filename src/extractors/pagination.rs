@@ -0,0 +1,82 @@
+use crate::{
+    errors::{AppError, Details},
+    services::pagination::{Cursor, Direction, PageRequest},
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_LIMIT: u64 = 20;
+const MAX_LIMIT: u64 = 100;
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl From<SortDirection> for Direction {
+    fn from(value: SortDirection) -> Self {
+        match value {
+            SortDirection::Asc => Direction::Asc,
+            SortDirection::Desc => Direction::Desc,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    //? `next_cursor` of the previous page, first page if not set
+    cursor: Option<String>,
+    //? 20 by default, at most 100
+    limit: Option<u64>,
+    //? Descending by default, ascending for queues
+    direction: Option<SortDirection>,
+}
+
+//? Shared by every list endpoint
+//? Filters and sorting column are read separately by the handler
+#[derive(Debug)]
+pub struct Pagination(pub PageRequest);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Pagination
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PageQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| {
+                AppError::BadRequest(Details {
+                    details: rejection.body_text(),
+                })
+            })?;
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(AppError::BadRequest(Details {
+                details: format!("`limit` must be between 1 and {}", MAX_LIMIT),
+            }));
+        }
+
+        let cursor = match query.cursor {
+            Some(token) => Some(Cursor::decode(&token)?),
+            None => None,
+        };
+
+        Ok(Self(PageRequest {
+            cursor,
+            limit,
+            direction: query.direction.map(Into::into).unwrap_or_default(),
+            direction_requested: query.direction.is_some(),
+        }))
+    }
+}
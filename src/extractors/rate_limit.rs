@@ -0,0 +1,94 @@
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+
+use crate::{
+    config::Configuration,
+    errors::AppError,
+    extractors::user_jwt::{authenticate, Authenticated},
+    services::rate_limit::{Hit, HitParameters, RateLimit, Service as RateLimitService},
+    state::AppState,
+};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+//? Every group has its own counters and limits taken from configuration
+pub trait RateLimitGroup {
+    const NAME: &'static str;
+
+    fn limit(configuration: &Configuration) -> RateLimit;
+}
+
+pub struct OrderCreation;
+
+impl RateLimitGroup for OrderCreation {
+    const NAME: &'static str = "order-creation";
+
+    fn limit(configuration: &Configuration) -> RateLimit {
+        RateLimit {
+            requests: configuration.order_rate_limit(),
+            window_seconds: configuration.order_rate_limit_window_seconds(),
+        }
+    }
+}
+
+pub struct QuoteIssuing;
+
+impl RateLimitGroup for QuoteIssuing {
+    const NAME: &'static str = "quote-issuing";
+
+    fn limit(configuration: &Configuration) -> RateLimit {
+        RateLimit {
+            requests: configuration.quote_rate_limit(),
+            window_seconds: configuration.quote_rate_limit_window_seconds(),
+        }
+    }
+}
+
+//? Counts requests per steam id, placed after AuthJWT or AllowedTo it reuses their result
+//? Otherwise it authenticates the user itself so requests are never let through uncounted
+pub struct RateLimited<G: RateLimitGroup>(PhantomData<G>);
+
+impl<G: RateLimitGroup> Debug for RateLimited<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RateLimited").field(&G::NAME).finish()
+    }
+}
+
+#[async_trait]
+impl<S, G> FromRequestParts<S> for RateLimited<G>
+where
+    Arc<AppState>: FromRef<S>,
+    S: Send + Sync,
+    G: RateLimitGroup,
+{
+    type Rejection = AppError;
+
+    #[tracing::instrument(skip(parts, state), fields(group = G::NAME))]
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = Arc::from_ref(state);
+
+        let subject = match parts.extensions.get::<Authenticated>() {
+            Some(authenticated) => authenticated.steam_id,
+            None => authenticate(parts, &app_state).await?.0.steam_id,
+        }
+        .to_string();
+
+        let parameters = HitParameters {
+            group: G::NAME,
+            subject,
+            limit: G::limit(app_state.configuration()),
+        };
+
+        match RateLimitService::hit(parameters, app_state.redis_client()).await {
+            Ok(Hit::Allowed) => Ok(Self(PhantomData)),
+            Ok(Hit::Limited { retry_after }) => Err(AppError::TooManyRequests(retry_after)),
+            Err(cause) => {
+                //? Limiter must not take the service down with redis
+                tracing::warn!(%cause, "Failed to check rate limit!");
+                Ok(Self(PhantomData))
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use std::sync::Arc;
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
 
 use crate::{
     errors::AppError,
-    services::auth::{JwtCheckParams, Service as AuthService},
+    services::{
+        admin::blacklist::{Capability, Service as BlacklistService},
+        auth::{JwtCheckParams, Service as AuthService},
+        sessions::{Audience, Service as SessionService, Subject},
+    },
     state::AppState,
 };
 use axum::{
@@ -10,9 +14,20 @@ use axum::{
     extract::{FromRef, FromRequestParts},
     http::request::Parts,
 };
-use entity::user::{Entity as UserEntity, Model as UserModel};
+use entity::{
+    blacklisted::Model as BlacklistedModel,
+    user::{Entity as UserEntity, Model as UserModel},
+};
 use sea_orm::EntityTrait;
 
+//? Left in request extensions by the user extractors
+//? so the ones placed after them do not decode the token again
+#[derive(Debug, Clone, Copy)]
+pub struct Authenticated {
+    pub steam_id: i64,
+}
+
+//? Rejects users with a ban which blocks everything
 pub struct AuthJWT(pub UserModel);
 
 #[async_trait]
@@ -27,35 +42,140 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let app_state = Arc::from_ref(state);
 
-        let auth_header_value = parts
-            .headers
-            .get("Authorization")
-            .ok_or(AppError::AuthorizationHeaderMissing)?
-            .to_str()
-            .map_err(|_| AppError::AuthorizationHeaderBadChars)?;
-
-        let token = match auth_header_value.split_once(' ') {
-            Some(("Bearer", contents)) => Ok(contents.to_string()),
-            _ => Err(AppError::AuthorizationHeaderBadSchema),
-        }?;
-
-        let params = JwtCheckParams {
-            token,
-            secret: app_state.configuration().jwt_secret(),
-        };
-
-        let claims = match AuthService::check(params) {
-            Ok(claims) => Ok(claims),
-            Err(cause) => Err(AppError::JwtError(Box::new(cause))),
-        }?;
-
-        match UserEntity::find_by_id(claims.sub)
-            .one(app_state.database_connection())
-            .await
-        {
-            Ok(Some(user)) => Ok(Self(user)),
-            Ok(None) => Err(AppError::Unauthorized),
-            Err(cause) => Err(AppError::InternalServerError(Box::new(cause))),
+        let (user, ban) = authenticate(parts, &app_state).await?;
+
+        match ban {
+            Some(ban) if BlacklistService::is_full(&ban) => Err(rejection(ban)),
+            _ => Ok(Self(user)),
         }
     }
 }
+
+pub trait Restricted {
+    const CAPABILITY: Capability;
+}
+
+pub struct Ordering;
+
+impl Restricted for Ordering {
+    const CAPABILITY: Capability = Capability::Ordering;
+}
+
+pub struct Chatting;
+
+impl Restricted for Chatting {
+    const CAPABILITY: Capability = Capability::Chatting;
+}
+
+pub struct Reviewing;
+
+impl Restricted for Reviewing {
+    const CAPABILITY: Capability = Capability::Reviewing;
+}
+
+//? Same as AuthJWT but also rejects users
+//? whose ban blocks the capability
+pub struct AllowedTo<C: Restricted>(pub UserModel, pub PhantomData<C>);
+
+impl<C: Restricted> Debug for AllowedTo<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AllowedTo")
+            .field(&self.0)
+            .field(&C::CAPABILITY)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<S, C> FromRequestParts<S> for AllowedTo<C>
+where
+    Arc<AppState>: FromRef<S>,
+    S: Send + Sync,
+    C: Restricted,
+{
+    type Rejection = AppError;
+
+    #[tracing::instrument(skip(parts, state))]
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state = Arc::from_ref(state);
+
+        let (user, ban) = authenticate(parts, &app_state).await?;
+
+        match ban {
+            Some(ban) if C::CAPABILITY.is_blocked_by(&ban) => Err(rejection(ban)),
+            _ => Ok(Self(user, PhantomData)),
+        }
+    }
+}
+
+fn rejection(ban: BlacklistedModel) -> AppError {
+    AppError::UserBlacklisted {
+        reason: ban.reason,
+        expires_at: ban.expires_at,
+    }
+}
+
+pub(crate) async fn authenticate(
+    parts: &mut Parts,
+    app_state: &AppState,
+) -> Result<(UserModel, Option<BlacklistedModel>), AppError> {
+    let auth_header_value = parts
+        .headers
+        .get("Authorization")
+        .ok_or(AppError::AuthorizationHeaderMissing)?
+        .to_str()
+        .map_err(|_| AppError::AuthorizationHeaderBadChars)?;
+
+    let token = match auth_header_value.split_once(' ') {
+        Some(("Bearer", contents)) => Ok(contents.to_string()),
+        _ => Err(AppError::AuthorizationHeaderBadSchema),
+    }?;
+
+    let (user, ban) = user(token, app_state).await?;
+    parts.extensions.insert(Authenticated {
+        steam_id: user.steam_id,
+    });
+
+    Ok((user, ban))
+}
+
+//? Shared with the chat websocket which passes the token in the query
+//? Tokens of revoked sessions are rejected even if they have not expired yet
+pub async fn user(
+    token: String,
+    app_state: &AppState,
+) -> Result<(UserModel, Option<BlacklistedModel>), AppError> {
+    let params = JwtCheckParams {
+        token,
+        audience: Audience::User,
+        keys: app_state.keys(),
+    };
+
+    let claims = match AuthService::check(params) {
+        Ok(claims) => Ok(claims),
+        Err(cause) => Err(AppError::JwtError(Box::new(cause))),
+    }?;
+
+    if !SessionService::is_active(
+        claims.sid,
+        Subject::User(claims.sub),
+        app_state.database_connection(),
+    )
+    .await?
+    {
+        return Err(AppError::SessionRevoked);
+    }
+
+    let user = match UserEntity::find_by_id(claims.sub)
+        .one(app_state.database_connection())
+        .await
+    {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(AppError::Unauthorized),
+        Err(cause) => Err(AppError::InternalServerError(Box::new(cause))),
+    }?;
+
+    let ban = BlacklistService::active(user.steam_id, app_state.database_connection()).await?;
+
+    Ok((user, ban))
+}
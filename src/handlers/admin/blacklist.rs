@@ -1,6 +1,12 @@
 use crate::{
-    errors::AppError, extractors::admin_jwt::AdminAuthJWT,
-    services::admin::blacklist::Service as BlacklistService, state::AppState,
+    errors::{AppError, Details},
+    extractors::{
+        admin_jwt::{BlacklistUsers, Permitted},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::pagination::Paged,
+    services::admin::blacklist::{BlacklistUserParameters, Service as BlacklistService},
+    state::AppState,
 };
 use axum::{
     extract::State,
@@ -8,6 +14,8 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::{NaiveDateTime, Utc};
+use entity::blacklisted::Model as BlacklistedModel;
 use sea_orm::TransactionTrait;
 use std::sync::Arc;
 use utoipa::ToSchema;
@@ -20,17 +28,17 @@ use utoipa::ToSchema;
         (status = 204, description = "User was successfully blacklisted"),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
-        (status = 409, description = "User has already been blacklisted",  body = Details),
+        (status = 409, description = "User is already blacklisted",        body = Details),
         (status = 404, description = "User was not found",                 body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["blacklist_users"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn blacklist_user(
-    AdminAuthJWT(admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<BlacklistUsers>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<BlacklistUserRequest>,
 ) -> Response {
@@ -40,9 +48,36 @@ pub async fn blacklist_user(
             return Into::<AppError>::into(error).into_response();
         }
     };
+
+    if matches!(payload.expires_at, Some(expires_at) if expires_at <= Utc::now().naive_local()) {
+        return AppError::BadRequest(Details {
+            details: String::from("Ban expiration must be in the future"),
+        })
+        .into_response();
+    }
+
+    //? Everything is blocked unless admin has chosen otherwise
+    let blocks = payload.blocks.unwrap_or_else(|| {
+        vec![
+            BlockedCapability::Ordering,
+            BlockedCapability::Chatting,
+            BlockedCapability::Reviewing,
+        ]
+    });
+
+    let parameters = BlacklistUserParameters {
+        steam_id,
+        reason: payload.reason,
+        expires_at: payload.expires_at,
+        blocks_ordering: blocks.contains(&BlockedCapability::Ordering),
+        blocks_chatting: blocks.contains(&BlockedCapability::Chatting),
+        blocks_reviewing: blocks.contains(&BlockedCapability::Reviewing),
+        admin_id: Some(admin.id),
+    };
+
     match app_state.database_connection().begin().await {
-        Ok(transaction) => match BlacklistService::blacklist_user(steam_id, &transaction).await {
-            Ok(()) => {
+        Ok(transaction) => match BlacklistService::blacklist_user(parameters, &transaction).await {
+            Ok(_) => {
                 if let Err(cause) = transaction.commit().await {
                     return AppError::InternalServerError(Box::new(cause)).into_response();
                 }
@@ -67,12 +102,12 @@ pub async fn blacklist_user(
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["blacklist_users"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn unblacklist_user(
-    AdminAuthJWT(admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<BlacklistUsers>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<UnblacklistUserRequest>,
 ) -> Response {
@@ -100,29 +135,111 @@ pub async fn unblacklist_user(
 #[utoipa::path(
     get,
     path = "/api/admin/blacklist",
+    params(PageQuery),
     responses(
-        (status = 200, description = "Blacklist was successfully retrieved", body = [String]),
+        (status = 200, description = "Blacklist was successfully retrieved", body = PagedSteamIds),
+        (status = 400, description = "Bad request",                          body = Details),
         (status = 401, description = "Unauthorized",                         body = Details),
         (status = 500, description = "Internal Server Error",                body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["blacklist_users"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn full_blacklist(
-    AdminAuthJWT(admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<BlacklistUsers>,
     State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
 ) -> Response {
-    match BlacklistService::all(app_state.database_connection()).await {
-        Ok(ids) => Json(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()).into_response(),
+    match BlacklistService::all(&page, app_state.database_connection()).await {
+        Ok(bans) => Json(Into::<Paged<String>>::into(
+            bans.map(|ban| ban.steam_id.to_string()),
+        ))
+        .into_response(),
         Err(error) => Into::<AppError>::into(error).into_response(),
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockedCapability {
+    Ordering,
+    Chatting,
+    Reviewing,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
 pub struct BlacklistUserRequest {
     steam_id: String,
+    reason: Option<String>,
+    //? Permanent ban if not set
+    expires_at: Option<NaiveDateTime>,
+    //? Everything is blocked if not set
+    //? Blocking everything also forbids logging in
+    blocks: Option<Vec<BlockedCapability>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct BlacklistEntry {
+    steam_id: String,
+    reason: Option<String>,
+    expires_at: Option<NaiveDateTime>,
+    blocks: Vec<BlockedCapability>,
+    admin_id: Option<String>,
+    created_at: NaiveDateTime,
+    active: bool,
+}
+
+impl From<BlacklistedModel> for BlacklistEntry {
+    fn from(value: BlacklistedModel) -> Self {
+        let blocks = [
+            (value.blocks_ordering, BlockedCapability::Ordering),
+            (value.blocks_chatting, BlockedCapability::Chatting),
+            (value.blocks_reviewing, BlockedCapability::Reviewing),
+        ]
+        .into_iter()
+        .filter_map(|(blocked, capability)| blocked.then_some(capability))
+        .collect();
+
+        Self {
+            steam_id: value.steam_id.to_string(),
+            active: value
+                .expires_at
+                .is_none_or(|expires_at| expires_at > Utc::now().naive_local()),
+            reason: value.reason,
+            expires_at: value.expires_at,
+            blocks,
+            admin_id: value.admin_id.map(|id| id.to_string()),
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/blacklist/entries",
+    params(PageQuery),
+    responses(
+        (status = 200, description = "Blacklist entries were successfully retrieved", body = PagedBlacklistEntries),
+        (status = 400, description = "Bad request",                                   body = Details),
+        (status = 401, description = "Unauthorized",                                  body = Details),
+        (status = 500, description = "Internal Server Error",                         body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["blacklist_users"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn blacklist_entries(
+    Permitted(_admin, _): Permitted<BlacklistUsers>,
+    State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
+) -> Response {
+    match BlacklistService::entries(&page, app_state.database_connection()).await {
+        Ok(entries) => Json(Into::<Paged<BlacklistEntry>>::into(entries)).into_response(),
+        Err(error) => Into::<AppError>::into(error).into_response(),
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
@@ -1,8 +1,13 @@
 use crate::{
     errors::AppError,
-    extractors::admin_jwt::AdminAuthJWT,
+    extractors::{
+        admin_jwt::{ManageCurrency, Permitted},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::pagination::Paged,
     services::currency::{
-        CreateCurrencyRateParameters, Service as CurrencyService, SetCurrencyRateParameters,
+        CreateCurrencyRateParameters, Service as CurrencyService, SetCurrencyLimitsParameters,
+        SetCurrencyRateParameters, SetCurrencySettingsParameters,
     },
     state::AppState,
 };
@@ -12,7 +17,11 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use entity::currency_rate::Model as RateModel;
+use chrono::NaiveDateTime;
+use entity::{
+    currency_rate::Model as RateModel, currency_rate_history::Model as RateHistoryModel,
+    sea_orm_active_enums::RateSource,
+};
 use sea_orm::{prelude::Decimal, TransactionTrait};
 use std::sync::Arc;
 use utoipa::ToSchema;
@@ -29,6 +38,17 @@ pub struct Currency {
     symbol: String,
     #[schema(value_type = String)]
     rate: Decimal,
+    //? Provider has not updated this rate for too long
+    stale: bool,
+    updated_at: NaiveDateTime,
+    #[schema(value_type = Option<String>)]
+    min_amount: Option<Decimal>,
+    #[schema(value_type = Option<String>)]
+    max_amount: Option<Decimal>,
+    //? Maximum number of digits after the point in order amount
+    scale: Option<i32>,
+    #[schema(value_type = Option<String>)]
+    daily_limit: Option<Decimal>,
 }
 
 impl From<RateModel> for Currency {
@@ -37,6 +57,113 @@ impl From<RateModel> for Currency {
             id: value.id.to_string(),
             symbol: value.symbol,
             rate: value.rate,
+            stale: value.stale,
+            updated_at: value.updated_at,
+            min_amount: value.min_amount,
+            max_amount: value.max_amount,
+            scale: value.scale,
+            daily_limit: value.daily_limit,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema)]
+pub struct CurrencySettings {
+    id: String,
+    symbol: String,
+    #[schema(value_type = String)]
+    rate: Decimal,
+    auto_update: bool,
+    //? Percents added on top of provider rate
+    #[schema(value_type = String)]
+    markup: Decimal,
+    //? Percents of the current rate
+    #[schema(value_type = Option<String>)]
+    max_jump: Option<Decimal>,
+    #[schema(value_type = Option<String>)]
+    pending_rate: Option<Decimal>,
+    manual_override: bool,
+    stale: bool,
+    updated_at: NaiveDateTime,
+}
+
+impl From<RateModel> for CurrencySettings {
+    fn from(value: RateModel) -> Self {
+        Self {
+            id: value.id.to_string(),
+            symbol: value.symbol,
+            rate: value.rate,
+            auto_update: value.auto_update,
+            markup: value.markup,
+            max_jump: value.max_jump,
+            pending_rate: value.pending_rate,
+            manual_override: value.manual_override,
+            stale: value.stale,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema)]
+pub struct CurrencySettingsRequest {
+    auto_update: bool,
+    #[schema(value_type = String)]
+    markup: Decimal,
+    #[schema(value_type = Option<String>)]
+    max_jump: Option<Decimal>,
+    manual_override: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema)]
+pub struct CurrencyLimitsRequest {
+    #[schema(value_type = Option<String>)]
+    min_amount: Option<Decimal>,
+    #[schema(value_type = Option<String>)]
+    max_amount: Option<Decimal>,
+    scale: Option<i32>,
+    //? Sum of amounts of one user during the last 24 hours
+    #[schema(value_type = Option<String>)]
+    daily_limit: Option<Decimal>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CurrencyRateSource {
+    Manual,
+    Provider,
+    Approval,
+}
+
+impl From<RateSource> for CurrencyRateSource {
+    fn from(value: RateSource) -> Self {
+        match value {
+            RateSource::Manual => Self::Manual,
+            RateSource::Provider => Self::Provider,
+            RateSource::Approval => Self::Approval,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema)]
+pub struct CurrencyRateChange {
+    id: String,
+    symbol: String,
+    #[schema(value_type = String)]
+    rate: Decimal,
+    admin_id: Option<String>,
+    source: CurrencyRateSource,
+    created_at: NaiveDateTime,
+}
+
+impl From<RateHistoryModel> for CurrencyRateChange {
+    fn from(value: RateHistoryModel) -> Self {
+        Self {
+            id: value.id.to_string(),
+            symbol: value.symbol,
+            rate: value.rate,
+            admin_id: value.admin_id.map(|id| id.to_string()),
+            source: value.source.into(),
+            created_at: value.created_at,
         }
     }
 }
@@ -51,12 +178,12 @@ impl From<RateModel> for Currency {
         (status = 500, description = "Internal Server Error", body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_currency"])
     )
 )]
 pub async fn create_currency(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<ManageCurrency>,
     Json(payload): Json<CreateCurrencyRequest>,
 ) -> Response {
     match app_state.database_connection().begin().await {
@@ -64,6 +191,7 @@ pub async fn create_currency(
             let parameters = CreateCurrencyRateParameters {
                 symbol: payload.symbol,
                 rate: payload.rate,
+                admin_id: Some(admin.id),
             };
             let rate = match CurrencyService::create(parameters, &transaction).await {
                 Ok(rate) => rate,
@@ -93,12 +221,12 @@ pub async fn create_currency(
         ("id" = i64, Path, description ="Currency rate id")
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_currency"])
     )
 )]
 pub async fn delete_currency_rate_by_id(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(_admin, _): Permitted<ManageCurrency>,
     Path(id): Path<i64>,
 ) -> Response {
     match app_state.database_connection().begin().await {
@@ -137,12 +265,12 @@ pub struct SetRateRequest {
         ("id" = i64, Path, description ="Currency rate id")
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_currency"])
     )
 )]
 pub async fn set_currency_rate_by_id(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<ManageCurrency>,
     Path(id): Path<i64>,
     Json(payload): Json<SetRateRequest>,
 ) -> Response {
@@ -151,6 +279,7 @@ pub async fn set_currency_rate_by_id(
             let parameters = SetCurrencyRateParameters {
                 rate: payload.rate,
                 id,
+                admin_id: Some(admin.id),
             };
 
             if let Err(cause) = CurrencyService::set_rate(parameters, &transaction).await {
@@ -166,3 +295,217 @@ pub async fn set_currency_rate_by_id(
         Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/currency/{id}/history",
+    responses(
+        (status = 200, description = "Currency rate changes were successfully retrieved", body = PagedCurrencyRateChanges),
+        (status = 400, description = "Bad request", body = Details),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description ="Currency rate id"),
+        PageQuery
+    ),
+    security(
+        ("jwt_admin" = ["manage_currency"])
+    )
+)]
+pub async fn currency_rate_changes(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageCurrency>,
+    Path(id): Path<i64>,
+    Pagination(page): Pagination,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let rate = match CurrencyService::get(id, &transaction).await {
+                Ok(rate) => rate,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let changes = match CurrencyService::changes(&rate.symbol, &page, &transaction).await {
+                Ok(changes) => changes,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (
+                StatusCode::OK,
+                Json(Into::<Paged<CurrencyRateChange>>::into(changes)),
+            )
+                .into_response()
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/currency/{id}/settings",
+    responses(
+        (status = 200, description = "Currency settings were successfully retrieved", body = CurrencySettings),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description ="Currency rate id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_currency"])
+    )
+)]
+pub async fn currency_settings(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageCurrency>,
+    Path(id): Path<i64>,
+) -> Response {
+    match CurrencyService::get(id, app_state.database_connection()).await {
+        Ok(rate) => (StatusCode::OK, Json(Into::<CurrencySettings>::into(rate))).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/currency/{id}/settings",
+    request_body = CurrencySettingsRequest,
+    responses(
+        (status = 200, description = "Currency settings were successfully changed", body = CurrencySettings),
+        (status = 400, description = "Settings are invalid", body = ValidationDetails),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description ="Currency rate id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_currency"])
+    )
+)]
+pub async fn set_currency_settings(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageCurrency>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CurrencySettingsRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let parameters = SetCurrencySettingsParameters {
+                id,
+                auto_update: payload.auto_update,
+                markup: payload.markup,
+                max_jump: payload.max_jump,
+                manual_override: payload.manual_override,
+            };
+
+            let rate = match CurrencyService::set_settings(parameters, &transaction).await {
+                Ok(rate) => rate,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (StatusCode::OK, Json(Into::<CurrencySettings>::into(rate))).into_response()
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/currency/{id}/approve",
+    responses(
+        (status = 200, description = "Pending currency rate was successfully approved", body = CurrencySettings),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 409, description = "Currency has no pending rate", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description ="Currency rate id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_currency"])
+    )
+)]
+pub async fn approve_pending_rate(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(admin, _): Permitted<ManageCurrency>,
+    Path(id): Path<i64>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let rate = match CurrencyService::approve_pending(id, admin.id, &transaction).await {
+                Ok(rate) => rate,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (StatusCode::OK, Json(Into::<CurrencySettings>::into(rate))).into_response()
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/currency/{id}/limits",
+    request_body = CurrencyLimitsRequest,
+    responses(
+        (status = 200, description = "Currency limits were successfully changed", body = Currency),
+        (status = 400, description = "Limits are invalid", body = ValidationDetails),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description ="Currency rate id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_currency"])
+    )
+)]
+pub async fn set_currency_limits(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageCurrency>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CurrencyLimitsRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let parameters = SetCurrencyLimitsParameters {
+                id,
+                min_amount: payload.min_amount,
+                max_amount: payload.max_amount,
+                scale: payload.scale,
+                daily_limit: payload.daily_limit,
+            };
+
+            let rate = match CurrencyService::set_limits(parameters, &transaction).await {
+                Ok(rate) => rate,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (StatusCode::OK, Json(Into::<Currency>::into(rate))).into_response()
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
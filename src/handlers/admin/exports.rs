@@ -0,0 +1,158 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::AppError,
+    extractors::admin_jwt::{Permitted, ViewReports},
+    handlers::admin::orders::OrderSearchQuery,
+    services::exports::{Export, Service as ExportsService, UserExportFilter},
+    state::AppState,
+};
+
+const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    //? Csv by default
+    format: Option<ExportFormat>,
+}
+
+//? Registration range is inclusive
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserExportQuery {
+    steam_id: Option<i64>,
+    registered_from: Option<NaiveDateTime>,
+    registered_to: Option<NaiveDateTime>,
+}
+
+//? Csv is streamed batch by batch
+//? Xlsx is assembled in a temporary file first and streamed from it
+async fn export(app_state: &AppState, export: Export, format: ExportFormat) -> Response {
+    let name = export.name();
+
+    match format {
+        ExportFormat::Csv => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    String::from("text/csv; charset=utf-8"),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.csv\"", name),
+                ),
+            ],
+            Body::from_stream(ExportsService::csv(
+                export,
+                app_state.database_connection().clone(),
+            )),
+        )
+            .into_response(),
+        ExportFormat::Xlsx => {
+            match ExportsService::xlsx(export, app_state.database_connection()).await {
+                Ok(file) => (
+                    [
+                        (header::CONTENT_TYPE, String::from(XLSX_CONTENT_TYPE)),
+                        (
+                            header::CONTENT_DISPOSITION,
+                            format!("attachment; filename=\"{}.xlsx\"", name),
+                        ),
+                    ],
+                    Body::from_stream(ReaderStream::new(file)),
+                )
+                    .into_response(),
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/export/orders",
+    params(ExportQuery, OrderSearchQuery),
+    responses(
+        (status = 200, description = "Orders were successfully exported", content(
+            ("text/csv" = String),
+            ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" = Vec<u8>),
+        )),
+        (status = 400, description = "Bad request",           body = Details),
+        (status = 401, description = "Unauthorized",          body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["view_reports"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn export_orders(
+    Permitted(admin, _): Permitted<ViewReports>,
+    State(app_state): State<Arc<AppState>>,
+    Query(format): Query<ExportQuery>,
+    Query(search): Query<OrderSearchQuery>,
+) -> Response {
+    let (search, sort) = search.into_sorted_search();
+
+    export(
+        &app_state,
+        Export::Orders(search, sort),
+        format.format.unwrap_or_default(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/export/users",
+    params(ExportQuery, UserExportQuery),
+    responses(
+        (status = 200, description = "Users were successfully exported", content(
+            ("text/csv" = String),
+            ("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" = Vec<u8>),
+        )),
+        (status = 400, description = "Bad request",           body = Details),
+        (status = 401, description = "Unauthorized",          body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["view_reports"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn export_users(
+    Permitted(admin, _): Permitted<ViewReports>,
+    State(app_state): State<Arc<AppState>>,
+    Query(format): Query<ExportQuery>,
+    Query(filter): Query<UserExportQuery>,
+) -> Response {
+    let filter = UserExportFilter {
+        steam_id: filter.steam_id,
+        registered_from: filter.registered_from,
+        registered_to: filter.registered_to,
+    };
+
+    export(
+        &app_state,
+        Export::Users(filter),
+        format.format.unwrap_or_default(),
+    )
+    .await
+}
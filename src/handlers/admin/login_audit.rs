@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDateTime;
+use entity::{
+    sea_orm_active_enums::{LoginOutcome, LoginSource},
+    staff_login_audit::Model as StaffLoginAuditModel,
+};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::AppError,
+    extractors::{
+        admin_jwt::{ManageStaff, Permitted},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::pagination::Paged,
+    services::{
+        admin::login_audit::{
+            LoginAuditFilter, SearchLoginAuditParameters, Service as LoginAuditService,
+        },
+        pagination::PageRequest,
+    },
+    state::AppState,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StaffLoginOutcome {
+    Succeeded,
+    //? Password was accepted and a two-factor code was asked for
+    Challenged,
+    BadCredentials,
+    BadTwoFactorCode,
+    Disabled,
+    PasswordResetRequired,
+    LockedOut,
+}
+
+impl From<LoginOutcome> for StaffLoginOutcome {
+    fn from(value: LoginOutcome) -> Self {
+        match value {
+            LoginOutcome::Succeeded => StaffLoginOutcome::Succeeded,
+            LoginOutcome::Challenged => StaffLoginOutcome::Challenged,
+            LoginOutcome::BadCredentials => StaffLoginOutcome::BadCredentials,
+            LoginOutcome::BadTwoFactorCode => StaffLoginOutcome::BadTwoFactorCode,
+            LoginOutcome::Disabled => StaffLoginOutcome::Disabled,
+            LoginOutcome::PasswordResetRequired => StaffLoginOutcome::PasswordResetRequired,
+            LoginOutcome::LockedOut => StaffLoginOutcome::LockedOut,
+        }
+    }
+}
+
+impl From<StaffLoginOutcome> for LoginOutcome {
+    fn from(value: StaffLoginOutcome) -> Self {
+        match value {
+            StaffLoginOutcome::Succeeded => LoginOutcome::Succeeded,
+            StaffLoginOutcome::Challenged => LoginOutcome::Challenged,
+            StaffLoginOutcome::BadCredentials => LoginOutcome::BadCredentials,
+            StaffLoginOutcome::BadTwoFactorCode => LoginOutcome::BadTwoFactorCode,
+            StaffLoginOutcome::Disabled => LoginOutcome::Disabled,
+            StaffLoginOutcome::PasswordResetRequired => LoginOutcome::PasswordResetRequired,
+            StaffLoginOutcome::LockedOut => LoginOutcome::LockedOut,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StaffLoginSource {
+    Web,
+    Bot,
+}
+
+impl From<LoginSource> for StaffLoginSource {
+    fn from(value: LoginSource) -> Self {
+        match value {
+            LoginSource::Web => StaffLoginSource::Web,
+            LoginSource::Bot => StaffLoginSource::Bot,
+        }
+    }
+}
+
+impl From<StaffLoginSource> for LoginSource {
+    fn from(value: StaffLoginSource) -> Self {
+        match value {
+            StaffLoginSource::Web => LoginSource::Web,
+            StaffLoginSource::Bot => LoginSource::Bot,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct LoginAuditEntry {
+    id: String,
+    //? Empty when the login did not belong to any account
+    admin_id: Option<String>,
+    login: String,
+    succeeded: bool,
+    outcome: StaffLoginOutcome,
+    source: StaffLoginSource,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+impl From<StaffLoginAuditModel> for LoginAuditEntry {
+    fn from(value: StaffLoginAuditModel) -> Self {
+        Self {
+            id: value.id.to_string(),
+            admin_id: value.admin_id.map(|id| id.to_string()),
+            login: value.login,
+            succeeded: value.outcome == LoginOutcome::Succeeded,
+            outcome: value.outcome.into(),
+            source: value.source.into(),
+            ip: value.ip,
+            user_agent: value.user_agent,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LoginAuditQuery {
+    admin_id: Option<i64>,
+    login: Option<String>,
+    outcome: Option<StaffLoginOutcome>,
+    source: Option<StaffLoginSource>,
+    ip: Option<String>,
+    created_from: Option<NaiveDateTime>,
+    created_to: Option<NaiveDateTime>,
+}
+
+impl LoginAuditQuery {
+    fn into_parameters(self, page: PageRequest) -> SearchLoginAuditParameters {
+        SearchLoginAuditParameters {
+            filter: LoginAuditFilter {
+                admin_id: self.admin_id,
+                login: self.login,
+                outcome: self.outcome.map(Into::into),
+                source: self.source.map(Into::into),
+                ip: self.ip,
+                created_from: self.created_from,
+                created_to: self.created_to,
+            },
+            page,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/login-audit",
+    params(PageQuery, LoginAuditQuery),
+    responses(
+        (status = 200, description = "Staff login attempts were successfully retrieved", body = PagedLoginAuditEntries),
+        (status = 400, description = "Bad request",                                      body = Details),
+        (status = 401, description = "Unauthorized",                                     body = Details),
+        (status = 403, description = "Permission required",                              body = Details),
+        (status = 500, description = "Internal Server Error",                            body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn login_audit(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
+    Query(query): Query<LoginAuditQuery>,
+) -> Response {
+    match LoginAuditService::search(query.into_parameters(page), app_state.database_connection())
+        .await
+    {
+        Ok(entries) => Json(Into::<Paged<LoginAuditEntry>>::into(entries)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
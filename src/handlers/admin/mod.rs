@@ -1,14 +1,19 @@
 use crate::state::AppState;
-use axum::routing::{delete, get, patch, post};
+use axum::routing::{delete, get, patch, post, put};
 use std::sync::Arc;
 
 pub mod blacklist;
 pub mod currency;
+pub mod exports;
+pub mod login_audit;
 pub mod moderators;
 pub mod orders;
+pub mod permissions;
+pub mod reports;
 pub mod requisites;
 pub mod reviews;
 pub mod social;
+pub mod two_factor;
 pub mod users;
 
 pub fn router() -> axum::Router<Arc<AppState>> {
@@ -16,13 +21,26 @@ pub fn router() -> axum::Router<Arc<AppState>> {
         .route("/blacklist", get(blacklist::full_blacklist))
         .route("/blacklist", post(blacklist::blacklist_user))
         .route("/blacklist", delete(blacklist::unblacklist_user))
+        .route("/blacklist/entries", get(blacklist::blacklist_entries))
         .route("/review/video", post(reviews::add_video_review))
         .route("/review/video", delete(reviews::remove_video_review))
         .route("/review/video", patch(reviews::update_video_review))
         .route("/moderator", post(moderators::create_moderator))
-        .route("/moderator/:id", delete(moderators::delete_moderator))
+        .route("/moderator/:id", delete(moderators::disable_moderator))
+        .route("/moderator/:id/enable", patch(moderators::enable_moderator))
+        .route("/moderator/:id/login", patch(moderators::change_login))
+        .route(
+            "/moderator/:id/password-reset",
+            post(moderators::issue_password_reset),
+        )
+        .route(
+            "/moderator/:id/assignment",
+            patch(moderators::set_moderator_assignment),
+        )
+        .route("/moderator/shift", patch(moderators::set_shift))
         .route("/moderator", get(moderators::list_moderators))
         .route("/moderator/orders", get(moderators::list_moderators_orders))
+        .route("/moderator/metrics", get(moderators::moderator_metrics))
         .route(
             "/moderator/unassigned-orders",
             get(moderators::list_unassigned_orders),
@@ -30,16 +48,62 @@ pub fn router() -> axum::Router<Arc<AppState>> {
         .route("/moderator/unassign", patch(moderators::unassign_moderator))
         .route("/moderator/assign", patch(moderators::assign_moderator))
         .route("/review", delete(reviews::remove_review))
+        .route("/review", get(reviews::review_queue))
+        .route("/review/:id/publish", patch(reviews::publish_review))
+        .route("/review/:id/reject", patch(reviews::reject_review))
+        .route("/review/:id/reply", put(reviews::reply_to_review))
+        .route("/order", get(orders::search_orders))
+        .route("/order/:id", get(orders::order_details))
         .route("/order/:id/cancel", patch(orders::cancel_order_by_id))
         .route("/order/:id/success", patch(orders::finish_order_by_id))
+        .route("/order/:id/history", get(orders::order_history))
         .route("/order/all-in-period", post(orders::all_in_period))
+        .route("/report", get(reports::report))
+        .route("/export/orders", get(exports::export_orders))
+        .route("/export/users", get(exports::export_users))
         .route("/currency", post(currency::create_currency))
         .route(
             "/currency/:id",
             delete(currency::delete_currency_rate_by_id),
         )
         .route("/currency/:id", patch(currency::set_currency_rate_by_id))
+        .route(
+            "/currency/:id/history",
+            get(currency::currency_rate_changes),
+        )
+        .route(
+            "/currency/:id/settings",
+            get(currency::currency_settings).put(currency::set_currency_settings),
+        )
+        .route("/currency/:id/limits", put(currency::set_currency_limits))
+        .route(
+            "/currency/:id/approve",
+            post(currency::approve_pending_rate),
+        )
+        .route("/permission/role/:role", get(permissions::role_permissions))
+        .route(
+            "/permission/role/:role/:permission",
+            put(permissions::grant_to_role).delete(permissions::revoke_from_role),
+        )
+        .route("/permission/staff/:id", get(permissions::staff_permissions))
+        .route(
+            "/permission/staff/:id/:permission",
+            put(permissions::grant_to_staff).delete(permissions::revoke_from_staff),
+        )
+        .route("/login-audit", get(login_audit::login_audit))
         .route("/self", get(moderators::self_info))
+        .route(
+            "/self/two-factor",
+            post(two_factor::begin_enrolment).delete(two_factor::disable),
+        )
+        .route(
+            "/self/two-factor/confirm",
+            post(two_factor::confirm_enrolment),
+        )
+        .route(
+            "/two-factor/policy",
+            get(two_factor::policy).put(two_factor::set_policy),
+        )
         .route("/social", patch(social::set_url))
         .route("/requisites", patch(requisites::set_data))
         .route("/moderator/password", patch(moderators::change_password))
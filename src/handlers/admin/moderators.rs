@@ -1,14 +1,22 @@
 use crate::{
-    extractors::admin_jwt::ModeratorAuthJWT,
+    extractors::{
+        admin_jwt::{self, HandleOrders, ManageStaff, Permitted, StaffAuthJWT, ViewReports},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::{admin::permissions::StaffPermission, pagination::Paged},
     services::{
+        admin::metrics::{MetricsParameters, ModeratorMetrics, Service as MetricsService},
         admin::moderators::{
-            AssignModeratorParameters, CreateModeratorParameters, Service as AdminService,
-            UnassignModeratorParameters,
+            AssignModeratorParameters, ChangeLoginParameters, CreateModeratorParameters,
+            IssuePasswordResetParameters, PasswordReset, Service as AdminService,
+            SetAssignmentParameters, UnassignModeratorParameters,
         },
-        auth::{JwtCheckParams, ResetPasswordParameters, Service as AuthService},
+        admin::permissions::Service as PermissionsService,
+        auth::{ResetPasswordParameters, Service as AuthService},
         chat::{GetChatParameters, SendMessageParameters, Sender, Service as ChatService},
+        events::{OrderEvent, OrderEventKind, Service as EventsService},
     },
-    Order,
+    Order, OrderListQuery,
 };
 use axum::{
     body::{Body, Bytes},
@@ -20,20 +28,21 @@ use axum::{
 use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
 use chrono::NaiveDateTime as DateTime;
 use entity::{
-    admin::{Entity as AdminEntity, Model as AdminModel},
+    admin::Model as AdminModel,
     chat::{Column as ChatColumn, Entity as ChatEntity, Model as ChatModel},
     image::{Entity as ImageEntity, Model as ImageModel},
     message::{Entity as MessageEntity, Model as MessageModel},
+    sea_orm_active_enums::Permission,
 };
 
 use redis::AsyncCommands;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+use sea_orm::{prelude::Decimal, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_util::io::ReaderStream;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{errors::AppError, extractors::admin_jwt::AdminAuthJWT, state::AppState};
+use crate::{errors::AppError, state::AppState};
 
 #[derive(serde::Serialize, serde::Deserialize, ToSchema)]
 pub struct ModeratorCredentials {
@@ -59,6 +68,14 @@ impl ModeratorCredentials {
 pub struct ModeratorResponse {
     pub id: String,
     pub login: String,
+    //? Null means unlimited
+    pub capacity: Option<i32>,
+    pub on_shift: bool,
+    //? Null for active accounts
+    pub disabled_at: Option<DateTime>,
+    pub last_login_at: Option<DateTime>,
+    //? Updated at most once a minute
+    pub last_activity_at: Option<DateTime>,
 }
 
 impl From<AdminModel> for ModeratorResponse {
@@ -66,6 +83,11 @@ impl From<AdminModel> for ModeratorResponse {
         Self {
             id: value.id.to_string(),
             login: value.login,
+            capacity: value.capacity,
+            on_shift: value.on_shift,
+            disabled_at: value.disabled_at,
+            last_login_at: value.last_login_at,
+            last_activity_at: value.last_activity_at,
         }
     }
 }
@@ -159,12 +181,12 @@ impl From<Vec<(MessageModel, Vec<ImageModel>)>> for ChatHistory {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_staff"])
     )
 )]
 pub async fn create_moderator(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(_admin, _): Permitted<ManageStaff>,
     Json(credentials): Json<ModeratorCredentials>,
 ) -> Response {
     if !credentials.valid() {
@@ -188,35 +210,151 @@ pub async fn create_moderator(
     }
 }
 
+//? Finished orders and chats of the moderator are kept
+//? Open orders are unassigned so that they can be picked up again
 #[utoipa::path(
     delete,
     path = "/api/admin/moderator/{id}",
     responses(
-        (status = 204, description = "Moderator was successfully deleted"),
+        (status = 204, description = "Moderator was successfully disabled"),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Moderator outranks you",             body = Details),
         (status = 404, description = "Moderator was not found",            body = Details),
-        (status = 403, description = "Moderator has admin role",           body = Details),
+        (status = 409, description = "Last staff able to manage staff",    body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     params(("id" = i64, Path, description = "Moderator id")),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_staff"])
     )
 )]
-#[tracing::instrument(skip(app_state, _admin))]
-pub async fn delete_moderator(
+#[tracing::instrument(skip(app_state, admin))]
+pub async fn disable_moderator(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(admin, _): Permitted<ManageStaff>,
+    Path(moderator_id): Path<i64>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match AdminService::disable_moderator(admin.id, moderator_id, &transaction).await {
+                Ok(unassigned_orders) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+
+                    let events = unassigned_orders
+                        .into_iter()
+                        .map(|order| {
+                            OrderEvent::new(
+                                OrderEventKind::ModeratorUnassigned { moderator_id },
+                                order,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+
+                    EventsService::publish_all(
+                        events,
+                        app_state.configuration().order_events_channel_name(),
+                        app_state.redis_client(),
+                    )
+                    .await;
+
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/admin/moderator/{id}/enable",
+    responses(
+        (status = 200, description = "Moderator was successfully enabled", body = ModeratorResponse),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Moderator outranks you",             body = Details),
+        (status = 404, description = "Moderator was not found",            body = Details),
+        (status = 409, description = "Moderator is not disabled",          body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(("id" = i64, Path, description = "Moderator id")),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state, admin))]
+pub async fn enable_moderator(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(admin, _): Permitted<ManageStaff>,
     Path(moderator_id): Path<i64>,
 ) -> Response {
     match app_state.database_connection().begin().await {
-        Ok(transaction) => match AdminService::delete_moderator(moderator_id, &transaction).await {
-            Ok(()) => {
+        Ok(transaction) => {
+            match AdminService::enable_moderator(admin.id, moderator_id, &transaction).await {
+                Ok(moderator) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<ModeratorResponse>::into(moderator)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct ChangeLoginRequest {
+    login: String,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/admin/moderator/{id}/login",
+    request_body = ChangeLoginRequest,
+    responses(
+        (status = 200, description = "Login was successfully changed",     body = ModeratorResponse),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Admin or moderator outranks you",    body = Details),
+        (status = 404, description = "Admin or moderator was not found",   body = Details),
+        (status = 409, description = "Login was occupied",                 body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(("id" = i64, Path, description = "Admin or moderator id")),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state, admin))]
+pub async fn change_login(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(admin, _): Permitted<ManageStaff>,
+    Path(admin_id): Path<i64>,
+    Json(payload): Json<ChangeLoginRequest>,
+) -> Response {
+    if payload.login.is_empty() {
+        return AppError::EmptyCredentials.into_response();
+    }
+
+    let parameters = ChangeLoginParameters {
+        manager_id: admin.id,
+        admin_id,
+        login: payload.login,
+    };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => match AdminService::change_login(parameters, &transaction).await {
+            Ok(staff) => {
                 if let Err(cause) = transaction.commit().await {
                     return AppError::InternalServerError(Box::new(cause)).into_response();
                 }
-                StatusCode::NO_CONTENT.into_response()
+                Json(Into::<ModeratorResponse>::into(staff)).into_response()
             }
             Err(cause) => Into::<AppError>::into(cause).into_response(),
         },
@@ -224,6 +362,161 @@ pub async fn delete_moderator(
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct PasswordResetResponse {
+    //? Shown once, only its hash is stored
+    token: String,
+    expires_at: DateTime,
+}
+
+impl From<PasswordReset> for PasswordResetResponse {
+    fn from(value: PasswordReset) -> Self {
+        Self {
+            token: value.token,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+//? Staff can not sign in with the old password until the token is redeemed
+//? Issuing a new token replaces the previous one
+#[utoipa::path(
+    post,
+    path = "/api/admin/moderator/{id}/password-reset",
+    responses(
+        (status = 200, description = "Reset token was successfully issued", body = PasswordResetResponse),
+        (status = 400, description = "Bad request",                         body = Details),
+        (status = 401, description = "Unauthorized",                        body = Details),
+        (status = 403, description = "Admin or moderator outranks you",     body = Details),
+        (status = 404, description = "Admin or moderator was not found",    body = Details),
+        (status = 500, description = "Internal Server Error",               body = Details),
+    ),
+    params(("id" = i64, Path, description = "Admin or moderator id")),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state, admin))]
+pub async fn issue_password_reset(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(admin, _): Permitted<ManageStaff>,
+    Path(moderator_id): Path<i64>,
+) -> Response {
+    let parameters = IssuePasswordResetParameters {
+        manager_id: admin.id,
+        moderator_id,
+        ttl_seconds: app_state.configuration().password_reset_ttl_seconds(),
+    };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match AdminService::issue_password_reset(parameters, &transaction).await {
+                Ok(reset) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<PasswordResetResponse>::into(reset)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct ModeratorAssignmentRequest {
+    //? Maximum of open orders, null means unlimited
+    capacity: Option<u16>,
+    on_shift: bool,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/admin/moderator/{id}/assignment",
+    request_body = ModeratorAssignmentRequest,
+    responses(
+        (status = 200, description = "Assignment settings were successfully changed", body = ModeratorResponse),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 404, description = "Moderator was not found",            body = Details),
+        (status = 403, description = "Staff can not handle orders",        body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(("id" = i64, Path, description = "Moderator id")),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state, _admin))]
+pub async fn set_moderator_assignment(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageStaff>,
+    Path(moderator_id): Path<i64>,
+    Json(payload): Json<ModeratorAssignmentRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let parameters = SetAssignmentParameters {
+                moderator_id,
+                capacity: payload.capacity.map(i32::from),
+                on_shift: payload.on_shift,
+            };
+
+            match AdminService::set_assignment(parameters, &transaction).await {
+                Ok(moderator) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<ModeratorResponse>::into(moderator)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct ShiftRequest {
+    on_shift: bool,
+}
+
+//? Moderators who are off shift do not get new orders
+#[utoipa::path(
+    patch,
+    path = "/api/admin/moderator/shift",
+    request_body = ShiftRequest,
+    responses(
+        (status = 200, description = "Shift was successfully changed",     body = ModeratorResponse),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["handle_orders"])
+    )
+)]
+#[tracing::instrument(skip(app_state, moderator))]
+pub async fn set_shift(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(moderator, _): Permitted<HandleOrders>,
+    Json(payload): Json<ShiftRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match AdminService::set_on_shift(moderator.id, payload.on_shift, &transaction).await {
+                Ok(moderator) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<ModeratorResponse>::into(moderator)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
 pub struct AssignModeratorRequest {
     order_id: String,
@@ -238,17 +531,17 @@ pub struct AssignModeratorRequest {
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
         (status = 404, description = "Moderator was not found",            body = Details),
-        (status = 403, description = "Moderator has admin role",           body = Details),
+        (status = 403, description = "Staff can not handle orders",        body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 #[tracing::instrument(skip(app_state, moderator))]
 pub async fn assign_moderator(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Json(payload): Json<AssignModeratorRequest>,
 ) -> Response {
     let order_id = match payload.order_id.parse::<i64>() {
@@ -265,10 +558,24 @@ pub async fn assign_moderator(
                 order_id,
             };
             match AdminService::assign_moderator(parameters, &transaction).await {
-                Ok(()) => {
+                Ok(order) => {
                     if let Err(cause) = transaction.commit().await {
                         return AppError::InternalServerError(Box::new(cause)).into_response();
                     }
+
+                    let event = OrderEvent::new(
+                        OrderEventKind::ModeratorAssigned {
+                            moderator_id: moderator.id,
+                        },
+                        order,
+                    );
+                    EventsService::publish(
+                        &event,
+                        app_state.configuration().order_events_channel_name(),
+                        app_state.redis_client(),
+                    )
+                    .await;
+
                     StatusCode::NO_CONTENT.into_response()
                 }
                 Err(cause) => Into::<AppError>::into(cause).into_response(),
@@ -295,13 +602,13 @@ pub struct UnassignModeratorRequest {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 #[tracing::instrument(skip(app_state, moderator))]
 pub async fn unassign_moderator(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Json(payload): Json<UnassignModeratorRequest>,
 ) -> Response {
     let order_id = match payload.order_id.parse::<i64>() {
@@ -317,10 +624,24 @@ pub async fn unassign_moderator(
                 order_id,
             };
             match AdminService::unassign_moderator(parameters, &transaction).await {
-                Ok(()) => {
+                Ok(order) => {
                     if let Err(cause) = transaction.commit().await {
                         return AppError::InternalServerError(Box::new(cause)).into_response();
                     }
+
+                    let event = OrderEvent::new(
+                        OrderEventKind::ModeratorUnassigned {
+                            moderator_id: moderator.id,
+                        },
+                        order,
+                    );
+                    EventsService::publish(
+                        &event,
+                        app_state.configuration().order_events_channel_name(),
+                        app_state.redis_client(),
+                    )
+                    .await;
+
                     StatusCode::NO_CONTENT.into_response()
                 }
                 Err(cause) => Into::<AppError>::into(cause).into_response(),
@@ -337,10 +658,18 @@ pub async fn unassign_moderator(
         (status = 200, description = "Moderators were successfully retrieved", body = [ModeratorResponse]),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Forbidden",                          body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
     )
 )]
-pub async fn list_moderators(State(app_state): State<Arc<AppState>>) -> Response {
+#[tracing::instrument(skip(app_state, _admin))]
+pub async fn list_moderators(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(_admin, _): Permitted<ManageStaff>,
+) -> Response {
     match AdminService::moderators(app_state.database_connection()).await {
         Ok(moderators) => Json(
             moderators
@@ -353,29 +682,79 @@ pub async fn list_moderators(State(app_state): State<Arc<AppState>>) -> Response
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MetricsQuery {
+    from: DateTime,
+    to: DateTime,
+}
+
+//? Finished orders, volume and confirmation time cover orders finished in the period
+//? First response covers orders created in the period
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct ModeratorMetricsResponse {
+    pub id: String,
+    pub login: String,
+    pub succeeded: i64,
+    pub cancelled: i64,
+    pub open_orders: i64,
+    #[schema(value_type = String)]
+    pub converted_volume: Decimal,
+    pub median_first_response_seconds: Option<i64>,
+    pub median_confirmation_seconds: Option<i64>,
+}
+
+impl From<ModeratorMetrics> for ModeratorMetricsResponse {
+    fn from(value: ModeratorMetrics) -> Self {
+        Self {
+            id: value.moderator_id.to_string(),
+            login: value.login,
+            succeeded: value.succeeded,
+            cancelled: value.cancelled,
+            open_orders: value.open_orders,
+            converted_volume: value.converted_volume,
+            median_first_response_seconds: value.median_first_response_seconds,
+            median_confirmation_seconds: value.median_confirmation_seconds,
+        }
+    }
+}
+
 #[utoipa::path(
     get,
-    path = "/api/admin/moderator/orders",
+    path = "/api/admin/moderator/metrics",
+    params(MetricsQuery),
     responses(
-        (status = 200, description = "Orders were successfully retrieved", body = [Order]),
+        (status = 200, description = "Metrics were successfully computed", body = [ModeratorMetricsResponse]),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
-        (status = 404, description = "Moderator was not found",            body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["view_reports"])
     )
 )]
-pub async fn list_moderators_orders(
+#[tracing::instrument(skip(app_state))]
+pub async fn moderator_metrics(
+    Permitted(admin, _): Permitted<ViewReports>,
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Query(query): Query<MetricsQuery>,
 ) -> Response {
-    match AdminService::moderators_orders(moderator.id, app_state.database_connection()).await {
-        Ok(moderators) => Json(
-            moderators
+    let (from, to) = if query.from <= query.to {
+        (query.from, query.to)
+    } else {
+        (query.to, query.from)
+    };
+
+    match MetricsService::metrics(
+        MetricsParameters { from, to },
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(metrics) => Json(
+            metrics
                 .into_iter()
-                .map(Into::<Order>::into)
+                .map(Into::<ModeratorMetricsResponse>::into)
                 .collect::<Vec<_>>(),
         )
         .into_response(),
@@ -383,32 +762,67 @@ pub async fn list_moderators_orders(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/admin/moderator/orders",
+    params(PageQuery, OrderListQuery),
+    responses(
+        (status = 200, description = "Orders were successfully retrieved", body = PagedOrders),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 404, description = "Moderator was not found",            body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["handle_orders"])
+    )
+)]
+pub async fn list_moderators_orders(
+    State(app_state): State<Arc<AppState>>,
+    Permitted(moderator, _): Permitted<HandleOrders>,
+    Pagination(page): Pagination,
+    Query(query): Query<OrderListQuery>,
+) -> Response {
+    match AdminService::moderators_orders(
+        moderator.id,
+        query.into_parameters(page),
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(orders) => Json(Into::<Paged<Order>>::into(orders)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/admin/moderator/unassigned-orders",
+    params(PageQuery, OrderListQuery),
     responses(
-        (status = 200, description = "Orders were successfully retrieved", body = [Order]),
+        (status = 200, description = "Orders were successfully retrieved", body = PagedOrders),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
         (status = 404, description = "Moderator was not found",            body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 pub async fn list_unassigned_orders(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(_moderator): ModeratorAuthJWT,
+    Permitted(_moderator, _): Permitted<HandleOrders>,
+    Pagination(page): Pagination,
+    Query(query): Query<OrderListQuery>,
 ) -> Response {
-    match AdminService::unassigned_orders(app_state.database_connection()).await {
-        Ok(orders) => Json(
-            orders
-                .into_iter()
-                .map(Into::<Order>::into)
-                .collect::<Vec<_>>(),
-        )
-        .into_response(),
+    match AdminService::unassigned_orders(
+        query.into_parameters(page),
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(orders) => Json(Into::<Paged<Order>>::into(orders)).into_response(),
         Err(cause) => Into::<AppError>::into(cause).into_response(),
     }
 }
@@ -418,14 +832,19 @@ pub struct ModeratorOrAdminInfo {
     id: String,
     login: String,
     role: String,
+    //? Granted to the account or to its role
+    permissions: Vec<StaffPermission>,
+    two_factor_enabled: bool,
 }
 
-impl From<AdminModel> for ModeratorOrAdminInfo {
-    fn from(value: AdminModel) -> Self {
+impl ModeratorOrAdminInfo {
+    fn new(value: AdminModel, permissions: Vec<Permission>) -> Self {
         Self {
             id: value.id.to_string(),
             login: value.login,
             role: serde_json::to_string(&value.role).unwrap(),
+            permissions: permissions.into_iter().map(Into::into).collect(),
+            two_factor_enabled: value.totp_enabled_at.is_some(),
         }
     }
 }
@@ -436,13 +855,20 @@ impl From<AdminModel> for ModeratorOrAdminInfo {
     responses(
         (status = 200, description = "Information was successfully retrieved", body = ModeratorOrAdminInfo),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
         ("jwt_admin" = [])
     )
 )]
-pub async fn self_info(ModeratorAuthJWT(moderator): ModeratorAuthJWT) -> impl IntoResponse {
-    Json(Into::<ModeratorOrAdminInfo>::into(moderator))
+pub async fn self_info(
+    State(app_state): State<Arc<AppState>>,
+    StaffAuthJWT(moderator): StaffAuthJWT,
+) -> Response {
+    match PermissionsService::effective(&moderator, app_state.database_connection()).await {
+        Ok(permissions) => Json(ModeratorOrAdminInfo::new(moderator, permissions)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
 }
 
 #[utoipa::path(
@@ -460,7 +886,7 @@ pub async fn self_info(ModeratorAuthJWT(moderator): ModeratorAuthJWT) -> impl In
 )]
 pub async fn change_password(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    StaffAuthJWT(moderator): StaffAuthJWT,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Response {
     match app_state.database_connection().begin().await {
@@ -492,12 +918,12 @@ pub async fn change_password(
         (status = 500, description = "Internal server error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 pub async fn chat(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Json(payload): Json<GetChatRequest>,
 ) -> Response {
     let steam_id: i64 = match payload.id.parse() {
@@ -532,12 +958,12 @@ pub async fn chat(
     ),
     params(("id" = i64, Path, description = "Order id")),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_staff"])
     )
 )]
 pub async fn chat_history_admin(
     State(app_state): State<Arc<AppState>>,
-    AdminAuthJWT(_admin): AdminAuthJWT,
+    Permitted(_admin, _): Permitted<ManageStaff>,
     Path(order_id): Path<i64>,
 ) -> Response {
     let chat = match ChatEntity::find()
@@ -556,7 +982,7 @@ pub async fn chat_history_admin(
 
     match ChatService::history(chat.id, app_state.database_connection()).await {
         Ok(res) => Json(Into::<ChatHistory>::into(res)).into_response(),
-        Err(cause) => return Into::<AppError>::into(cause).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
     }
 }
 
@@ -575,12 +1001,12 @@ pub async fn chat_history_admin(
 
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 pub async fn send_message(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Path(chat_id): Path<i64>,
     TypedMultipart(UploadData { image, text }): TypedMultipart<UploadData>,
 ) -> Response {
@@ -598,7 +1024,7 @@ pub async fn send_message(
             let params = SendMessageParameters {
                 folder: app_state.configuration().upload_folder().clone(),
                 chat_id,
-                sender: Sender::Moderator,
+                sender: Sender::Moderator(moderator.id),
                 text,
                 image: image.as_ref(),
             };
@@ -651,12 +1077,12 @@ pub async fn send_message(
 
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 pub async fn history(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Path(chat_id): Path<i64>,
 ) -> Response {
     match app_state.database_connection().begin().await {
@@ -708,26 +1134,24 @@ pub async fn websocket_handler(
         _ => return AppError::AuthorizationHeaderBadSchema.into_response(),
     };
 
-    let params = JwtCheckParams {
-        token,
-        secret: state.configuration().jwt_secret(),
+    let moderator = match admin_jwt::staff(token, &state).await {
+        Ok(moderator) => moderator,
+        Err(cause) => return cause.into_response(),
     };
 
-    let claims = match AuthService::check(params) {
-        Ok(claims) => claims,
-        Err(cause) => return Into::<AppError>::into(cause).into_response(),
-    };
-
-    let moderator = match AdminEntity::find_by_id(claims.sub)
-        .one(state.database_connection())
-        .await
+    match PermissionsService::holds(
+        &moderator,
+        Permission::HandleOrders,
+        state.database_connection(),
+    )
+    .await
     {
-        Ok(Some(admin)) => admin,
-        Ok(None) => return AppError::Unauthorized.into_response(),
-        Err(cause) => return AppError::InternalServerError(Box::new(cause)).into_response(),
-    };
+        Ok(true) => {}
+        Ok(false) => return AppError::Forbidden.into_response(),
+        Err(cause) => return Into::<AppError>::into(cause).into_response(),
+    }
 
-    match ChatEntity::find_by_id(chat_id)
+    let order_id = match ChatEntity::find_by_id(chat_id)
         .one(state.database_connection())
         .await
     {
@@ -735,15 +1159,16 @@ pub async fn websocket_handler(
             if chat.moderator_id != moderator.id {
                 return StatusCode::FORBIDDEN.into_response();
             }
+            chat.order_id
         }
         Ok(None) => return StatusCode::NOT_FOUND.into_response(),
         Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
 
-    ws.on_upgrade(move |socket| handle_socket(socket, state, chat_id))
+    ws.on_upgrade(move |socket| handle_socket(socket, state, chat_id, order_id))
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64) {
+async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64, order_id: i64) {
     let (tx, mut rx) = mpsc::channel(10);
     let (mut sender, _) = socket.split();
 
@@ -751,13 +1176,28 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64) {
         let connection = state.redis_client().get_async_connection().await.unwrap();
         let mut pubsub = connection.into_pubsub();
         pubsub.subscribe(format!("chat-{}", chat_id)).await.unwrap();
+        pubsub
+            .subscribe(state.configuration().order_events_channel_name())
+            .await
+            .unwrap();
 
         while let Some(msg) = pubsub.on_message().next().await {
-            if let Ok(payload) = msg.get_payload() {
-                if tx.send(payload).await.is_err() {
-                    break;
+            let payload = match msg.get_payload::<String>() {
+                Ok(payload) => payload,
+                Err(_) => continue,
+            };
+
+            //? Only events of the order this chat belongs to are forwarded
+            if msg.get_channel_name() == state.configuration().order_events_channel_name() {
+                match OrderEvent::from_payload(&payload) {
+                    Some(event) if event.order.id == order_id => {}
+                    _ => continue,
                 }
             }
+
+            if tx.send(payload).await.is_err() {
+                break;
+            }
         }
     });
 
@@ -783,12 +1223,12 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64) {
 
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 pub async fn image(
     State(app_state): State<Arc<AppState>>,
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<HandleOrders>,
     Path((chat_id, image_id)): Path<(i64, i64)>,
 ) -> Response {
     let _chat = match ChatEntity::find_by_id(chat_id)
@@ -1,54 +1,94 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use chrono::NaiveDateTime;
-use sea_orm::TransactionTrait;
+use entity::order::Entity as OrderEntity;
+use sea_orm::{prelude::Decimal, EntityTrait, TransactionTrait};
 use std::sync::Arc;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    errors::AppError, extractors::admin_jwt::ModeratorAuthJWT,
-    services::orders::Service as OrderService, state::AppState, Order,
+    errors::AppError,
+    extractors::{
+        admin_jwt::{HandleOrders, Permitted},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::pagination::Paged,
+    services::{
+        events::{OrderEvent, OrderEventKind, Service as EventsService},
+        orders::{
+            state_machine::Actor, CancelOrderByIdParameters, FinishOrderParameters, OrderDetails,
+            OrderFilter, OrderSearch, OrderSort, SearchOrdersParameters, Service as OrderService,
+        },
+        pagination::PageRequest,
+    },
+    state::AppState,
+    CancelOrderRequest, Order, OrderSortField, OrderStatus, OrderStatusChange,
 };
 
-use redis::AsyncCommands;
-
 #[utoipa::path(
     patch,
     path = "/api/admin/order/{id}/cancel",
+    request_body(content = Option<CancelOrderRequest>, description = "Optional cancellation reason"),
     responses(
         (status = 204, description = "Order was successfully canceled"),
         (status = 404, description = "Order was not found", body = Details),
         (status = 401, description = "Unauthorized", body = Details),
         (status = 400, description = "Order has already been marked as succeeded", body = Details),
+        (status = 403, description = "Moderator is not assigned to this order", body = Details),
         (status = 500, description = "Internal Server Error", body = Details),
     ),
     params(
         ("id" = i64, Path, description = "Order id")
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn cancel_order_by_id(
-    ModeratorAuthJWT(admin): ModeratorAuthJWT,
+    Permitted(admin, _): Permitted<HandleOrders>,
     State(app_state): State<Arc<AppState>>,
     Path(order_id): Path<i64>,
+    payload: Option<Json<CancelOrderRequest>>,
 ) -> axum::response::Response {
     match app_state.database_connection().begin().await {
-        Ok(transaction) => match OrderService::cancel_order_by_id(order_id, &transaction).await {
-            Ok(()) => {
-                if let Err(cause) = transaction.commit().await {
-                    return AppError::InternalServerError(Box::new(cause)).into_response();
+        Ok(transaction) => {
+            let actor = match Actor::staff(&admin, &transaction).await {
+                Ok(actor) => actor,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let parameters = CancelOrderByIdParameters {
+                order_id,
+                actor,
+                reason: payload.and_then(|Json(payload)| payload.reason),
+            };
+
+            match OrderService::cancel_order_by_id(parameters, &transaction).await {
+                Ok(change) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+
+                    if change.changed {
+                        let event = OrderEvent::new(OrderEventKind::Cancelled, change.order);
+                        EventsService::publish(
+                            &event,
+                            app_state.configuration().order_events_channel_name(),
+                            app_state.redis_client(),
+                        )
+                        .await;
+                    }
+
+                    StatusCode::NO_CONTENT.into_response()
                 }
-                StatusCode::NO_CONTENT.into_response()
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
             }
-            Err(cause) => Into::<AppError>::into(cause).into_response(),
-        },
+        }
         Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
     }
 }
@@ -61,43 +101,108 @@ pub async fn cancel_order_by_id(
         (status = 404, description = "Order was not found", body = Details),
         (status = 401, description = "Unauthorized", body = Details),
         (status = 400, description = "Order has already been marked as canceled", body = Details),
+        (status = 403, description = "Moderator is not assigned to this order", body = Details),
         (status = 500, description = "Internal Server Error", body = Details),
     ),
     params(
         ("id" = i64, Path, description = "Order id")
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn finish_order_by_id(
-    ModeratorAuthJWT(admin): ModeratorAuthJWT,
+    Permitted(admin, _): Permitted<HandleOrders>,
     State(app_state): State<Arc<AppState>>,
     Path(order_id): Path<i64>,
 ) -> axum::response::Response {
     match app_state.database_connection().begin().await {
-        Ok(transaction) => match OrderService::finish_order_by_id(order_id, &transaction).await {
-            Ok(order) => {
-                if let Err(cause) = transaction.commit().await {
-                    return AppError::InternalServerError(Box::new(cause)).into_response();
-                }
+        Ok(transaction) => {
+            let actor = match Actor::staff(&admin, &transaction).await {
+                Ok(actor) => actor,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let parameters = FinishOrderParameters { order_id, actor };
 
-                match app_state.redis_client().get_async_connection().await {
-                    Ok(mut connection) => {
-                        let _: Result<(), _> = connection
-                            .publish("live_orders", serde_json::to_string(&order).unwrap())
-                            .await;
+            match OrderService::finish_order_by_id(parameters, &transaction).await {
+                Ok(change) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
                     }
-                    Err(cause) => {
-                        tracing::warn!(%cause, "Failed to connect to redis!");
+
+                    if change.changed {
+                        let event = OrderEvent::new(OrderEventKind::Succeeded, change.order);
+                        EventsService::publish(
+                            &event,
+                            app_state.configuration().order_events_channel_name(),
+                            app_state.redis_client(),
+                        )
+                        .await;
                     }
-                };
 
-                StatusCode::NO_CONTENT.into_response()
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
             }
-            Err(cause) => Into::<AppError>::into(cause).into_response(),
-        },
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/order/{id}/history",
+    responses(
+        (status = 200, description = "Order status history was successfully retrieved", body = [OrderStatusChange]),
+        (status = 404, description = "Order was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Order id")
+    ),
+    security(
+        ("jwt_admin" = ["handle_orders"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn order_history(
+    Permitted(admin, _): Permitted<HandleOrders>,
+    State(app_state): State<Arc<AppState>>,
+    Path(order_id): Path<i64>,
+) -> axum::response::Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match OrderEntity::find_by_id(order_id).one(&transaction).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return AppError::OrderWasNotFound.into_response(),
+                Err(cause) => {
+                    return AppError::InternalServerError(Box::new(cause)).into_response()
+                }
+            };
+
+            let history = match OrderService::history(order_id, &transaction).await {
+                Ok(history) => history,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (
+                StatusCode::OK,
+                Json(
+                    history
+                        .into_iter()
+                        .map(Into::<OrderStatusChange>::into)
+                        .collect::<Vec<_>>(),
+                ),
+            )
+                .into_response()
+        }
         Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
     }
 }
@@ -120,12 +225,12 @@ pub struct TimeBounds {
         (status = 500, description = "Internal Server Error",                     body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["handle_orders"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn all_in_period(
-    ModeratorAuthJWT(admin): ModeratorAuthJWT,
+    Permitted(admin, _): Permitted<HandleOrders>,
     State(app_state): State<Arc<AppState>>,
     Json(bounds): Json<TimeBounds>,
 ) -> axum::response::Response {
@@ -146,3 +251,169 @@ pub async fn all_in_period(
         Err(cause) => Into::<AppError>::into(cause).into_response(),
     }
 }
+
+//? All ranges are inclusive
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderSearchQuery {
+    id: Option<i64>,
+    steam_id: Option<i64>,
+    status: Option<OrderStatus>,
+    moderator_id: Option<i64>,
+    requisites_id: Option<i64>,
+    currency: Option<String>,
+    payment_method: Option<String>,
+    #[param(value_type = Option<String>)]
+    amount_min: Option<Decimal>,
+    #[param(value_type = Option<String>)]
+    amount_max: Option<Decimal>,
+    created_from: Option<NaiveDateTime>,
+    created_to: Option<NaiveDateTime>,
+    finished_from: Option<NaiveDateTime>,
+    finished_to: Option<NaiveDateTime>,
+    //? created_at by default
+    sort: Option<OrderSortField>,
+}
+
+impl OrderSearchQuery {
+    pub fn into_sorted_search(self) -> (OrderSearch, OrderSort) {
+        let sort = self.sort.map(Into::into).unwrap_or_default();
+        (self.into_search(), sort)
+    }
+
+    fn into_search(self) -> OrderSearch {
+        OrderSearch {
+            filter: OrderFilter {
+                status: self.status.map(Into::into),
+                currency: self.currency,
+                payment_method: self.payment_method,
+                from: self.created_from,
+                to: self.created_to,
+            },
+            id: self.id,
+            steam_id: self.steam_id,
+            moderator_id: self.moderator_id,
+            requisites_id: self.requisites_id,
+            amount_min: self.amount_min,
+            amount_max: self.amount_max,
+            finished_from: self.finished_from,
+            finished_to: self.finished_to,
+        }
+    }
+
+    fn into_parameters(self, page: PageRequest) -> SearchOrdersParameters {
+        let (search, sort) = self.into_sorted_search();
+        SearchOrdersParameters { search, sort, page }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/order",
+    params(PageQuery, OrderSearchQuery),
+    responses(
+        (status = 200, description = "Orders were successfully retrieved", body = PagedOrders),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["handle_orders"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn search_orders(
+    Permitted(admin, _): Permitted<HandleOrders>,
+    State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
+    Query(query): Query<OrderSearchQuery>,
+) -> axum::response::Response {
+    match OrderService::search(query.into_parameters(page), app_state.database_connection()).await {
+        Ok(orders) => Json(Into::<Paged<Order>>::into(orders)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct OrderUser {
+    steam_id: String,
+    username: Option<String>,
+    avatar_url: Option<String>,
+    trade_url: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct OrderModerator {
+    id: String,
+    login: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct OrderRequisites {
+    id: String,
+    name: String,
+    data: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct OrderDetailsResponse {
+    order: Order,
+    user: Option<OrderUser>,
+    moderator: Option<OrderModerator>,
+    requisites: Option<OrderRequisites>,
+    chat_id: Option<String>,
+    messages_count: u64,
+}
+
+impl From<OrderDetails> for OrderDetailsResponse {
+    fn from(value: OrderDetails) -> Self {
+        Self {
+            order: value.order.into(),
+            user: value.user.map(|user| OrderUser {
+                steam_id: user.steam_id.to_string(),
+                username: user.username,
+                avatar_url: user.avatar_url,
+                trade_url: user.trade_url,
+            }),
+            moderator: value.moderator.map(|moderator| OrderModerator {
+                id: moderator.id.to_string(),
+                login: moderator.login,
+            }),
+            requisites: value.requisites.map(|requisites| OrderRequisites {
+                id: requisites.id.to_string(),
+                name: requisites.name,
+                data: requisites.data,
+            }),
+            chat_id: value.chat.map(|chat| chat.id.to_string()),
+            messages_count: value.messages_count,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/order/{id}",
+    responses(
+        (status = 200, description = "Order was successfully retrieved", body = OrderDetailsResponse),
+        (status = 404, description = "Order was not found",              body = Details),
+        (status = 401, description = "Unauthorized",                     body = Details),
+        (status = 500, description = "Internal Server Error",            body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Order id")
+    ),
+    security(
+        ("jwt_admin" = ["handle_orders"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn order_details(
+    Permitted(admin, _): Permitted<HandleOrders>,
+    State(app_state): State<Arc<AppState>>,
+    Path(order_id): Path<i64>,
+) -> axum::response::Response {
+    match OrderService::details(order_id, app_state.database_connection()).await {
+        Ok(details) => Json(Into::<OrderDetailsResponse>::into(details)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
@@ -0,0 +1,328 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use entity::sea_orm_active_enums::{Permission, Role};
+use sea_orm::TransactionTrait;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{
+    errors::AppError,
+    extractors::admin_jwt::{ManageStaff, Permitted},
+    services::admin::permissions::{Service as PermissionsService, StaffPermissions},
+    state::AppState,
+};
+
+//? Manage staff also covers social links, manage currency also covers requisites
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StaffPermission {
+    ManageCurrency,
+    ManageReviews,
+    BlacklistUsers,
+    ViewReports,
+    ManageStaff,
+    HandleOrders,
+}
+
+impl From<Permission> for StaffPermission {
+    fn from(value: Permission) -> Self {
+        match value {
+            Permission::ManageCurrency => StaffPermission::ManageCurrency,
+            Permission::ManageReviews => StaffPermission::ManageReviews,
+            Permission::BlacklistUsers => StaffPermission::BlacklistUsers,
+            Permission::ViewReports => StaffPermission::ViewReports,
+            Permission::ManageStaff => StaffPermission::ManageStaff,
+            Permission::HandleOrders => StaffPermission::HandleOrders,
+        }
+    }
+}
+
+impl From<StaffPermission> for Permission {
+    fn from(value: StaffPermission) -> Self {
+        match value {
+            StaffPermission::ManageCurrency => Permission::ManageCurrency,
+            StaffPermission::ManageReviews => Permission::ManageReviews,
+            StaffPermission::BlacklistUsers => Permission::BlacklistUsers,
+            StaffPermission::ViewReports => Permission::ViewReports,
+            StaffPermission::ManageStaff => Permission::ManageStaff,
+            StaffPermission::HandleOrders => Permission::HandleOrders,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StaffRole {
+    Admin,
+    Moderator,
+}
+
+impl From<StaffRole> for Role {
+    fn from(value: StaffRole) -> Self {
+        match value {
+            StaffRole::Admin => Role::Admin,
+            StaffRole::Moderator => Role::Moderator,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct RolePermissions {
+    role: StaffRole,
+    permissions: Vec<StaffPermission>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct StaffPermissionsResponse {
+    id: String,
+    //? Granted to the account itself and revocable through this api
+    granted: Vec<StaffPermission>,
+    //? Granted to the account or to its role
+    effective: Vec<StaffPermission>,
+}
+
+impl StaffPermissionsResponse {
+    fn new(id: i64, permissions: StaffPermissions) -> Self {
+        Self {
+            id: id.to_string(),
+            granted: permissions.granted.into_iter().map(Into::into).collect(),
+            effective: permissions.effective.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/permission/role/{role}",
+    responses(
+        (status = 200, description = "Role permissions were successfully retrieved", body = RolePermissions),
+        (status = 400, description = "Bad request",                                  body = Details),
+        (status = 401, description = "Unauthorized",                                 body = Details),
+        (status = 403, description = "Permission required",                          body = Details),
+        (status = 500, description = "Internal Server Error",                        body = Details),
+    ),
+    params(
+        ("role" = StaffRole, Path, description = "Staff role")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn role_permissions(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path(role): Path<StaffRole>,
+) -> Response {
+    match PermissionsService::role_permissions(role.into(), app_state.database_connection()).await {
+        Ok(permissions) => Json(RolePermissions {
+            role,
+            permissions: permissions.into_iter().map(Into::into).collect(),
+        })
+        .into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/permission/role/{role}/{permission}",
+    responses(
+        (status = 204, description = "Permission was successfully granted to the role"),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Permission required",                body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("role" = StaffRole, Path, description = "Staff role"),
+        ("permission" = StaffPermission, Path, description = "Permission to grant")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn grant_to_role(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path((role, permission)): Path<(StaffRole, StaffPermission)>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match PermissionsService::grant_to_role(role.into(), permission.into(), &transaction)
+                .await
+            {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/permission/role/{role}/{permission}",
+    responses(
+        (status = 204, description = "Permission was successfully revoked from the role"),
+        (status = 400, description = "Bad request",                          body = Details),
+        (status = 401, description = "Unauthorized",                         body = Details),
+        (status = 403, description = "Permission required",                  body = Details),
+        (status = 409, description = "Nobody would be able to manage staff", body = Details),
+        (status = 500, description = "Internal Server Error",                body = Details),
+    ),
+    params(
+        ("role" = StaffRole, Path, description = "Staff role"),
+        ("permission" = StaffPermission, Path, description = "Permission to revoke")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn revoke_from_role(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path((role, permission)): Path<(StaffRole, StaffPermission)>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match PermissionsService::revoke_from_role(role.into(), permission.into(), &transaction)
+                .await
+            {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/permission/staff/{id}",
+    responses(
+        (status = 200, description = "Staff permissions were successfully retrieved", body = StaffPermissionsResponse),
+        (status = 400, description = "Bad request",                                   body = Details),
+        (status = 401, description = "Unauthorized",                                  body = Details),
+        (status = 403, description = "Permission required",                           body = Details),
+        (status = 404, description = "Admin or moderator was not found",              body = Details),
+        (status = 500, description = "Internal Server Error",                         body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Admin or moderator id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn staff_permissions(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Response {
+    match PermissionsService::staff_permissions(id, app_state.database_connection()).await {
+        Ok(permissions) => Json(StaffPermissionsResponse::new(id, permissions)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/permission/staff/{id}/{permission}",
+    responses(
+        (status = 204, description = "Permission was successfully granted to the account"),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Permission required",                body = Details),
+        (status = 404, description = "Admin or moderator was not found",   body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Admin or moderator id"),
+        ("permission" = StaffPermission, Path, description = "Permission to grant")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn grant_to_staff(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, permission)): Path<(i64, StaffPermission)>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match PermissionsService::grant_to_staff(id, permission.into(), &transaction).await {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+//? Permissions granted through the role are not affected
+#[utoipa::path(
+    delete,
+    path = "/api/admin/permission/staff/{id}/{permission}",
+    responses(
+        (status = 204, description = "Permission was successfully revoked from the account"),
+        (status = 400, description = "Bad request",                          body = Details),
+        (status = 401, description = "Unauthorized",                         body = Details),
+        (status = 403, description = "Permission required",                  body = Details),
+        (status = 404, description = "Admin or moderator was not found",     body = Details),
+        (status = 409, description = "Nobody would be able to manage staff", body = Details),
+        (status = 500, description = "Internal Server Error",                body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Admin or moderator id"),
+        ("permission" = StaffPermission, Path, description = "Permission to revoke")
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn revoke_from_staff(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, permission)): Path<(i64, StaffPermission)>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match PermissionsService::revoke_from_staff(id, permission.into(), &transaction).await {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
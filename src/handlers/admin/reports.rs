@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDateTime;
+use sea_orm::prelude::Decimal;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    errors::AppError,
+    extractors::admin_jwt::{Permitted, ViewReports},
+    services::reports::{
+        Bucket, BucketVolume, CurrencyVolume, ModeratorVolume, PaymentMethodVolume, Report,
+        ReportParameters, Service as ReportsService,
+    },
+    state::AppState,
+    OrderStatus,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl From<ReportBucket> for Bucket {
+    fn from(value: ReportBucket) -> Self {
+        match value {
+            ReportBucket::Day => Bucket::Day,
+            ReportBucket::Week => Bucket::Week,
+            ReportBucket::Month => Bucket::Month,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportQuery {
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    //? Day by default
+    bucket: Option<ReportBucket>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct CurrencyTurnover {
+    currency_symbol: String,
+    orders: i64,
+    #[schema(value_type = String)]
+    volume: Decimal,
+    #[schema(value_type = String)]
+    converted_volume: Decimal,
+}
+
+impl From<CurrencyVolume> for CurrencyTurnover {
+    fn from(value: CurrencyVolume) -> Self {
+        Self {
+            currency_symbol: value.currency_symbol,
+            orders: value.orders,
+            volume: value.volume,
+            converted_volume: value.converted_volume,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct BucketTurnover {
+    //? Start of the day, week or month
+    bucket: NaiveDateTime,
+    currency_symbol: String,
+    orders: i64,
+    #[schema(value_type = String)]
+    volume: Decimal,
+    #[schema(value_type = String)]
+    converted_volume: Decimal,
+}
+
+impl From<BucketVolume> for BucketTurnover {
+    fn from(value: BucketVolume) -> Self {
+        Self {
+            bucket: value.bucket,
+            currency_symbol: value.currency_symbol,
+            orders: value.orders,
+            volume: value.volume,
+            converted_volume: value.converted_volume,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct ModeratorTurnover {
+    moderator_id: Option<String>,
+    login: Option<String>,
+    orders: i64,
+    #[schema(value_type = String)]
+    converted_volume: Decimal,
+    average_completion_seconds: Option<i64>,
+}
+
+impl From<ModeratorVolume> for ModeratorTurnover {
+    fn from(value: ModeratorVolume) -> Self {
+        Self {
+            moderator_id: value.moderator_id.map(|id| id.to_string()),
+            login: value.login,
+            orders: value.orders,
+            converted_volume: value.converted_volume,
+            average_completion_seconds: value.average_completion_seconds,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct PaymentMethodTurnover {
+    payment_method: String,
+    orders: i64,
+    #[schema(value_type = String)]
+    converted_volume: Decimal,
+}
+
+impl From<PaymentMethodVolume> for PaymentMethodTurnover {
+    fn from(value: PaymentMethodVolume) -> Self {
+        Self {
+            payment_method: value.payment_method,
+            orders: value.orders,
+            converted_volume: value.converted_volume,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct StatusCount {
+    status: OrderStatus,
+    orders: i64,
+}
+
+//? Turnover figures include succeeded orders finished in the range
+//? Status counts include every order created in the range
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct FinancialReport {
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    bucket: ReportBucket,
+    currencies: Vec<CurrencyTurnover>,
+    buckets: Vec<BucketTurnover>,
+    moderators: Vec<ModeratorTurnover>,
+    payment_methods: Vec<PaymentMethodTurnover>,
+    statuses: Vec<StatusCount>,
+    average_completion_seconds: Option<i64>,
+}
+
+impl FinancialReport {
+    fn new(report: Report, from: NaiveDateTime, to: NaiveDateTime, bucket: ReportBucket) -> Self {
+        Self {
+            from,
+            to,
+            bucket,
+            currencies: report.currencies.into_iter().map(Into::into).collect(),
+            buckets: report.buckets.into_iter().map(Into::into).collect(),
+            moderators: report.moderators.into_iter().map(Into::into).collect(),
+            payment_methods: report.payment_methods.into_iter().map(Into::into).collect(),
+            statuses: report
+                .statuses
+                .into_iter()
+                .map(|(status, orders)| StatusCount {
+                    status: status.into(),
+                    orders,
+                })
+                .collect(),
+            average_completion_seconds: report.average_completion_seconds,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/report",
+    params(ReportQuery),
+    responses(
+        (status = 200, description = "Report was successfully built", body = FinancialReport),
+        (status = 400, description = "Bad request",                   body = Details),
+        (status = 401, description = "Unauthorized",                  body = Details),
+        (status = 500, description = "Internal Server Error",         body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["view_reports"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn report(
+    Permitted(admin, _): Permitted<ViewReports>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ReportQuery>,
+) -> Response {
+    let (from, to) = if query.from <= query.to {
+        (query.from, query.to)
+    } else {
+        (query.to, query.from)
+    };
+    let bucket = query.bucket.unwrap_or(ReportBucket::Day);
+
+    let parameters = ReportParameters {
+        from,
+        to,
+        bucket: bucket.into(),
+    };
+
+    match ReportsService::report(parameters, app_state.database_connection()).await {
+        Ok(report) => Json(FinancialReport::new(report, from, to, bucket)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
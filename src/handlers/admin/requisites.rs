@@ -1,6 +1,6 @@
 use crate::{
     errors::AppError,
-    extractors::admin_jwt::AdminAuthJWT,
+    extractors::admin_jwt::{ManageCurrency, Permitted},
     services::requisites::{Service as RequisitesService, SetRequisitesParameters},
 };
 use axum::{
@@ -24,6 +24,7 @@ pub struct SetRequisitesDataRequest {
     data: Option<String>,
 }
 
+//? Requisites are where orders get paid to, managing currency covers them
 #[utoipa::path(
     patch,
     path = "/api/admin/requisites",
@@ -36,12 +37,12 @@ pub struct SetRequisitesDataRequest {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_currency"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn set_data(
-    AdminAuthJWT(user): AdminAuthJWT,
+    Permitted(user, _): Permitted<ManageCurrency>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<SetRequisitesDataRequest>,
 ) -> Response {
@@ -1,12 +1,20 @@
 use crate::{
     errors::AppError,
-    extractors::admin_jwt::{AdminAuthJWT, ModeratorAuthJWT},
+    extractors::{
+        admin_jwt::{ManageReviews, Permitted},
+        pagination::{PageQuery, Pagination},
+    },
+    handlers::{
+        pagination::Paged,
+        reviews::users::{Review, ReviewState},
+    },
     services::reviews::{
-        AddVideoReviewParameters, Service as ReviewsService, UpdateVideoReviewParameters,
+        AddVideoReviewParameters, ModerateReviewParameters, ReplyToReviewParameters,
+        ReviewDecision, Service as ReviewsService, UpdateVideoReviewParameters,
     },
 };
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
@@ -15,7 +23,7 @@ use entity::video_review::Model as VideoReviewModel;
 use sea_orm::TransactionTrait;
 
 use std::sync::Arc;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::state::AppState;
 
@@ -31,12 +39,12 @@ use crate::state::AppState;
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_reviews"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn add_video_review(
-    AdminAuthJWT(user): AdminAuthJWT,
+    Permitted(user, _): Permitted<ManageReviews>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<AddVideoReviewRequest>,
 ) -> Response {
@@ -75,12 +83,12 @@ pub async fn add_video_review(
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_reviews"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn remove_video_review(
-    AdminAuthJWT(user): AdminAuthJWT,
+    Permitted(user, _): Permitted<ManageReviews>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<RemoveVideoReviewRequest>,
 ) -> Response {
@@ -157,12 +165,12 @@ pub struct RemoveReviewRequest {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_reviews"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn remove_review(
-    ModeratorAuthJWT(moderator): ModeratorAuthJWT,
+    Permitted(moderator, _): Permitted<ManageReviews>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<RemoveVideoReviewRequest>,
 ) -> Response {
@@ -207,12 +215,12 @@ pub struct UpdateVideoReviewRequest {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_reviews"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn update_video_review(
-    AdminAuthJWT(user): AdminAuthJWT,
+    Permitted(user, _): Permitted<ManageReviews>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<UpdateVideoReviewRequest>,
 ) -> Response {
@@ -245,3 +253,178 @@ pub async fn update_video_review(
         Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
     }
 }
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReviewQueueFilter {
+    //? Pending by default
+    status: Option<ReviewState>,
+}
+
+//? Oldest reviews come first unless another direction is requested
+#[utoipa::path(
+    get,
+    path = "/api/admin/review",
+    responses(
+        (status = 200, description = "Reviews were successfully retrieved", body = PagedReviews),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        PageQuery,
+        ReviewQueueFilter
+    ),
+    security(
+        ("jwt_admin" = ["manage_reviews"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn review_queue(
+    Permitted(moderator, _): Permitted<ManageReviews>,
+    State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
+    Query(filter): Query<ReviewQueueFilter>,
+) -> Response {
+    let status = filter.status.unwrap_or(ReviewState::Pending).into();
+
+    let page = page.oldest_first();
+
+    match ReviewsService::by_status(status, &page, app_state.database_connection()).await {
+        Ok(reviews) => Json(Into::<Paged<Review>>::into(reviews)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+async fn moderate(app_state: &AppState, parameters: ModerateReviewParameters) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => match ReviewsService::moderate(parameters, &transaction).await {
+            Ok(review) => {
+                if let Err(cause) = transaction.commit().await {
+                    return AppError::InternalServerError(Box::new(cause)).into_response();
+                }
+                Json(Into::<Review>::into(review)).into_response()
+            }
+            Err(cause) => Into::<AppError>::into(cause).into_response(),
+        },
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/admin/review/{id}/publish",
+    responses(
+        (status = 200, description = "Review was successfully published",  body = Review),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 404, description = "Review was not found",               body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Review id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_reviews"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn publish_review(
+    Permitted(moderator, _): Permitted<ManageReviews>,
+    State(app_state): State<Arc<AppState>>,
+    Path(review_id): Path<i64>,
+) -> Response {
+    let parameters = ModerateReviewParameters {
+        review_id,
+        admin_id: moderator.id,
+        decision: ReviewDecision::Publish,
+    };
+
+    moderate(&app_state, parameters).await
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct RejectReviewRequest {
+    pub reason: Option<String>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/admin/review/{id}/reject",
+    request_body(content = Option<RejectReviewRequest>, description = "Optional rejection reason"),
+    responses(
+        (status = 200, description = "Review was successfully rejected",   body = Review),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 404, description = "Review was not found",               body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Review id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_reviews"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn reject_review(
+    Permitted(moderator, _): Permitted<ManageReviews>,
+    State(app_state): State<Arc<AppState>>,
+    Path(review_id): Path<i64>,
+    payload: Option<Json<RejectReviewRequest>>,
+) -> Response {
+    let parameters = ModerateReviewParameters {
+        review_id,
+        admin_id: moderator.id,
+        decision: ReviewDecision::Reject(payload.and_then(|Json(payload)| payload.reason)),
+    };
+
+    moderate(&app_state, parameters).await
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct ReplyToReviewRequest {
+    //? Null removes the reply
+    pub reply: Option<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/review/{id}/reply",
+    request_body = ReplyToReviewRequest,
+    responses(
+        (status = 200, description = "Reply was successfully saved",       body = Review),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 404, description = "Review was not found",               body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Review id")
+    ),
+    security(
+        ("jwt_admin" = ["manage_reviews"])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn reply_to_review(
+    Permitted(moderator, _): Permitted<ManageReviews>,
+    State(app_state): State<Arc<AppState>>,
+    Path(review_id): Path<i64>,
+    Json(payload): Json<ReplyToReviewRequest>,
+) -> Response {
+    let parameters = ReplyToReviewParameters {
+        review_id,
+        reply: payload.reply,
+    };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => match ReviewsService::reply(parameters, &transaction).await {
+            Ok(review) => {
+                if let Err(cause) = transaction.commit().await {
+                    return AppError::InternalServerError(Box::new(cause)).into_response();
+                }
+                Json(Into::<Review>::into(review)).into_response()
+            }
+            Err(cause) => Into::<AppError>::into(cause).into_response(),
+        },
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     errors::AppError,
-    extractors::admin_jwt::AdminAuthJWT,
+    extractors::admin_jwt::{ManageStaff, Permitted},
     services::social::{Service as SocialService, SetSocialParameters},
 };
 use axum::{
@@ -24,6 +24,7 @@ pub struct SetSocialUrlRequest {
     url: Option<String>,
 }
 
+//? Social links have no permission of their own, managing staff covers them
 #[utoipa::path(
     patch,
     path = "/api/admin/social",
@@ -36,12 +37,12 @@ pub struct SetSocialUrlRequest {
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
-        ("jwt_admin" = [])
+        ("jwt_admin" = ["manage_staff"])
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn set_url(
-    AdminAuthJWT(user): AdminAuthJWT,
+    Permitted(user, _): Permitted<ManageStaff>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<SetSocialUrlRequest>,
 ) -> Response {
@@ -0,0 +1,204 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::TransactionTrait;
+use std::{fmt::Debug, sync::Arc};
+use utoipa::ToSchema;
+
+use crate::{
+    errors::AppError,
+    extractors::admin_jwt::{ManageStaff, Permitted, StaffAuthJWT},
+    handlers::auth::admins::TwoFactorEnrolment,
+    services::auth::two_factor::Service as TwoFactorService,
+    state::AppState,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    //? Current totp code or one of the recovery codes
+    pub code: String,
+}
+
+//? This is manual for hiding codes in logs
+impl Debug for TwoFactorCodeRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwoFactorCodeRequest")
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema)]
+pub struct TwoFactorPolicy {
+    //? Staff without 2fa have to enrol on their next login
+    pub required: bool,
+}
+
+//? Previous unconfirmed enrolment is replaced
+#[utoipa::path(
+    post,
+    path = "/api/admin/self/two-factor",
+    responses(
+        (status = 200, description = "Two-factor enrolment was successfully started", body = TwoFactorEnrolment),
+        (status = 401, description = "Unauthorized",                                 body = Details),
+        (status = 409, description = "Two-factor authentication is already enabled", body = Details),
+        (status = 500, description = "Internal Server Error",                        body = Details),
+    ),
+    security(
+        ("jwt_admin" = [])
+    )
+)]
+#[tracing::instrument(skip(staff, app_state), fields(staff_id = staff.id))]
+pub async fn begin_enrolment(
+    StaffAuthJWT(staff): StaffAuthJWT,
+    State(app_state): State<Arc<AppState>>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match TwoFactorService::begin_enrolment(
+                staff,
+                app_state.configuration().totp_issuer(),
+                &transaction,
+            )
+            .await
+            {
+                Ok(enrolment) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<TwoFactorEnrolment>::into(enrolment)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/self/two-factor/confirm",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication was successfully enabled"),
+        (status = 401, description = "Unauthorized or bad code",                             body = Details),
+        (status = 409, description = "Two-factor authentication is enabled or not enrolled", body = Details),
+        (status = 500, description = "Internal Server Error",                                body = Details),
+    ),
+    security(
+        ("jwt_admin" = [])
+    )
+)]
+#[tracing::instrument(skip(staff, app_state), fields(staff_id = staff.id))]
+pub async fn confirm_enrolment(
+    StaffAuthJWT(staff): StaffAuthJWT,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Response {
+    match TwoFactorService::confirm_enrolment(staff, &payload.code, app_state.database_connection())
+        .await
+    {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/self/two-factor",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication was successfully disabled"),
+        (status = 401, description = "Unauthorized or bad code",                      body = Details),
+        (status = 403, description = "Two-factor authentication is required",         body = Details),
+        (status = 409, description = "Two-factor authentication is not enabled",      body = Details),
+        (status = 500, description = "Internal Server Error",                         body = Details),
+    ),
+    security(
+        ("jwt_admin" = [])
+    )
+)]
+#[tracing::instrument(skip(staff, app_state), fields(staff_id = staff.id))]
+pub async fn disable(
+    StaffAuthJWT(staff): StaffAuthJWT,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match TwoFactorService::disable(staff, &payload.code, &transaction).await {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/two-factor/policy",
+    responses(
+        (status = 200, description = "Policy was successfully retrieved", body = TwoFactorPolicy),
+        (status = 401, description = "Unauthorized",                      body = Details),
+        (status = 403, description = "Permission required",               body = Details),
+        (status = 500, description = "Internal Server Error",             body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(admin, app_state), fields(admin_id = admin.id))]
+pub async fn policy(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+) -> Response {
+    match TwoFactorService::is_required(app_state.database_connection()).await {
+        Ok(required) => Json(TwoFactorPolicy { required }).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+//? Requiring 2fa logs out every staff account which has not enabled it
+#[utoipa::path(
+    put,
+    path = "/api/admin/two-factor/policy",
+    request_body = TwoFactorPolicy,
+    responses(
+        (status = 204, description = "Policy was successfully changed"),
+        (status = 401, description = "Unauthorized",                      body = Details),
+        (status = 403, description = "Permission required",               body = Details),
+        (status = 500, description = "Internal Server Error",             body = Details),
+    ),
+    security(
+        ("jwt_admin" = ["manage_staff"])
+    )
+)]
+#[tracing::instrument(skip(admin, app_state), fields(admin_id = admin.id))]
+pub async fn set_policy(
+    Permitted(admin, _): Permitted<ManageStaff>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<TwoFactorPolicy>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match TwoFactorService::set_required(payload.required, &transaction).await {
+                Ok(()) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
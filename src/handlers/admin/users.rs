@@ -1,6 +1,6 @@
 use crate::errors::AppError;
 use crate::services::users::Service as UserService;
-use crate::{extractors::admin_jwt::ModeratorAuthJWT, state::AppState};
+use crate::{extractors::admin_jwt::StaffAuthJWT, state::AppState};
 use axum::response::IntoResponse;
 use axum::{
     extract::{Query, State},
@@ -38,7 +38,7 @@ pub struct RegistrationsInPeriodResponse {
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn registrations_in_period(
-    ModeratorAuthJWT(admin): ModeratorAuthJWT,
+    StaffAuthJWT(admin): StaffAuthJWT,
     State(app_state): State<Arc<AppState>>,
     Query(bounds): Query<TimeBounds>,
 ) -> axum::response::Response {
@@ -1,15 +1,33 @@
 use axum::{
     extract::State,
+    http::StatusCode,
     response::{IntoResponse, Response},
     routing::post,
     Json,
 };
-use sea_orm::TransactionTrait;
+use entity::{admin::Model as AdminModel, sea_orm_active_enums::LoginOutcome};
+use sea_orm::{DatabaseTransaction, TransactionTrait};
 use utoipa::ToSchema;
 
 use crate::{
     errors::AppError,
-    services::auth::{AdminCredentials, GenerateAdminJwtParameters, Jwt, Service as AuthService},
+    extractors::{admin_jwt::StaffAuthJWT, client::Client},
+    handlers::auth::RefreshTokenRequest,
+    services::{
+        admin::login_audit::{LoginAttempt, Service as LoginAuditService},
+        auth::{
+            two_factor::{
+                Enrolment, Service as TwoFactorService, ServiceError as TwoFactorServiceError,
+            },
+            AdminCredentials, GenerateAdminJwtParameters, Jwt, RedeemPasswordResetParameters,
+            Service as AuthService, ServiceError as AuthServiceError,
+        },
+        lockout::{Attempt, Check, LockoutPolicy, Service as LockoutService},
+        sessions::{
+            Audience, IssuedSession, OpenSessionParameters, RefreshSessionParameters,
+            Service as SessionService, Subject,
+        },
+    },
     state::AppState,
 };
 use std::{fmt::Debug, sync::Arc};
@@ -32,6 +50,36 @@ impl Debug for Credentials {
 pub struct AdminLoginResponse {
     #[schema(value_type = String)]
     pub token: Jwt,
+    //? Exchanged for a new pair at /api/auth/admin/refresh
+    pub refresh_token: String,
+}
+
+impl AdminLoginResponse {
+    fn issue(session: IssuedSession, app_state: &AppState) -> Response {
+        let parameters = GenerateAdminJwtParameters {
+            admin_id: session.subject.id(),
+            session_id: session.id,
+            keys: app_state.keys(),
+            ttl: app_state.configuration().jwt_ttl(),
+        };
+
+        match AuthService::admin_jwt(parameters) {
+            Ok(token) => Json(Self {
+                token,
+                refresh_token: session.refresh_token,
+            })
+            .into_response(),
+            Err(cause) => Into::<AppError>::into(cause).into_response(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct TwoFactorChallenge {
+    //? Sent to /api/auth/admin/login/totp together with the code
+    pub challenge_token: String,
+    //? Account has to enrol at /api/auth/admin/login/enrol first
+    pub enrolment_required: bool,
 }
 
 #[utoipa::path(
@@ -40,39 +88,264 @@ pub struct AdminLoginResponse {
     request_body = Credentials,
     responses(
         (status = 200, description = "Admin or moderator was successfully authenticated", body = AdminLoginResponse),
+        (status = 202, description = "Password was accepted, two-factor code is required", body = TwoFactorChallenge),
         (status = 500, description = "Internal server error", body = Details),
         (status = 401, description = "Bad username or password", body = Details),
+        (status = 403, description = "Account is disabled or its password has to be reset", body = Details),
+        (status = 429, description = "Too many failed attempts, retry after the lockout", body = Details),
         (status = 400, description = "Bad request", body = Details),
     ),
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn login(
     State(app_state): State<Arc<AppState>>,
+    client: Client,
     Json(admin_or_moderator_credentials): Json<Credentials>,
 ) -> Response {
+    let login = admin_or_moderator_credentials.login;
+    let attempt = Attempt {
+        login: &login,
+        ip: client.lockout_ip(),
+    };
+
+    if let Some(retry_after) = locked_out(&attempt, &app_state).await {
+        audit(&login, LoginOutcome::LockedOut, &client, &app_state).await;
+        return AppError::TooManyRequests(retry_after).into_response();
+    }
+
     match app_state.database_connection().begin().await {
         Ok(transaction) => {
             let credentials = AdminCredentials {
-                login: admin_or_moderator_credentials.login,
+                login: login.clone(),
                 password: admin_or_moderator_credentials.password,
             };
 
-            match AuthService::login_admin(credentials, &transaction).await {
-                Ok(admin_or_moderator) => {
-                    if let Err(cause) = transaction.commit().await {
-                        return AppError::InternalServerError(Box::new(cause)).into_response();
+            let admin_or_moderator = match AuthService::login_admin(credentials, &transaction).await
+            {
+                Ok(admin_or_moderator) => admin_or_moderator,
+                Err(cause) => {
+                    let outcome = match cause {
+                        AuthServiceError::Unauthorized => {
+                            count_failure(&attempt, &app_state).await;
+                            Some(LoginOutcome::BadCredentials)
+                        }
+                        AuthServiceError::Disabled => Some(LoginOutcome::Disabled),
+                        AuthServiceError::PasswordResetRequired => {
+                            Some(LoginOutcome::PasswordResetRequired)
+                        }
+                        _ => None,
+                    };
+                    if let Some(outcome) = outcome {
+                        audit(&login, outcome, &client, &app_state).await;
                     }
+                    return Into::<AppError>::into(cause).into_response();
+                }
+            };
 
-                    let parameters = GenerateAdminJwtParameters {
-                        admin_id: admin_or_moderator.id,
-                        secret: app_state.configuration().jwt_secret(),
-                        ttl: app_state.configuration().jwt_ttl(),
-                    };
+            let enrolment_required = match admin_or_moderator.totp_enabled_at {
+                Some(_) => Some(false),
+                None => match TwoFactorService::is_required(&transaction).await {
+                    Ok(true) => Some(true),
+                    Ok(false) => None,
+                    Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                },
+            };
 
-                    match AuthService::admin_jwt(parameters) {
-                        Ok(token) => Json(AdminLoginResponse { token }).into_response(),
-                        Err(cause) => Into::<AppError>::into(cause).into_response(),
+            //? Failures are not reset here, otherwise every correct password
+            //? would give one more free guess of the code
+            if let Some(enrolment_required) = enrolment_required {
+                return match TwoFactorService::open_challenge(
+                    admin_or_moderator.id,
+                    app_state.configuration().two_factor_challenge_ttl_seconds(),
+                    app_state.redis_client(),
+                )
+                .await
+                {
+                    Ok(challenge_token) => {
+                        audit(&login, LoginOutcome::Challenged, &client, &app_state).await;
+                        (
+                            StatusCode::ACCEPTED,
+                            Json(TwoFactorChallenge {
+                                challenge_token,
+                                enrolment_required,
+                            }),
+                        )
+                            .into_response()
                     }
+                    Err(cause) => Into::<AppError>::into(cause).into_response(),
+                };
+            }
+
+            //? AppError is not Send so it must not live across the awaits below
+            let session = match open_session(admin_or_moderator, transaction, &app_state).await {
+                Ok(session) => session,
+                Err(cause) => return cause.into_response(),
+            };
+
+            count_success(&attempt, &app_state).await;
+            audit(&login, LoginOutcome::Succeeded, &client, &app_state).await;
+            AdminLoginResponse::issue(session, &app_state)
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+async fn open_session(
+    admin_or_moderator: AdminModel,
+    transaction: DatabaseTransaction,
+    app_state: &AppState,
+) -> Result<IssuedSession, AppError> {
+    let admin_or_moderator = AuthService::record_login(admin_or_moderator, &transaction).await?;
+
+    let parameters = OpenSessionParameters {
+        subject: Subject::Staff(admin_or_moderator.id),
+        ttl_seconds: app_state.configuration().refresh_token_ttl_seconds(),
+    };
+
+    let session = SessionService::open(parameters, &transaction).await?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|cause| AppError::InternalServerError(Box::new(cause)))?;
+
+    Ok(session)
+}
+
+fn lockout_policy(app_state: &AppState) -> LockoutPolicy {
+    let configuration = app_state.configuration();
+    LockoutPolicy {
+        attempts_per_login: configuration.staff_login_attempts_per_login(),
+        attempts_per_ip: configuration.staff_login_attempts_per_ip(),
+        window_seconds: configuration.staff_login_attempts_window_seconds(),
+        base_seconds: configuration.staff_login_lockout_base_seconds(),
+        max_seconds: configuration.staff_login_lockout_max_seconds(),
+    }
+}
+
+//? Lockout must not take staff login down with redis
+async fn locked_out(attempt: &Attempt<'_>, app_state: &AppState) -> Option<u64> {
+    match LockoutService::check(
+        attempt,
+        &lockout_policy(app_state),
+        app_state.redis_client(),
+    )
+    .await
+    {
+        Ok(Check::Locked { retry_after }) => Some(retry_after),
+        Ok(Check::Allowed) => None,
+        Err(cause) => {
+            tracing::warn!(%cause, "Failed to check staff login lockout!");
+            None
+        }
+    }
+}
+
+async fn count_failure(attempt: &Attempt<'_>, app_state: &AppState) {
+    if let Err(cause) = LockoutService::failed(
+        attempt,
+        &lockout_policy(app_state),
+        app_state.redis_client(),
+    )
+    .await
+    {
+        tracing::warn!(%cause, "Failed to count failed staff login!");
+    }
+}
+
+async fn count_success(attempt: &Attempt<'_>, app_state: &AppState) {
+    if let Err(cause) = LockoutService::succeeded(attempt, app_state.redis_client()).await {
+        tracing::warn!(%cause, "Failed to reset failed staff logins!");
+    }
+}
+
+async fn audit(login: &str, outcome: LoginOutcome, client: &Client, app_state: &AppState) {
+    let attempt = LoginAttempt {
+        login: login.to_owned(),
+        outcome,
+        source: client.source.clone(),
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+    };
+
+    if let Err(cause) = LoginAuditService::record(attempt, app_state.database_connection()).await {
+        tracing::error!(%cause, "Failed to write staff login audit!");
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct ChallengeRequest {
+    pub challenge_token: String,
+}
+
+//? This is manual for hiding challenge token in logs
+impl Debug for ChallengeRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChallengeRequest").finish_non_exhaustive()
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct TwoFactorEnrolment {
+    //? Base32 secret for apps which can not scan the provisioning uri
+    pub secret: String,
+    //? otpauth:// uri which is rendered as a QR code
+    pub provisioning_uri: String,
+    //? Every code can be used once instead of a totp code, they are shown only now
+    pub recovery_codes: Vec<String>,
+}
+
+impl From<Enrolment> for TwoFactorEnrolment {
+    fn from(value: Enrolment) -> Self {
+        Self {
+            secret: value.secret,
+            provisioning_uri: value.provisioning_uri,
+            recovery_codes: value.recovery_codes,
+        }
+    }
+}
+
+//? Enrolment is confirmed by the first code sent to /api/auth/admin/login/totp
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/login/enrol",
+    request_body = ChallengeRequest,
+    responses(
+        (status = 200, description = "Two-factor enrolment was successfully started", body = TwoFactorEnrolment),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Challenge has expired", body = Details),
+        (status = 403, description = "Account is disabled", body = Details),
+        (status = 409, description = "Two-factor authentication is already enabled", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn login_enrol(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<ChallengeRequest>,
+) -> Response {
+    let staff_id =
+        match TwoFactorService::peek_challenge(&payload.challenge_token, app_state.redis_client())
+            .await
+        {
+            Ok(staff_id) => staff_id,
+            Err(cause) => return Into::<AppError>::into(cause).into_response(),
+        };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match TwoFactorService::enrol_on_login(
+                staff_id,
+                app_state.configuration().totp_issuer(),
+                &transaction,
+            )
+            .await
+            {
+                Ok(enrolment) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<TwoFactorEnrolment>::into(enrolment)).into_response()
                 }
                 Err(cause) => Into::<AppError>::into(cause).into_response(),
             }
@@ -81,6 +354,247 @@ pub async fn login(
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    //? Current totp code or one of the recovery codes
+    pub code: String,
+}
+
+//? This is manual for hiding codes in logs
+impl Debug for TwoFactorLoginRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TwoFactorLoginRequest")
+            .finish_non_exhaustive()
+    }
+}
+
+//? Challenge is consumed even by a wrong code
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/login/totp",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Admin or moderator was successfully authenticated", body = AdminLoginResponse),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Bad code or challenge has expired", body = Details),
+        (status = 403, description = "Account is disabled", body = Details),
+        (status = 409, description = "Two-factor authentication was not enrolled", body = Details),
+        (status = 429, description = "Too many failed attempts, retry after the lockout", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn login_totp(
+    State(app_state): State<Arc<AppState>>,
+    client: Client,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Response {
+    let staff_id =
+        match TwoFactorService::take_challenge(&payload.challenge_token, app_state.redis_client())
+            .await
+        {
+            Ok(staff_id) => staff_id,
+            Err(cause) => return Into::<AppError>::into(cause).into_response(),
+        };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let admin_or_moderator =
+                match TwoFactorService::challenged(staff_id, &transaction).await {
+                    Ok(admin_or_moderator) => admin_or_moderator,
+                    Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                };
+
+            let login = admin_or_moderator.login.clone();
+            let attempt = Attempt {
+                login: &login,
+                ip: client.lockout_ip(),
+            };
+
+            if let Some(retry_after) = locked_out(&attempt, &app_state).await {
+                audit(&login, LoginOutcome::LockedOut, &client, &app_state).await;
+                return AppError::TooManyRequests(retry_after).into_response();
+            }
+
+            let admin_or_moderator = match TwoFactorService::complete_login(
+                admin_or_moderator,
+                &payload.code,
+                &transaction,
+            )
+            .await
+            {
+                Ok(admin_or_moderator) => admin_or_moderator,
+                Err(TwoFactorServiceError::BadCode) => {
+                    count_failure(&attempt, &app_state).await;
+                    audit(&login, LoginOutcome::BadTwoFactorCode, &client, &app_state).await;
+                    return AppError::BadTwoFactorCode.into_response();
+                }
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let session = match open_session(admin_or_moderator, transaction, &app_state).await {
+                Ok(session) => session,
+                Err(cause) => return cause.into_response(),
+            };
+
+            count_success(&attempt, &app_state).await;
+            audit(&login, LoginOutcome::Succeeded, &client, &app_state).await;
+            AdminLoginResponse::issue(session, &app_state)
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+//? Refresh token is rotated, the one sent here can not be used again
+//? Transaction is not used so that reuse of a rotated token revokes the session
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/refresh",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Token pair was successfully refreshed", body = AdminLoginResponse),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Session has expired or was revoked", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn refresh(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Response {
+    let parameters = RefreshSessionParameters {
+        refresh_token: payload.refresh_token,
+        audience: Audience::Staff,
+        ttl_seconds: app_state.configuration().refresh_token_ttl_seconds(),
+    };
+
+    match SessionService::refresh(parameters, app_state.database_connection()).await {
+        Ok(session) => AdminLoginResponse::issue(session, &app_state),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/logout",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 204, description = "Session was successfully revoked"),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Refresh token is not valid", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn logout(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Response {
+    match SessionService::revoke(
+        &payload.refresh_token,
+        Audience::Staff,
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/logout-everywhere",
+    responses(
+        (status = 204, description = "All sessions of the account were successfully revoked"),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+    ),
+    security(
+        ("jwt_admin" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn logout_everywhere(
+    StaffAuthJWT(staff): StaffAuthJWT,
+    State(app_state): State<Arc<AppState>>,
+) -> Response {
+    match SessionService::revoke_all(Subject::Staff(staff.id), app_state.database_connection())
+        .await
+    {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct PasswordResetRequest {
+    pub login: String,
+    pub token: String,
+    pub new_password: String,
+}
+
+impl Debug for PasswordResetRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordResetRequest")
+            .field("login", &self.login)
+            .finish()
+    }
+}
+
+//? Token is issued by an admin and can be used only once
+#[utoipa::path(
+    post,
+    path = "/api/auth/admin/password-reset",
+    request_body = PasswordResetRequest,
+    responses(
+        (status = 204, description = "Password was successfully reset"),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 401, description = "Bad login or token", body = Details),
+        (status = 403, description = "Account is disabled", body = Details),
+        (status = 410, description = "Token has expired", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn reset_password(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Response {
+    if payload.new_password.is_empty() {
+        return AppError::EmptyCredentials.into_response();
+    }
+
+    let parameters = RedeemPasswordResetParameters {
+        login: payload.login,
+        token: payload.token,
+        new_password: payload.new_password,
+    };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => match AuthService::redeem_password_reset(parameters, &transaction).await
+        {
+            Ok(()) => {
+                if let Err(cause) = transaction.commit().await {
+                    return AppError::InternalServerError(Box::new(cause)).into_response();
+                }
+                StatusCode::NO_CONTENT.into_response()
+            }
+            Err(cause) => Into::<AppError>::into(cause).into_response(),
+        },
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
 pub fn router() -> axum::Router<Arc<AppState>> {
-    axum::Router::new().route("/login", post(login))
+    axum::Router::new()
+        .route("/login", post(login))
+        .route("/login/enrol", post(login_enrol))
+        .route("/login/totp", post(login_totp))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/logout-everywhere", post(logout_everywhere))
+        .route("/password-reset", post(reset_password))
 }
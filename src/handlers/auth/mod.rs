@@ -1,2 +1,63 @@
+use crate::{services::auth::keys::PublicKey, state::AppState};
+use axum::{extract::State, routing::get, Json};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
 pub mod admins;
 pub mod users;
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+//? This is manual for hiding refresh token in logs
+impl std::fmt::Debug for RefreshTokenRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshTokenRequest")
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ToSchema)]
+pub struct PublicKeyResponse {
+    pub kid: String,
+    //? RS256 or EdDSA
+    pub alg: String,
+    pub pem: String,
+}
+
+impl From<&PublicKey> for PublicKeyResponse {
+    fn from(value: &PublicKey) -> Self {
+        Self {
+            kid: value.kid.clone(),
+            alg: format!("{:?}", value.alg),
+            pem: value.pem.clone(),
+        }
+    }
+}
+
+//? Lets other services verify access tokens without the secret
+//? Empty when tokens are signed with hs256
+#[utoipa::path(
+    get,
+    path = "/api/auth/keys",
+    responses(
+        (status = 200, description = "Public keys which access tokens are verified with", body = [PublicKeyResponse]),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn public_keys(State(app_state): State<Arc<AppState>>) -> Json<Vec<PublicKeyResponse>> {
+    Json(
+        app_state
+            .keys()
+            .published()
+            .iter()
+            .map(Into::into)
+            .collect(),
+    )
+}
+
+pub fn router() -> axum::Router<Arc<AppState>> {
+    axum::Router::new().route("/keys", get(public_keys))
+}
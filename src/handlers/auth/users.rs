@@ -1,16 +1,24 @@
 use crate::{
     errors::AppError,
+    extractors::user_jwt::AuthJWT,
+    handlers::auth::RefreshTokenRequest,
     openid::VerifyForm,
     services::{
+        admin::blacklist::Service as BlacklistService,
         auth::{GenerateUserJwtParameters, Jwt, Service as AuthService},
+        sessions::{
+            Audience, IssuedSession, OpenSessionParameters, RefreshSessionParameters,
+            Service as SessionService, Subject,
+        },
         users::Service as UserService,
     },
     state::AppState,
 };
 use axum::{
     extract::{Query, State},
+    http::StatusCode,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json,
 };
 use sea_orm::ActiveModelTrait;
@@ -27,6 +35,28 @@ pub struct LoginLinkResponse {
 pub struct JwtResponse {
     #[schema(value_type = String)]
     pub token: Jwt,
+    //? Exchanged for a new pair at /api/auth/user/refresh
+    pub refresh_token: String,
+}
+
+impl JwtResponse {
+    fn issue(session: IssuedSession, app_state: &AppState) -> Response {
+        let parameters = GenerateUserJwtParameters {
+            steam_id: session.subject.id(),
+            session_id: session.id,
+            keys: app_state.keys(),
+            ttl: app_state.configuration().jwt_ttl(),
+        };
+
+        match AuthService::user_jwt(parameters) {
+            Ok(token) => Json(Self {
+                token,
+                refresh_token: session.refresh_token,
+            })
+            .into_response(),
+            Err(cause) => Into::<AppError>::into(cause).into_response(),
+        }
+    }
 }
 
 #[utoipa::path(
@@ -51,7 +81,7 @@ pub async fn login_link(State(app_state): State<Arc<AppState>>) -> impl IntoResp
     ),
     responses(
         (status = 200, description = "User was successfully authenticated", body = JwtResponse),
-        (status = 403, description = "Steam denied user registration", body = Details),
+        (status = 403, description = "Steam denied user registration or user is blacklisted", body = Details),
         (status = 500, description = "Internal server error", body = Details),
         (status = 400, description = "Bad request", body = Details),
     ),
@@ -161,23 +191,124 @@ pub async fn login(
                 Err(cause) => return Into::<AppError>::into(cause).into_response(),
             };
 
-            let parameters = GenerateUserJwtParameters {
-                steam_id: user.steam_id,
-                secret: app_state.configuration().jwt_secret(),
-                ttl: app_state.configuration().jwt_ttl(),
+            //? Fully banned users do not get a token at all
+            match BlacklistService::active(user.steam_id, app_state.database_connection()).await {
+                Ok(Some(ban)) if BlacklistService::is_full(&ban) => {
+                    return AppError::UserBlacklisted {
+                        reason: ban.reason,
+                        expires_at: ban.expires_at,
+                    }
+                    .into_response()
+                }
+                Ok(_) => {}
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let parameters = OpenSessionParameters {
+                subject: Subject::User(user.steam_id),
+                ttl_seconds: app_state.configuration().refresh_token_ttl_seconds(),
             };
 
-            return match AuthService::user_jwt(parameters).map_err(Into::<AppError>::into) {
-                Ok(jwt) => Json(JwtResponse { token: jwt }).into_response(),
-                Err(cause) => cause.into_response(),
+            return match SessionService::open(parameters, app_state.database_connection()).await {
+                Ok(session) => JwtResponse::issue(session, &app_state),
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
             };
         }
         Err(cause) => return AppError::InternalServerError(Box::new(cause)).into_response(),
     };
 }
 
+//? Refresh token is rotated, the one sent here can not be used again
+//? Transaction is not used so that reuse of a rotated token revokes the session
+#[utoipa::path(
+    post,
+    path = "/api/auth/user/refresh",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Token pair was successfully refreshed", body = JwtResponse),
+        (status = 401, description = "Session has expired or was revoked", body = Details),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn refresh(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Response {
+    let parameters = RefreshSessionParameters {
+        refresh_token: payload.refresh_token,
+        audience: Audience::User,
+        ttl_seconds: app_state.configuration().refresh_token_ttl_seconds(),
+    };
+
+    match SessionService::refresh(parameters, app_state.database_connection()).await {
+        Ok(session) => JwtResponse::issue(session, &app_state),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/user/logout",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 204, description = "Session was successfully revoked"),
+        (status = 401, description = "Refresh token is not valid", body = Details),
+        (status = 500, description = "Internal server error", body = Details),
+        (status = 400, description = "Bad request", body = Details),
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn logout(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Response {
+    match SessionService::revoke(
+        &payload.refresh_token,
+        Audience::User,
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/user/logout-everywhere",
+    responses(
+        (status = 204, description = "All sessions of the user were successfully revoked"),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal server error", body = Details),
+    ),
+    security(
+        ("jwt_user" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn logout_everywhere(
+    AuthJWT(user): AuthJWT,
+    State(app_state): State<Arc<AppState>>,
+) -> Response {
+    match SessionService::revoke_all(
+        Subject::User(user.steam_id),
+        app_state.database_connection(),
+    )
+    .await
+    {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
 pub fn router() -> axum::Router<Arc<AppState>> {
     axum::Router::new()
         .route("/link", get(login_link))
         .route("/callback", get(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/logout-everywhere", post(logout_everywhere))
 }
@@ -1,18 +1,49 @@
 use crate::handlers::admin::currency::Currency;
 
-use crate::services::currency::Service as CurrencyService;
+use crate::services::{
+    currency::{Bucket, RateHistoryParameters, RatePoint, Service as CurrencyService},
+    orders::{Service as OrderService, ServiceError as OrderServiceError},
+    quotes::{IssueQuoteParameters, Service as QuotesService},
+};
 
-use crate::{errors::AppError, state::AppState};
+use crate::{
+    errors::{AppError, Details},
+    extractors::{
+        rate_limit::{QuoteIssuing, RateLimited},
+        user_jwt::{AllowedTo, Ordering},
+    },
+    state::AppState,
+};
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json,
 };
 
-use sea_orm::TransactionTrait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{prelude::Decimal, TransactionTrait};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct QuoteRequest {
+    symbol: String,
+    #[schema(value_type = String)]
+    amount: Decimal,
+}
+
+#[derive(Debug, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct Quote {
+    quote_id: String,
+    symbol: String,
+    #[schema(value_type = String)]
+    amount: Decimal,
+    #[schema(value_type = String)]
+    rate: Decimal,
+    expires_at: NaiveDateTime,
+}
 
 #[utoipa::path(
     get,
@@ -82,8 +113,210 @@ pub async fn get_currency_rates(State(app_state): State<Arc<AppState>>) -> Respo
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/currency/quote",
+    request_body = QuoteRequest,
+    responses(
+        (status = 200, description = "Quote was successfully issued", body = Quote),
+        (status = 400, description = "Amount violates currency limits", body = ValidationDetails),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 403, description = "User is not allowed to order", body = Details),
+        (status = 404, description = "Currency was not found", body = Details),
+        (status = 429, description = "Too many quotes, see Retry-After", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    security(
+        ("jwt_user" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn quote(
+    AllowedTo(user, _): AllowedTo<Ordering>,
+    _limit: RateLimited<QuoteIssuing>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<QuoteRequest>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let currency_rate =
+                match CurrencyService::currency_rate(&payload.symbol, &transaction).await {
+                    Ok(currency_rate) => currency_rate,
+                    Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                };
+
+            //? Do not quote amounts which order creation would reject anyway
+            match OrderService::validate_amount(
+                &currency_rate,
+                user.steam_id,
+                payload.amount,
+                &transaction,
+            )
+            .await
+            {
+                Ok(violations) if violations.is_empty() => {}
+                Ok(violations) => {
+                    return Into::<AppError>::into(OrderServiceError::AmountInvalid(violations))
+                        .into_response()
+                }
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            let parameters = IssueQuoteParameters {
+                steam_id: user.steam_id,
+                symbol: currency_rate.symbol,
+                amount: payload.amount,
+                rate: currency_rate.rate,
+                secret: app_state.configuration().jwt_secret(),
+                ttl: app_state.configuration().quote_ttl_seconds(),
+            };
+
+            match QuotesService::issue(parameters) {
+                Ok((quote_id, claims)) => (
+                    StatusCode::OK,
+                    Json(Quote {
+                        quote_id,
+                        expires_at: claims.expires_at(),
+                        symbol: claims.symbol,
+                        amount: claims.amount,
+                        rate: claims.rate,
+                    }),
+                )
+                    .into_response(),
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, ToSchema, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateBucket {
+    Hour,
+    Day,
+}
+
+impl From<RateBucket> for Bucket {
+    fn from(value: RateBucket) -> Self {
+        match value {
+            RateBucket::Hour => Bucket::Hour,
+            RateBucket::Day => Bucket::Day,
+        }
+    }
+}
+
+#[derive(Debug, IntoParams, serde::Serialize, serde::Deserialize)]
+pub struct RateHistoryQuery {
+    //? Defaults to 30 days before `to`
+    from: Option<NaiveDateTime>,
+    //? Defaults to now
+    to: Option<NaiveDateTime>,
+    //? Without bucket every single change is returned, up to 1000 of them
+    //? Range is limited to 31 days, or to 366 days with daily buckets
+    bucket: Option<RateBucket>,
+}
+
+#[derive(Debug, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct RateHistoryPoint {
+    timestamp: NaiveDateTime,
+    #[schema(value_type = String)]
+    open: Decimal,
+    #[schema(value_type = String)]
+    high: Decimal,
+    #[schema(value_type = String)]
+    low: Decimal,
+    #[schema(value_type = String)]
+    close: Decimal,
+}
+
+impl From<RatePoint> for RateHistoryPoint {
+    fn from(value: RatePoint) -> Self {
+        Self {
+            timestamp: value.timestamp,
+            open: value.open,
+            high: value.high,
+            low: value.low,
+            close: value.close,
+        }
+    }
+}
+
+#[derive(Debug, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct RateHistory {
+    symbol: String,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    //? Rate which was in effect at `from`
+    #[schema(value_type = Option<String>)]
+    initial: Option<Decimal>,
+    points: Vec<RateHistoryPoint>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/currency/history/{symbol}",
+    responses(
+        (status = 200, description = "Currency rate history was successfully retrieved", body = RateHistory),
+        (status = 400, description = "Bad request", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("symbol" = String, Path, description = "Currency symbol"),
+        RateHistoryQuery
+    ),
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn currency_rate_history(
+    State(app_state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(query): Query<RateHistoryQuery>,
+) -> Response {
+    let to = query.to.unwrap_or_else(|| Utc::now().naive_utc());
+    let from = query.from.unwrap_or(to - Duration::days(30));
+
+    if from > to {
+        return AppError::BadRequest(Details {
+            details: String::from("`from` must not be later than `to`"),
+        })
+        .into_response();
+    }
+
+    let parameters = RateHistoryParameters {
+        symbol: symbol.clone(),
+        from,
+        to,
+        bucket: query.bucket.map(Into::into),
+    };
+
+    match CurrencyService::history(parameters, app_state.database_connection()).await {
+        Ok(history) => (
+            StatusCode::OK,
+            Json(RateHistory {
+                symbol,
+                from,
+                to,
+                initial: history.initial,
+                points: history
+                    .points
+                    .into_iter()
+                    .map(Into::<RateHistoryPoint>::into)
+                    .collect(),
+            }),
+        )
+            .into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
 pub fn router() -> axum::Router<Arc<AppState>> {
     axum::Router::new()
         .route("/", get(get_currency_rates))
         .route("/:id", get(get_currency_rate_by_id))
+        .route("/quote", post(quote))
+        .route("/history/:symbol", get(currency_rate_history))
 }
@@ -2,6 +2,7 @@ pub mod admin;
 pub mod auth;
 pub mod currency;
 pub mod orders;
+pub mod pagination;
 pub mod requisites;
 pub mod reviews;
 pub mod social;
@@ -1,4 +1,8 @@
-use crate::{state::AppState, Order};
+use crate::{
+    services::events::{OrderEvent, OrderEventKind},
+    state::AppState,
+    Order,
+};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
@@ -37,7 +41,10 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     tokio::spawn(async move {
         let connection = state.redis_client().get_async_connection().await.unwrap();
         let mut pubsub = connection.into_pubsub();
-        pubsub.subscribe("live_orders").await.unwrap();
+        pubsub
+            .subscribe(state.configuration().order_events_channel_name())
+            .await
+            .unwrap();
 
         for order in orders_to_send {
             tx.send(serde_json::to_string(&Into::<Order>::into(order)).unwrap_or_default())
@@ -46,10 +53,23 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
         }
 
         while let Some(msg) = pubsub.on_message().next().await {
-            if let Ok(payload) = msg.get_payload() {
-                if tx.send(payload).await.is_err() {
-                    break;
-                }
+            //? Live feed shows only succeeded orders
+            let order = match msg
+                .get_payload::<String>()
+                .ok()
+                .as_deref()
+                .and_then(OrderEvent::from_payload)
+            {
+                Some(event) if event.kind == OrderEventKind::Succeeded => event.order,
+                _ => continue,
+            };
+
+            if tx
+                .send(serde_json::to_string(&Into::<Order>::into(order)).unwrap_or_default())
+                .await
+                .is_err()
+            {
+                break;
             }
         }
     });
@@ -1,19 +1,29 @@
 use crate::{
     errors::AppError,
-    extractors::user_jwt::AuthJWT,
+    extractors::{
+        pagination::{PageQuery, Pagination},
+        rate_limit::{OrderCreation, RateLimited},
+        user_jwt::{AllowedTo, AuthJWT, Ordering},
+    },
+    handlers::pagination::Paged,
     services::{
+        assignment,
         chat::{SendMessageParameters, Sender, Service as ChatService},
         currency::Service as CurrencyService,
+        events::{OrderEvent, OrderEventKind, Service as EventsService},
         orders::{
             CancelOrderParameters, CreateOrderParameters, GetUserOrderParameters,
-            MayBePayedOrderParameters, Service as OrderService,
+            ListOrdersParameters, MayBePayedOrderParameters, OrderFilter, OrderSort,
+            Service as OrderService,
         },
+        pagination::PageRequest,
+        quotes::{RedeemQuoteParameters, Service as QuotesService},
     },
     state::AppState,
     Message, SendMessageResponse,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::{get, patch, post},
@@ -21,7 +31,10 @@ use axum::{
 };
 use chrono::NaiveDateTime as DateTime;
 use chrono::NaiveDateTime;
-use entity::{chat::Entity as ChatEntity, order::Model as OrderModel};
+use entity::{
+    chat::Entity as ChatEntity, order::Model as OrderModel,
+    order_status_history::Model as HistoryModel, sea_orm_active_enums::Status,
+};
 use redis::AsyncCommands;
 use sea_orm::{prelude::Decimal, ModelTrait, TransactionTrait};
 use std::sync::Arc;
@@ -36,6 +49,8 @@ pub struct CreateOrderRequest {
     amount: Decimal,
     currency: String,
     requisites_id: String,
+    //? Issued by POST /api/currency/quote. Without it the current rate is used
+    quote_id: Option<String>,
 }
 
 #[derive(ToSchema, serde::Serialize, serde::Deserialize)]
@@ -53,6 +68,7 @@ pub struct Order {
     pub currency_symbol: String,
     pub requisites_id: String,
     pub finished_at: Option<DateTime>,
+    pub flagged_at: Option<DateTime>,
 }
 
 impl From<OrderModel> for Order {
@@ -69,19 +85,134 @@ impl From<OrderModel> for Order {
             currency_symbol: value.currency_symbol,
             finished_at: value.finished_at,
             requisites_id: value.requisites_id.to_string(),
+            flagged_at: value.flagged_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Created,
+    Maybepayed,
+    Succeeded,
+    Cancelled,
+}
+
+impl From<Status> for OrderStatus {
+    fn from(value: Status) -> Self {
+        match value {
+            Status::Created => OrderStatus::Created,
+            Status::Maybepayed => OrderStatus::Maybepayed,
+            Status::Succeeded => OrderStatus::Succeeded,
+            Status::Cancelled => OrderStatus::Cancelled,
+        }
+    }
+}
+
+impl From<OrderStatus> for Status {
+    fn from(value: OrderStatus) -> Self {
+        match value {
+            OrderStatus::Created => Status::Created,
+            OrderStatus::Maybepayed => Status::Maybepayed,
+            OrderStatus::Succeeded => Status::Succeeded,
+            OrderStatus::Cancelled => Status::Cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSortField {
+    CreatedAt,
+    Amount,
+}
+
+impl From<OrderSortField> for OrderSort {
+    fn from(value: OrderSortField) -> Self {
+        match value {
+            OrderSortField::CreatedAt => OrderSort::CreatedAt,
+            OrderSortField::Amount => OrderSort::Amount,
+        }
+    }
+}
+
+//? Filters of order listings, date range is inclusive and applies to created_at
+#[derive(Debug, serde::Serialize, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderListQuery {
+    status: Option<OrderStatus>,
+    currency: Option<String>,
+    payment_method: Option<String>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    //? created_at by default
+    sort: Option<OrderSortField>,
+}
+
+impl OrderListQuery {
+    pub fn into_parameters(self, page: PageRequest) -> ListOrdersParameters {
+        ListOrdersParameters {
+            filter: OrderFilter {
+                status: self.status.map(Into::into),
+                currency: self.currency,
+                payment_method: self.payment_method,
+                from: self.from,
+                to: self.to,
+            },
+            sort: self.sort.map(Into::into).unwrap_or_default(),
+            page,
+        }
+    }
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct OrderStatusChange {
+    pub id: String,
+    pub order_id: String,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub actor: String,
+    pub actor_id: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime,
+}
+
+impl From<HistoryModel> for OrderStatusChange {
+    fn from(value: HistoryModel) -> Self {
+        Self {
+            id: value.id.to_string(),
+            order_id: value.order_id.to_string(),
+            from_status: value
+                .from_status
+                .map(|status| serde_json::to_string(&status).unwrap()),
+            to_status: serde_json::to_string(&value.to_status).unwrap(),
+            actor: serde_json::to_string(&value.actor).unwrap(),
+            actor_id: value.actor_id.map(|id| id.to_string()),
+            reason: value.reason,
+            created_at: value.created_at,
         }
     }
 }
 
+#[derive(Debug, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct CancelOrderRequest {
+    pub reason: Option<String>,
+}
+
 #[utoipa::path(
     post,
     path = "/api/user/order",
     request_body = CreateOrderRequest,
     responses(
         (status = 201, description = "Order was successfully created",     body = Order),
-        (status = 400, description = "Bad request",                        body = Details),
+        (status = 400, description = "Amount violates currency limits",    body = ValidationDetails),
         (status = 404, description = "Currency symbol was not found",             body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "User is not allowed to order",       body = Details),
+        (status = 409, description = "Quote was already used",             body = Details),
+        (status = 410, description = "Quote has expired",                  body = Details),
+        (status = 429, description = "Too many orders, see Retry-After",   body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
    security(
@@ -90,7 +221,8 @@ impl From<OrderModel> for Order {
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn create_order(
-    AuthJWT(user): AuthJWT,
+    AllowedTo(user, _): AllowedTo<Ordering>,
+    _limit: RateLimited<OrderCreation>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<CreateOrderRequest>,
 ) -> Response {
@@ -109,13 +241,40 @@ pub async fn create_order(
                     Err(cause) => return Into::<AppError>::into(cause).into_response(),
                 };
 
+            //? Quoted rate wins over the current one so user gets exactly what was shown
+            let quote = match payload.quote_id {
+                Some(quote_id) => {
+                    let parameters = RedeemQuoteParameters {
+                        quote_id,
+                        steam_id: user.steam_id,
+                        symbol: &currency_rate.symbol,
+                        amount: payload.amount,
+                        secret: app_state.configuration().jwt_secret(),
+                    };
+
+                    match QuotesService::redeem(parameters) {
+                        Ok(claims) => Some(claims),
+                        Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                    }
+                }
+                None => None,
+            };
+            let rate = quote
+                .as_ref()
+                .map_or(currency_rate.rate, |claims| claims.rate);
+
             let parameters = CreateOrderParameters {
                 steam_id: user.steam_id,
                 amount: payload.amount,
                 payment_method: payload.payment_method,
                 symbol: currency_rate.symbol,
-                currency_rate: currency_rate.rate,
+                currency_rate: rate,
                 requisites_id,
+                max_open_orders: app_state.configuration().max_open_orders(),
+                created_order_ttl_seconds: app_state.configuration().created_order_ttl_seconds()
+                    as i64,
+                strategy: assignment::strategy(app_state.configuration(), app_state.redis_client()),
+                quote_jti: quote.map(|claims| claims.jti),
             };
 
             let created_order_model =
@@ -128,20 +287,13 @@ pub async fn create_order(
                 return AppError::InternalServerError(Box::new(cause)).into_response();
             }
 
-            match app_state.redis_client().get_async_connection().await {
-                Ok(mut connection) => {
-                    let _: Result<(), _> = connection
-                        .publish(
-                            app_state.configuration().new_orders_channel_name(),
-                            serde_json::to_string(&created_order_model).unwrap(),
-                        )
-                        .await;
-                }
-                Err(cause) => {
-                    // Not very important
-                    tracing::warn!(%cause, "Failed to connect to redis!");
-                }
-            };
+            let event = OrderEvent::new(OrderEventKind::Created, created_order_model.clone());
+            EventsService::publish(
+                &event,
+                app_state.configuration().order_events_channel_name(),
+                app_state.redis_client(),
+            )
+            .await;
 
             (
                 StatusCode::CREATED,
@@ -156,11 +308,13 @@ pub async fn create_order(
 #[utoipa::path(
     patch,
     path = "/api/user/order/{id}/cancel",
+    request_body(content = Option<CancelOrderRequest>, description = "Optional cancellation reason"),
     responses(
         (status = 204, description = "Order was successfully canceled"),
         (status = 404, description = "Order was not found", body = Details),
         (status = 401, description = "Unauthorized", body = Details),
         (status = 400, description = "Order has already been marked as succeeded", body = Details),
+        (status = 403, description = "Order can not be cancelled by this user", body = Details),
         (status = 500, description = "Internal Server Error", body = Details),
     ),
     params(
@@ -175,19 +329,32 @@ pub async fn cancel_order(
     AuthJWT(user): AuthJWT,
     State(app_state): State<Arc<AppState>>,
     Path(order_id): Path<i64>,
+    payload: Option<Json<CancelOrderRequest>>,
 ) -> Response {
     match app_state.database_connection().begin().await {
         Ok(transaction) => {
             let parameters = CancelOrderParameters {
                 steam_id: user.steam_id,
                 order_id,
+                reason: payload.and_then(|Json(payload)| payload.reason),
             };
 
             match OrderService::cancel_order(parameters, &transaction).await {
-                Ok(()) => {
+                Ok(change) => {
                     if let Err(cause) = transaction.commit().await {
                         return AppError::InternalServerError(Box::new(cause)).into_response();
                     }
+
+                    if change.changed {
+                        let event = OrderEvent::new(OrderEventKind::Cancelled, change.order);
+                        EventsService::publish(
+                            &event,
+                            app_state.configuration().order_events_channel_name(),
+                            app_state.redis_client(),
+                        )
+                        .await;
+                    }
+
                     StatusCode::NO_CONTENT.into_response()
                 }
                 Err(cause) => Into::<AppError>::into(cause).into_response(),
@@ -205,6 +372,7 @@ pub async fn cancel_order(
         (status = 404, description = "Order was not found", body = Details),
         (status = 401, description = "Unauthorized", body = Details),
         (status = 400, description = "Order has already been marked as succeeded or canceled", body = Details),
+        (status = 403, description = "Order can not be marked as payed by this user", body = Details),
         (status = 500, description = "Internal Server Error", body = Details),
     ),
     params(
@@ -227,49 +395,75 @@ pub async fn set_order_maybepayed(
                 order_id,
             };
             match OrderService::maybepayed(parameters, &transaction).await {
-                Ok(order) => {
-                    match order.find_related(ChatEntity).one(&transaction).await {
-                        Ok(Some(chat)) => {
-                            let params = SendMessageParameters {
-                                folder: app_state.configuration().upload_folder().clone(),
-                                chat_id: chat.id,
-                                sender: Sender::Moderator,
-                                text: String::from("automessage-payed"), // This will be parsed by frontend to a normal message of moderator
-                                image: None,
-                            };
-
-                            match ChatService::send_message(params, &transaction).await {
-                                Ok(res) => {
-                                    let send = SendMessageResponse {
-                                        message: Into::<Message>::into(res.0),
-                                        images_ids: vec![], // No images in automessage
-                                    };
-
-                                    match app_state.redis_client().get_async_connection().await {
-                                        Ok(mut connection) => {
-                                            let _: Result<(), _> = connection
-                                                .publish(
-                                                    format!("chat-{}", chat.id),
-                                                    serde_json::to_string(&send).unwrap(),
-                                                )
-                                                .await;
-                                        }
-                                        Err(cause) => {
-                                            // Not very important
-                                            tracing::warn!(%cause, "Failed to connect to redis!");
-                                        }
-                                    };
-                                }
-                                Err(cause) => return Into::<AppError>::into(cause).into_response(),
-                            };
-                        }
-                        Ok(None) => {}
-                        Err(cause) => return Into::<AppError>::into(cause).into_response(),
-                    };
+                Ok(change) => {
+                    //? Automessage is sent only once when status really changes
+                    let mut automessage = None;
+
+                    if change.changed {
+                        match change
+                            .order
+                            .find_related(ChatEntity)
+                            .one(&transaction)
+                            .await
+                        {
+                            Ok(Some(chat)) => {
+                                let params = SendMessageParameters {
+                                    folder: app_state.configuration().upload_folder().clone(),
+                                    chat_id: chat.id,
+                                    sender: Sender::Automessage,
+                                    text: String::from("automessage-payed"), // This will be parsed by frontend to a normal message of moderator
+                                    image: None,
+                                };
+
+                                match ChatService::send_message(params, &transaction).await {
+                                    Ok(res) => {
+                                        let send = SendMessageResponse {
+                                            message: Into::<Message>::into(res.0),
+                                            images_ids: vec![], // No images in automessage
+                                        };
+                                        automessage = Some((chat.id, send));
+                                    }
+                                    Err(cause) => {
+                                        return Into::<AppError>::into(cause).into_response()
+                                    }
+                                };
+                            }
+                            Ok(None) => {}
+                            Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                        };
+                    }
 
                     if let Err(cause) = transaction.commit().await {
                         return AppError::InternalServerError(Box::new(cause)).into_response();
                     }
+
+                    if let Some((chat_id, send)) = automessage {
+                        match app_state.redis_client().get_async_connection().await {
+                            Ok(mut connection) => {
+                                let _: Result<(), _> = connection
+                                    .publish(
+                                        format!("chat-{}", chat_id),
+                                        serde_json::to_string(&send).unwrap(),
+                                    )
+                                    .await;
+                            }
+                            Err(cause) => {
+                                // Not very important
+                                tracing::warn!(%cause, "Failed to connect to redis!");
+                            }
+                        };
+                    }
+
+                    if change.changed {
+                        let event = OrderEvent::new(OrderEventKind::Maybepayed, change.order);
+                        EventsService::publish(
+                            &event,
+                            app_state.configuration().order_events_channel_name(),
+                            app_state.redis_client(),
+                        )
+                        .await;
+                    }
+
                     StatusCode::NO_CONTENT.into_response()
                 }
                 Err(cause) => Into::<AppError>::into(cause).into_response(),
@@ -282,10 +476,12 @@ pub async fn set_order_maybepayed(
 #[utoipa::path(
     get,
     path = "/api/user/order",
+    params(PageQuery, OrderListQuery),
     responses(
-        (status = 200, description = "Orders were successfully retrieved", body = [Order]),
-        (status = 401, description = "Unauthorized", body = Details),
-        (status = 500, description = "Internal Server Error", body = Details),
+        (status = 200, description = "Orders were successfully retrieved", body = PagedOrders),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
         ("jwt_user" = [])
@@ -295,13 +491,17 @@ pub async fn set_order_maybepayed(
 pub async fn list_orders(
     AuthJWT(user): AuthJWT,
     State(app_state): State<Arc<AppState>>,
+    Pagination(page): Pagination,
+    Query(query): Query<OrderListQuery>,
 ) -> Response {
     match app_state.database_connection().begin().await {
         Ok(transaction) => {
-            let user_orders = match OrderService::user_orders(user.steam_id, &transaction).await {
-                Ok(orders) => orders,
-                Err(cause) => return Into::<AppError>::into(cause).into_response(),
-            };
+            let parameters = query.into_parameters(page);
+            let user_orders =
+                match OrderService::user_orders(user.steam_id, parameters, &transaction).await {
+                    Ok(orders) => orders,
+                    Err(cause) => return Into::<AppError>::into(cause).into_response(),
+                };
 
             if let Err(cause) = transaction.commit().await {
                 return AppError::InternalServerError(Box::new(cause)).into_response();
@@ -309,12 +509,7 @@ pub async fn list_orders(
 
             (
                 StatusCode::OK,
-                Json(
-                    user_orders
-                        .into_iter()
-                        .map(Into::<Order>::into)
-                        .collect::<Vec<_>>(),
-                ),
+                Json(Into::<Paged<Order>>::into(user_orders)),
             )
                 .into_response()
         }
@@ -365,6 +560,65 @@ pub async fn get_order(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/user/order/{id}/history",
+    responses(
+        (status = 200, description = "Order status history was successfully retrieved", body = [OrderStatusChange]),
+        (status = 404, description = "Order was not found", body = Details),
+        (status = 401, description = "Unauthorized", body = Details),
+        (status = 500, description = "Internal Server Error", body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Order id")
+    ),
+    security(
+        ("jwt_user" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn order_history(
+    AuthJWT(user): AuthJWT,
+    State(app_state): State<Arc<AppState>>,
+    Path(order_id): Path<i64>,
+) -> Response {
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            let parameters = GetUserOrderParameters {
+                steam_id: user.steam_id,
+                order_id,
+            };
+
+            match OrderService::user_order(parameters, &transaction).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return AppError::OrderWasNotFound.into_response(),
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            let history = match OrderService::history(order_id, &transaction).await {
+                Ok(history) => history,
+                Err(cause) => return Into::<AppError>::into(cause).into_response(),
+            };
+
+            if let Err(cause) = transaction.commit().await {
+                return AppError::InternalServerError(Box::new(cause)).into_response();
+            }
+
+            (
+                StatusCode::OK,
+                Json(
+                    history
+                        .into_iter()
+                        .map(Into::<OrderStatusChange>::into)
+                        .collect::<Vec<_>>(),
+                ),
+            )
+                .into_response()
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, ToSchema, IntoParams)]
 pub struct TimeBounds {
     start_datetime: NaiveDateTime,
@@ -418,6 +672,7 @@ pub fn router() -> axum::Router<Arc<AppState>> {
         .route("/:id/maybepayed", patch(set_order_maybepayed))
         .route("/", get(list_orders))
         .route("/:id", get(get_order))
+        .route("/:id/history", get(order_history))
         .route("/live", get(live::websocket_handler))
         .route("/all-in-period", post(all_in_period))
 }
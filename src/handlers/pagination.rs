@@ -0,0 +1,31 @@
+use crate::services::pagination::Page;
+use utoipa::ToSchema;
+
+//? Common envelope of every list endpoint
+//? Pass `next_cursor` as `cursor` to get the next page, null means the last page
+#[derive(Debug, serde::Serialize, serde::Deserialize, ToSchema)]
+#[aliases(
+    PagedOrders = Paged<crate::handlers::orders::Order>,
+    PagedReviews = Paged<crate::handlers::reviews::users::Review>,
+    PagedTopUsers = Paged<crate::handlers::user::TopUser>,
+    PagedBlacklistEntries = Paged<crate::handlers::admin::blacklist::BlacklistEntry>,
+    PagedLoginAuditEntries = Paged<crate::handlers::admin::login_audit::LoginAuditEntry>,
+    PagedCurrencyRateChanges = Paged<crate::handlers::admin::currency::CurrencyRateChange>,
+    PagedSteamIds = Paged<String>
+)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    //? Amount of items matching filters on all pages
+    pub total: u64,
+}
+
+impl<T, M: Into<T>> From<Page<M>> for Paged<T> {
+    fn from(value: Page<M>) -> Self {
+        Self {
+            items: value.items.into_iter().map(Into::into).collect(),
+            next_cursor: value.next_cursor.map(|cursor| cursor.encode()),
+            total: value.total,
+        }
+    }
+}
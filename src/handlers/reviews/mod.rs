@@ -1,10 +1,13 @@
-use axum::routing::{get, post};
+use axum::routing::{get, patch, post};
 
 use crate::state::AppState;
 use std::sync::Arc;
 
 pub mod users;
-use users::{add_users_review, all_users_reviews, all_video_reviews, count_reviews, five_stars};
+use users::{
+    add_users_review, all_users_reviews, all_video_reviews, count_reviews, five_stars,
+    review_stats, update_users_review,
+};
 
 pub fn router() -> axum::Router<Arc<AppState>> {
     axum::Router::new()
@@ -13,4 +16,6 @@ pub fn router() -> axum::Router<Arc<AppState>> {
         .route("/video", get(all_video_reviews))
         .route("/count", get(count_reviews))
         .route("/five-stars", get(five_stars))
+        .route("/stats", get(review_stats))
+        .route("/:id", patch(update_users_review))
 }
@@ -1,68 +1,53 @@
 use crate::{
-    errors::AppError,
-    extractors::user_jwt::AuthJWT,
-    services::reviews::{AddReviewParameters, Service as ReviewsService},
+    errors::{AppError, Details},
+    extractors::{
+        pagination::{PageQuery, Pagination},
+        user_jwt::{AllowedTo, Reviewing},
+    },
+    handlers::pagination::Paged,
+    services::reviews::{
+        AddReviewParameters, MonthlyReviews, ReviewStats as ReviewStatsModel, RollingReviews,
+        Service as ReviewsService, UpdateReviewParameters,
+    },
 };
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
 
 use chrono::NaiveDateTime as DateTime;
-use entity::{review::Model as ReviewModel, video_review::Model as VideoReviewModel};
+use entity::{
+    review::Model as ReviewModel, sea_orm_active_enums::ReviewStatus,
+    video_review::Model as VideoReviewModel,
+};
+use sea_orm::{prelude::Decimal, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::state::AppState;
 
-#[derive(serde::Serialize, serde::Deserialize, ToSchema, Debug, IntoParams)]
-pub struct Bounds {
-    limit: String,
-    offset: String,
-}
-
 #[utoipa::path(
     get,
     path = "/api/review",
     responses(
-        (status = 200, description = "Reviews were successfully retrieved",  body = [Review]),
+        (status = 200, description = "Reviews were successfully retrieved",  body = PagedReviews),
+        (status = 400, description = "Bad request",                          body = Details),
         (status = 500, description = "Internal Server Error",                body = Details),
     ),
     params(
-        Bounds
-
+        PageQuery
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn all_users_reviews(
     State(app_state): State<Arc<AppState>>,
-    Query(bounds): Query<Bounds>,
+    Pagination(page): Pagination,
 ) -> Response {
-    let limit = match bounds.limit.parse::<u64>() {
-        Ok(limit) => limit,
-        Err(cause) => {
-            return Into::<AppError>::into(cause).into_response();
-        }
-    };
-
-    let offset = match bounds.offset.parse::<u64>() {
-        Ok(offset) => offset,
-        Err(cause) => {
-            return Into::<AppError>::into(cause).into_response();
-        }
-    };
-
-    match ReviewsService::users_all(limit, offset, app_state.database_connection()).await {
-        Ok(reviews) => Json(
-            reviews
-                .into_iter()
-                .map(Into::<Review>::into)
-                .collect::<Vec<_>>(),
-        )
-        .into_response(),
+    match ReviewsService::users_all(&page, app_state.database_connection()).await {
+        Ok(reviews) => Json(Into::<Paged<Review>>::into(reviews)).into_response(),
         Err(cause) => Into::<AppError>::into(cause).into_response(),
     }
 }
@@ -94,9 +79,12 @@ pub async fn all_video_reviews(State(app_state): State<Arc<AppState>>) -> Respon
     path = "/api/review",
     request_body = AddReviewRequest,
     responses(
-        (status = 204, description = "Review was successfully saved"),
+        (status = 201, description = "Review was saved and awaits moderation", body = Review),
         (status = 400, description = "Bad request",                        body = Details),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "User is not allowed to review",      body = Details),
+        (status = 404, description = "Order was not found",                body = Details),
+        (status = 409, description = "Order has already been reviewed",    body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     security(
@@ -105,21 +93,87 @@ pub async fn all_video_reviews(State(app_state): State<Arc<AppState>>) -> Respon
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn add_users_review(
-    AuthJWT(user): AuthJWT,
+    AllowedTo(user, _): AllowedTo<Reviewing>,
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<AddReviewRequest>,
 ) -> Response {
+    let order_id = match payload.order_id.parse::<i64>() {
+        Ok(id) => id,
+        Err(cause) => {
+            return Into::<AppError>::into(cause).into_response();
+        }
+    };
+
     let review_to_be_added = AddReviewParameters {
         steam_id: user.steam_id,
+        order_id,
         review: payload.review,
         stars: payload.stars,
     };
 
-    match ReviewsService::add_users_review(review_to_be_added, app_state.database_connection())
-        .await
-    {
-        Ok(()) => StatusCode::NO_CONTENT.into_response(),
-        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match ReviewsService::add_users_review(review_to_be_added, &transaction).await {
+                Ok(review) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    (StatusCode::CREATED, Json(Into::<Review>::into(review))).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/review/{id}",
+    request_body = UpdateReviewRequest,
+    responses(
+        (status = 200, description = "Review was updated and awaits moderation", body = Review),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "Review can no longer be edited",     body = Details),
+        (status = 404, description = "Review was not found",               body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ("id" = i64, Path, description = "Review id")
+    ),
+    security(
+        ("jwt_user" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn update_users_review(
+    AllowedTo(user, _): AllowedTo<Reviewing>,
+    State(app_state): State<Arc<AppState>>,
+    Path(review_id): Path<i64>,
+    Json(payload): Json<UpdateReviewRequest>,
+) -> Response {
+    let parameters = UpdateReviewParameters {
+        steam_id: user.steam_id,
+        review_id,
+        review: payload.review,
+        stars: payload.stars,
+        edit_window_seconds: app_state.configuration().review_edit_window_seconds(),
+    };
+
+    match app_state.database_connection().begin().await {
+        Ok(transaction) => {
+            match ReviewsService::update_users_review(parameters, &transaction).await {
+                Ok(review) => {
+                    if let Err(cause) = transaction.commit().await {
+                        return AppError::InternalServerError(Box::new(cause)).into_response();
+                    }
+                    Json(Into::<Review>::into(review)).into_response()
+                }
+                Err(cause) => Into::<AppError>::into(cause).into_response(),
+            }
+        }
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
     }
 }
 
@@ -171,12 +225,143 @@ pub async fn five_stars(State(app_state): State<Arc<AppState>>) -> Response {
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, IntoParams)]
+pub struct ReviewStatsQuery {
+    //? Amount of monthly buckets including current month, 12 by default
+    months: Option<u32>,
+    //? Amount of points in rolling series including today, 30 by default
+    days: Option<u32>,
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct StarsCount {
+    stars: i16,
+    count: i64,
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct MonthlyReviewStats {
+    //? First moment of the month
+    month: DateTime,
+    count: i64,
+    //? Null for months without reviews
+    #[schema(value_type = Option<String>)]
+    average: Option<Decimal>,
+}
+
+impl From<MonthlyReviews> for MonthlyReviewStats {
+    fn from(value: MonthlyReviews) -> Self {
+        Self {
+            month: value.month,
+            count: value.count,
+            average: value.average,
+        }
+    }
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct RollingReviewStats {
+    //? Last day of the 30 day window
+    day: DateTime,
+    count: i64,
+    //? Null when the window has no reviews
+    #[schema(value_type = Option<String>)]
+    average: Option<Decimal>,
+}
+
+impl From<RollingReviews> for RollingReviewStats {
+    fn from(value: RollingReviews) -> Self {
+        Self {
+            day: value.day,
+            count: value.count,
+            average: value.average,
+        }
+    }
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct ReviewStats {
+    total: i64,
+    //? Null when there are no reviews yet
+    #[schema(value_type = Option<String>)]
+    average: Option<Decimal>,
+    //? Always has an entry for every amount of stars from 0 to 5
+    distribution: Vec<StarsCount>,
+    //? Every month of the range in order, including months without reviews
+    monthly: Vec<MonthlyReviewStats>,
+    //? Every day of the range in order, each one covering 30 days
+    rolling: Vec<RollingReviewStats>,
+}
+
+impl From<ReviewStatsModel> for ReviewStats {
+    fn from(value: ReviewStatsModel) -> Self {
+        Self {
+            total: value.total,
+            average: value.average,
+            distribution: (0..)
+                .zip(value.distribution)
+                .map(|(stars, count)| StarsCount { stars, count })
+                .collect(),
+            monthly: value.monthly.into_iter().map(Into::into).collect(),
+            rolling: value.rolling.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+//? Only published reviews of users who are not blacklisted are counted
+#[utoipa::path(
+    get,
+    path = "/api/review/stats",
+    responses(
+        (status = 200, description = "Statistics were successfully retrieved", body = ReviewStats),
+        (status = 400, description = "Bad request",                        body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    params(
+        ReviewStatsQuery
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn review_stats(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ReviewStatsQuery>,
+) -> Response {
+    let months = query.months.unwrap_or(12);
+    if !(1..=120).contains(&months) {
+        return AppError::BadRequest(Details {
+            details: String::from("`months` must be between 1 and 120"),
+        })
+        .into_response();
+    }
+
+    let days = query.days.unwrap_or(30);
+    if !(1..=366).contains(&days) {
+        return AppError::BadRequest(Details {
+            details: String::from("`days` must be between 1 and 366"),
+        })
+        .into_response();
+    }
+
+    match ReviewsService::stats(months, days, app_state.database_connection()).await {
+        Ok(stats) => Json(Into::<ReviewStats>::into(stats)).into_response(),
+        Err(cause) => Into::<AppError>::into(cause).into_response(),
+    }
+}
+
 #[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
 pub struct AddReviewRequest {
+    //? Must be one of user's own succeeded orders
+    pub order_id: String,
     pub review: String,
     pub stars: i16,
 }
 
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug)]
+pub struct UpdateReviewRequest {
+    pub review: Option<String>,
+    pub stars: Option<i16>,
+}
+
 #[derive(ToSchema, serde::Serialize, serde::Deserialize)]
 pub struct Review {
     pub id: String,
@@ -184,6 +369,43 @@ pub struct Review {
     pub review: String,
     pub stars: i16,
     pub created_at: DateTime,
+    pub order_id: Option<String>,
+    //? Review is bound to a succeeded order of its author
+    pub verified: bool,
+    pub updated_at: Option<DateTime>,
+    pub status: ReviewState,
+    pub rejection_reason: Option<String>,
+    //? Public answer of the staff
+    pub reply: Option<String>,
+    pub replied_at: Option<DateTime>,
+}
+
+#[derive(ToSchema, serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewState {
+    Pending,
+    Published,
+    Rejected,
+}
+
+impl From<ReviewStatus> for ReviewState {
+    fn from(value: ReviewStatus) -> Self {
+        match value {
+            ReviewStatus::Pending => Self::Pending,
+            ReviewStatus::Published => Self::Published,
+            ReviewStatus::Rejected => Self::Rejected,
+        }
+    }
+}
+
+impl From<ReviewState> for ReviewStatus {
+    fn from(value: ReviewState) -> Self {
+        match value {
+            ReviewState::Pending => Self::Pending,
+            ReviewState::Published => Self::Published,
+            ReviewState::Rejected => Self::Rejected,
+        }
+    }
 }
 
 //? I was forced to write this by utoipa
@@ -197,6 +419,13 @@ impl From<ReviewModel> for Review {
             review: value.review,
             stars: value.stars,
             created_at: value.created_at,
+            order_id: value.order_id.map(|id| id.to_string()),
+            verified: value.order_id.is_some(),
+            updated_at: value.updated_at,
+            status: value.status.into(),
+            rejection_reason: value.rejection_reason,
+            reply: value.reply,
+            replied_at: value.replied_at,
         }
     }
 }
@@ -1 +1,9 @@
+use crate::state::AppState;
+use std::sync::Arc;
+
+pub mod moderators;
 pub mod users;
+
+pub fn router() -> axum::Router<Arc<AppState>> {
+    users::router().merge(moderators::router())
+}
@@ -0,0 +1,51 @@
+use crate::{
+    errors::AppError, extractors::admin_jwt::StaffAuthJWT, services::assignment, state::AppState,
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::patch,
+};
+use redis::AsyncCommands;
+use std::sync::Arc;
+
+//? Presence is used by online only assignment strategy
+#[utoipa::path(
+    patch,
+    path = "/api/status/moderator",
+    responses(
+        (status = 204, description = "Status was successfully refreshed"),
+        (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 500, description = "Internal Server Error",              body = Details),
+    ),
+    security(
+        ("jwt_admin" = [])
+    )
+)]
+#[tracing::instrument(skip(app_state))]
+pub async fn refresh_status(
+    State(app_state): State<Arc<AppState>>,
+    StaffAuthJWT(moderator): StaffAuthJWT,
+) -> Response {
+    let mut client = match app_state.redis_client().get_async_connection().await {
+        Ok(connection) => connection,
+        Err(cause) => {
+            return AppError::InternalServerError(Box::new(cause)).into_response();
+        }
+    };
+
+    let exp = app_state.configuration().status_expiration_seconds();
+
+    match client
+        .set_ex(assignment::presence_key(moderator.id), true, exp)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(cause) => AppError::InternalServerError(Box::new(cause)).into_response(),
+    }
+}
+
+pub fn router() -> axum::Router<Arc<AppState>> {
+    axum::Router::new().route("/moderator", patch(refresh_status))
+}
@@ -1,9 +1,14 @@
 use crate::{
     errors::AppError,
-    extractors::user_jwt::AuthJWT,
+    extractors::{
+        pagination::{PageQuery, Pagination},
+        user_jwt::{self, AllowedTo, AuthJWT, Chatting},
+    },
+    handlers::pagination::Paged,
     services::{
-        auth::{JwtCheckParams, Service as AuthService},
+        admin::blacklist::Capability,
         chat::{GetChatParameters, SendMessageParameters, Sender, Service as ChatService},
+        events::OrderEvent,
         users::Service as UsersService,
     },
     state::AppState,
@@ -20,17 +25,15 @@ use axum::{
 use axum_typed_multipart::TypedMultipart;
 use chrono::NaiveDateTime;
 use entity::{
-    chat::Entity as ChatEntity,
-    image::Entity as ImageEntity,
-    message::Entity as MessageEntity,
-    user::{Entity as UserEntity, Model as UserModel},
+    chat::Entity as ChatEntity, image::Entity as ImageEntity, message::Entity as MessageEntity,
+    user::Model as UserModel,
 };
 use redis::AsyncCommands;
 use sea_orm::{prelude::Decimal, EntityTrait, TransactionTrait};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_util::io::ReaderStream;
-use utoipa::{IntoParams, ToSchema};
+use utoipa::ToSchema;
 
 #[derive(serde::Serialize, serde::Deserialize, ToSchema, Debug)]
 pub struct User {
@@ -162,43 +165,26 @@ impl From<crate::services::users::TopUser> for TopUser {
         }
     }
 }
-#[derive(serde::Serialize, serde::Deserialize, ToSchema, Debug, IntoParams)]
-pub struct Bounds {
-    limit: u64,
-    offset: u64,
-}
 
 #[utoipa::path(
     get,
     path = "/api/user/top",
     responses(
-        (status = 200, description = "Top users were successfully retrieved", body = [TopUser]),
+        (status = 200, description = "Top users were successfully retrieved", body = PagedTopUsers),
+        (status = 400, description = "Bad request",                        body = Details),
         (status = 500, description = "Internal Server Error",              body = Details),
     ),
     params(
-        Bounds
-
+        PageQuery
     )
 )]
 #[tracing::instrument(skip(app_state))]
 pub async fn get_top(
     State(app_state): State<Arc<AppState>>,
-    Query(payload): Query<Bounds>,
+    Pagination(page): Pagination,
 ) -> axum::response::Response {
-    match UsersService::top(
-        payload.limit,
-        payload.offset,
-        app_state.database_connection(),
-    )
-    .await
-    {
-        Ok(users) => Json(
-            users
-                .into_iter()
-                .map(Into::<TopUser>::into)
-                .collect::<Vec<_>>(),
-        )
-        .into_response(),
+    match UsersService::top(&page, app_state.database_connection()).await {
+        Ok(users) => Json(Into::<Paged<TopUser>>::into(users)).into_response(),
         Err(cause) => Into::<AppError>::into(cause).into_response(),
     }
 }
@@ -210,6 +196,7 @@ pub async fn get_top(
     responses(
         (status = 200, description = "Chat was successfully retrieved",    body = ChatResponse),
         (status = 401, description = "Unauthorized",                       body = Details),
+        (status = 403, description = "User is not allowed to chat",        body = Details),
         (status = 500, description = "Internal server error",              body = Details),
     ),
     security(
@@ -218,7 +205,7 @@ pub async fn get_top(
 )]
 pub async fn chat(
     State(app_state): State<Arc<AppState>>,
-    AuthJWT(user): AuthJWT,
+    AllowedTo(user, _): AllowedTo<Chatting>,
     Json(payload): Json<GetChatRequest>,
 ) -> Response {
     let moderator_id: i64 = match payload.id.parse() {
@@ -252,7 +239,7 @@ pub async fn chat(
         (status = 200, description = "Message was successfully sent",    body = SendMessageResponse),
         (status = 401, description = "Unauthorized",                       body = Details),
         (status = 500, description = "Internal server error",              body = Details),
-        (status = 403, description = "User is not a member of this chat"),
+        (status = 403, description = "User is not a member of this chat or is not allowed to chat"),
         (status = 404, description = "Chat  was not found"),
 
     ),
@@ -262,7 +249,7 @@ pub async fn chat(
 )]
 pub async fn send_message(
     State(app_state): State<Arc<AppState>>,
-    AuthJWT(user): AuthJWT,
+    AllowedTo(user, _): AllowedTo<Chatting>,
     Path(chat_id): Path<i64>,
     TypedMultipart(UploadData { image, text }): TypedMultipart<UploadData>,
 ) -> Response {
@@ -280,7 +267,7 @@ pub async fn send_message(
             let params = SendMessageParameters {
                 folder: app_state.configuration().upload_folder().clone(),
                 chat_id,
-                sender: Sender::User,
+                sender: Sender::User(user.steam_id),
                 text,
                 image: image.as_ref(),
             };
@@ -450,26 +437,19 @@ pub async fn websocket_handler(
         _ => return AppError::AuthorizationHeaderBadSchema.into_response(),
     };
 
-    let params = JwtCheckParams {
-        token,
-        secret: state.configuration().jwt_secret(),
-    };
-
-    let claims = match AuthService::check(params) {
-        Ok(claims) => claims,
-        Err(cause) => return Into::<AppError>::into(cause).into_response(),
-    };
-
-    let user = match UserEntity::find_by_id(claims.sub)
-        .one(state.database_connection())
-        .await
-    {
-        Ok(Some(user)) => user,
-        Ok(None) => return AppError::Unauthorized.into_response(),
-        Err(cause) => return AppError::InternalServerError(Box::new(cause)).into_response(),
+    let user = match user_jwt::user(token, &state).await {
+        Ok((_, Some(ban))) if Capability::Chatting.is_blocked_by(&ban) => {
+            return AppError::UserBlacklisted {
+                reason: ban.reason,
+                expires_at: ban.expires_at,
+            }
+            .into_response()
+        }
+        Ok((user, _)) => user,
+        Err(cause) => return cause.into_response(),
     };
 
-    match ChatEntity::find_by_id(chat_id)
+    let order_id = match ChatEntity::find_by_id(chat_id)
         .one(state.database_connection())
         .await
     {
@@ -477,15 +457,16 @@ pub async fn websocket_handler(
             if chat.steam_id != user.steam_id {
                 return StatusCode::FORBIDDEN.into_response();
             }
+            chat.order_id
         }
         Ok(None) => return StatusCode::NOT_FOUND.into_response(),
         Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     };
 
-    ws.on_upgrade(move |socket| handle_socket(socket, state, chat_id))
+    ws.on_upgrade(move |socket| handle_socket(socket, state, chat_id, order_id))
 }
 
-async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64) {
+async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64, order_id: i64) {
     let (tx, mut rx) = mpsc::channel(10);
     let (mut sender, _) = socket.split();
 
@@ -493,13 +474,28 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>, chat_id: i64) {
         let connection = state.redis_client().get_async_connection().await.unwrap();
         let mut pubsub = connection.into_pubsub();
         pubsub.subscribe(format!("chat-{}", chat_id)).await.unwrap();
+        pubsub
+            .subscribe(state.configuration().order_events_channel_name())
+            .await
+            .unwrap();
 
         while let Some(msg) = pubsub.on_message().next().await {
-            if let Ok(payload) = msg.get_payload() {
-                if tx.send(payload).await.is_err() {
-                    break;
+            let payload = match msg.get_payload::<String>() {
+                Ok(payload) => payload,
+                Err(_) => continue,
+            };
+
+            //? Only events of the order this chat belongs to are forwarded
+            if msg.get_channel_name() == state.configuration().order_events_channel_name() {
+                match OrderEvent::from_payload(&payload) {
+                    Some(event) if event.order.id == order_id => {}
+                    _ => continue,
                 }
             }
+
+            if tx.send(payload).await.is_err() {
+                break;
+            }
         }
     });
 
@@ -1,23 +1,12 @@
 use crate::handlers::{admin::moderators::*, orders::*};
 use axum::extract::DefaultBodyLimit;
 use config::{Configuration, ConfigurationReader, EnvConfigurationReader};
-use entity::{
-    admin::{ActiveModel as AdminActiveModel, Column as AdminColumn, Entity as AdminEntity},
-    requisites::{
-        ActiveModel as RequisitesActiveModel, Column as RequisitesColumn,
-        Entity as RequisitesEntity,
-    },
-    sea_orm_active_enums::Role,
-    social::{ActiveModel as SocialActiveModel, Column as SocialColumn, Entity as SocialEntity},
-};
 use utoipauto::utoipauto;
 
 use migration::{Migrator, MigratorTrait};
-use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectOptions, Database, EntityTrait, QueryFilter, Set,
-};
+use sea_orm::{ConnectOptions, Database};
 use state::AppState;
-use std::{path::PathBuf, sync::Arc};
+use std::{net::SocketAddr, path::PathBuf, process::ExitCode, sync::Arc};
 use tower_http::cors::CorsLayer;
 use tracing_subscriber::layer::SubscriberExt;
 use utoipa::{
@@ -28,16 +17,18 @@ use utoipa_rapidoc::RapiDoc;
 use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
+mod bootstrap;
 mod config;
 mod errors;
 mod extractors;
 mod handlers;
 mod openid;
+mod scheduler;
 mod services;
 mod state;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     dotenvy::dotenv().ok();
 
     //* Setting up tracing
@@ -48,12 +39,20 @@ async fn main() {
 
     tracing::subscriber::set_global_default(subscriber).ok();
 
+    let command = match bootstrap::Command::parse(std::env::args().skip(1)) {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", bootstrap::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
     //* Reading configuration
     let configuration: Configuration = match EnvConfigurationReader::read(None::<PathBuf>) {
         Ok(config) => config,
         Err(cause) => {
             tracing::error!(%cause);
-            return;
+            return ExitCode::FAILURE;
         }
     };
     if !configuration.upload_folder().exists() {
@@ -64,12 +63,21 @@ async fn main() {
         panic!("Upload folder is a file!");
     }
 
+    //* Loading jwt keys
+    let keys = match services::auth::keys::Keys::from_configuration(&configuration) {
+        Ok(keys) => keys,
+        Err(cause) => {
+            tracing::error!(%cause, "Failed to load jwt keys!");
+            return ExitCode::FAILURE;
+        }
+    };
+
     //* Connecting to redis
     let redis_client = match redis::Client::open(configuration.redis_url()) {
         Ok(client) => client,
         Err(cause) => {
             tracing::error!(%cause);
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
@@ -111,13 +119,51 @@ async fn main() {
     let database_connection = Database::connect(opt).await.unwrap();
     Migrator::up(&database_connection, None).await.unwrap();
 
+    match command {
+        bootstrap::Command::Serve => {}
+        bootstrap::Command::CreateAdmin => {
+            return match bootstrap::create_admin(&configuration, &database_connection).await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(cause) => {
+                    tracing::error!(%cause, "Failed to create admin!");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        bootstrap::Command::Seed => {
+            return match bootstrap::seed(&configuration, &database_connection).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(cause) => {
+                    tracing::error!(%cause);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+    }
+
+    if let Err(cause) = bootstrap::seed(&configuration, &database_connection).await {
+        tracing::error!(%cause);
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(cause) = bootstrap::ensure_admin(&configuration, &database_connection).await {
+        tracing::error!(%cause);
+        return ExitCode::FAILURE;
+    }
+
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", configuration.port()))
         .await
         .unwrap();
 
     let openid = openid::SteamOpenId::new(configuration.realm(), "/auth/steam-success").unwrap();
 
-    let state = AppState::new(database_connection, configuration, redis_client, openid);
+    let state = AppState::new(
+        database_connection,
+        configuration,
+        redis_client,
+        openid,
+        keys,
+    );
 
     //* Setting utoipa for openapi
     #[utoipauto]
@@ -152,7 +198,8 @@ async fn main() {
     let api_router = axum::Router::new()
         .nest("/auth/user", handlers::auth::users::router())
         .nest("/auth/admin", handlers::auth::admins::router())
-        .nest("/status", handlers::status::users::router())
+        .nest("/auth", handlers::auth::router())
+        .nest("/status", handlers::status::router())
         .nest("/admin", handlers::admin::router())
         .nest("/review", handlers::reviews::router())
         .nest("/user/order", handlers::orders::router())
@@ -161,79 +208,10 @@ async fn main() {
         .nest("/socials", handlers::social::router())
         .nest("/requisites", handlers::requisites::router());
 
-    let admin_login = "admin";
-    let admin_password = "$argon2id$v=19$m=19456,t=2,p=1$ZKBXQv1LtIbVXKASHcIbYw$MYqUU8AI5K2OWN3b4QdkFP+g3Dh6IDnXo40EvFvYeYQ";
-
-    if AdminEntity::find()
-        .filter(AdminColumn::Login.eq(admin_login))
-        .one(state.database_connection())
-        .await
-        .unwrap()
-        .is_none()
-    {
-        let admin_to_be_inserted = AdminActiveModel {
-            login: Set(admin_login.to_owned()),
-            password: Set(admin_password.to_owned()),
-            role: Set(Role::Admin),
-            ..Default::default()
-        };
-
-        AdminEntity::insert(admin_to_be_inserted)
-            .exec_with_returning(state.database_connection())
-            .await
-            .unwrap();
-    }
-
-    let requisites = [
-        "Тинькофф",
-        "Сбер Банк",
-        "Киви",
-        "Юмани",
-        "Каспи Банк",
-        "USDT",
-    ];
-
-    for req in requisites {
-        if RequisitesEntity::find()
-            .filter(RequisitesColumn::Name.eq(req))
-            .one(state.database_connection())
-            .await
-            .unwrap()
-            .is_none()
-        {
-            let req_to_be_inserted = RequisitesActiveModel {
-                name: Set(req.to_owned()),
-                ..Default::default()
-            };
-
-            req_to_be_inserted
-                .insert(state.database_connection())
-                .await
-                .unwrap();
-        }
-    }
-
-    let socials = ["Вконтакте", "Ютуб", "Телеграм"];
-
-    for social in socials {
-        if SocialEntity::find()
-            .filter(SocialColumn::Name.eq(social))
-            .one(state.database_connection())
-            .await
-            .unwrap()
-            .is_none()
-        {
-            let social_to_be_inserted = SocialActiveModel {
-                name: Set(social.to_owned()),
-                ..Default::default()
-            };
+    let state = Arc::new(state);
 
-            social_to_be_inserted
-                .insert(state.database_connection())
-                .await
-                .unwrap();
-        }
-    }
+    //* Starting background jobs
+    scheduler::spawn(state.clone());
 
     let app = axum::Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
@@ -242,7 +220,15 @@ async fn main() {
         .nest("/api", api_router)
         .layer(CorsLayer::permissive())
         .layer(DefaultBodyLimit::max(10 * 1024 * 1024)) //10 mb
-        .with_state(Arc::new(state));
+        .with_state(state);
+
+    //? Peer address is needed for staff login lockouts and audit
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,40 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use tokio::time::MissedTickBehavior;
+
+use crate::state::AppState;
+
+pub mod orders;
+pub mod rates;
+
+//* Spawns every background job of the server
+pub fn spawn(app_state: Arc<AppState>) {
+    let period = Duration::from_secs(app_state.configuration().orders_sweep_interval_seconds());
+    every(period, app_state.clone(), orders::sweep);
+
+    if let Some(provider) = rates::provider(app_state.configuration()) {
+        let period =
+            Duration::from_secs(app_state.configuration().rates_refresh_interval_seconds());
+        every(period, app_state, move |app_state| {
+            rates::refresh(app_state, provider.clone())
+        });
+    }
+}
+
+//? Runs job with a fixed period. Ticks are not piled up
+//? if one of the sweeps took longer than the period
+fn every<F, Fut>(period: Duration, app_state: Arc<AppState>, job: F)
+where
+    F: Fn(Arc<AppState>) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            job(app_state.clone()).await;
+        }
+    });
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use entity::{chat::Entity as ChatEntity, order::Model as OrderModel};
+use redis::AsyncCommands;
+use sea_orm::{DatabaseTransaction, ModelTrait, TransactionTrait};
+
+use crate::{
+    errors::AppError,
+    services::{
+        chat::{SendMessageParameters, Sender, Service as ChatService},
+        events::{OrderEvent, OrderEventKind, Service as EventsService},
+        orders::Service as OrderService,
+    },
+    state::AppState,
+    Message, SendMessageResponse,
+};
+
+//* Cancels expired created orders and flags stale maybepayed orders
+#[tracing::instrument(skip(app_state))]
+pub async fn sweep(app_state: Arc<AppState>) {
+    let now = Utc::now().naive_local();
+    let configuration = app_state.configuration();
+
+    let created_before = now - Duration::seconds(configuration.created_order_ttl_seconds() as i64);
+    match OrderService::expired_orders(created_before, app_state.database_connection()).await {
+        Ok(orders) => {
+            for order in orders {
+                if let Err(cause) = expire(&app_state, order.id).await {
+                    tracing::error!(?cause, order_id = order.id, "Failed to expire order!");
+                }
+            }
+        }
+        Err(cause) => tracing::error!(%cause, "Failed to retrieve expired orders!"),
+    };
+
+    let maybepayed_before =
+        now - Duration::seconds(configuration.maybepayed_order_ttl_seconds() as i64);
+    match OrderService::stale_orders(maybepayed_before, app_state.database_connection()).await {
+        Ok(orders) => {
+            for order in orders {
+                if let Err(cause) = flag(&app_state, order.id).await {
+                    tracing::error!(?cause, order_id = order.id, "Failed to flag order!");
+                }
+            }
+        }
+        Err(cause) => tracing::error!(%cause, "Failed to retrieve stale orders!"),
+    };
+}
+
+async fn expire(app_state: &AppState, order_id: i64) -> Result<(), AppError> {
+    let transaction = app_state.database_connection().begin().await?;
+
+    let change = OrderService::expire_order(order_id, &transaction).await?;
+    if !change.changed {
+        return Ok(()); // User has paid or cancelled it in the meantime
+    }
+
+    let automessage = automessage(
+        app_state,
+        &change.order,
+        "automessage-expired",
+        &transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    publish(
+        app_state,
+        OrderEventKind::Cancelled,
+        change.order,
+        automessage,
+    )
+    .await;
+    Ok(())
+}
+
+async fn flag(app_state: &AppState, order_id: i64) -> Result<(), AppError> {
+    let transaction = app_state.database_connection().begin().await?;
+
+    let order = match OrderService::flag_order(order_id, &transaction).await? {
+        Some(order) => order,
+        None => return Ok(()), // Moderator has acted in the meantime
+    };
+
+    let automessage = automessage(app_state, &order, "automessage-stale", &transaction).await?;
+
+    transaction.commit().await?;
+
+    publish(app_state, OrderEventKind::Flagged, order, automessage).await;
+    Ok(())
+}
+
+//? Text will be parsed by frontend to a normal message of moderator
+async fn automessage(
+    app_state: &AppState,
+    order: &OrderModel,
+    text: &str,
+    transaction: &DatabaseTransaction,
+) -> Result<Option<(i64, SendMessageResponse)>, AppError> {
+    let chat = match order.find_related(ChatEntity).one(transaction).await? {
+        Some(chat) => chat,
+        None => return Ok(None),
+    };
+
+    let params = SendMessageParameters {
+        folder: app_state.configuration().upload_folder().clone(),
+        chat_id: chat.id,
+        sender: Sender::Automessage,
+        text: String::from(text),
+        image: None,
+    };
+
+    let (message, _) = ChatService::send_message(params, transaction).await?;
+
+    Ok(Some((
+        chat.id,
+        SendMessageResponse {
+            message: Into::<Message>::into(message),
+            images_ids: vec![], // No images in automessage
+        },
+    )))
+}
+
+async fn publish(
+    app_state: &AppState,
+    kind: OrderEventKind,
+    order: OrderModel,
+    automessage: Option<(i64, SendMessageResponse)>,
+) {
+    if let Some((chat_id, send)) = automessage {
+        match app_state.redis_client().get_async_connection().await {
+            Ok(mut connection) => {
+                let _: Result<(), _> = connection
+                    .publish(
+                        format!("chat-{}", chat_id),
+                        serde_json::to_string(&send).unwrap(),
+                    )
+                    .await;
+            }
+            Err(cause) => {
+                // Not very important
+                tracing::warn!(%cause, "Failed to connect to redis!");
+            }
+        };
+    }
+
+    let event = OrderEvent::new(kind, order);
+    EventsService::publish(
+        &event,
+        app_state.configuration().order_events_channel_name(),
+        app_state.redis_client(),
+    )
+    .await;
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use sea_orm::{prelude::Decimal, TransactionTrait};
+use url::Url;
+
+use crate::{
+    config::{Configuration, RatesProviderKind},
+    errors::AppError,
+    services::{
+        currency::{ProviderRateOutcome, Service as CurrencyService},
+        rates::{FileRateProvider, HttpRateProvider, RateProvider},
+    },
+    state::AppState,
+};
+
+//? Returns None when provider is disabled or misconfigured
+pub fn provider(configuration: &Configuration) -> Option<Arc<dyn RateProvider>> {
+    let source = match (
+        configuration.rates_provider(),
+        configuration.rates_provider_source(),
+    ) {
+        (RatesProviderKind::None, _) => return None,
+        (_, Some(source)) if !source.is_empty() => source,
+        (kind, _) => {
+            tracing::error!(?kind, "Rates provider is enabled but source was not set!");
+            return None;
+        }
+    };
+
+    match configuration.rates_provider() {
+        RatesProviderKind::None => None,
+        RatesProviderKind::File => Some(Arc::new(FileRateProvider::new(source))),
+        RatesProviderKind::Http => match Url::parse(source) {
+            Ok(url) => Some(Arc::new(HttpRateProvider::new(url))),
+            Err(cause) => {
+                tracing::error!(%cause, "Rates provider source is not a valid url!");
+                None
+            }
+        },
+    }
+}
+
+//* Pulls fresh rates from provider and marks outdated rates as stale
+#[tracing::instrument(skip(app_state, provider), fields(provider = provider.name()))]
+pub async fn refresh(app_state: Arc<AppState>, provider: Arc<dyn RateProvider>) {
+    match provider.fetch().await {
+        Ok(rates) => match CurrencyService::auto_updated(app_state.database_connection()).await {
+            Ok(currencies) => {
+                for currency in currencies {
+                    let raw = match rates.get(&currency.symbol) {
+                        Some(raw) => *raw,
+                        None => continue, // Will become stale eventually
+                    };
+
+                    if let Err(cause) = apply(&app_state, currency.id, raw).await {
+                        tracing::error!(?cause, symbol = currency.symbol, "Failed to apply rate!");
+                    }
+                }
+            }
+            Err(cause) => tracing::error!(%cause, "Failed to retrieve currencies!"),
+        },
+        Err(cause) => tracing::error!(%cause, "Failed to fetch rates!"),
+    };
+
+    let updated_before = Utc::now().naive_local()
+        - Duration::seconds(app_state.configuration().rates_stale_after_seconds());
+    match CurrencyService::mark_stale(updated_before, app_state.database_connection()).await {
+        Ok(0) => {}
+        Ok(count) => tracing::warn!(count, "Currency rates became stale!"),
+        Err(cause) => tracing::error!(%cause, "Failed to mark stale rates!"),
+    };
+}
+
+async fn apply(app_state: &AppState, id: i64, raw: Decimal) -> Result<(), AppError> {
+    let transaction = app_state.database_connection().begin().await?;
+
+    match CurrencyService::apply_provider_rate(id, raw, &transaction).await? {
+        ProviderRateOutcome::PendingApproval(rate) => tracing::warn!(
+            symbol = rate.symbol,
+            %rate.rate,
+            pending = ?rate.pending_rate,
+            "Rate jump requires approval!"
+        ),
+        ProviderRateOutcome::Updated(rate) => {
+            tracing::info!(symbol = rate.symbol, %rate.rate, "Rate was updated")
+        }
+        ProviderRateOutcome::Unchanged | ProviderRateOutcome::Skipped => {}
+    };
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration(provider: &str, source: Option<&str>) -> Configuration {
+        let mut variables = vec![
+            ("database_url", "postgres://localhost/buff"),
+            ("redis_url", "redis://localhost"),
+            ("sqlx_logging", "false"),
+            ("port", "3000"),
+            ("jwt_secret", "secret"),
+            ("status_expiration_seconds", "60"),
+            ("realm", "buff"),
+            ("upload_folder", "uploads"),
+            ("jwt_ttl", "3600"),
+            ("rates_provider", provider),
+        ];
+        variables.extend(source.map(|source| ("rates_provider_source", source)));
+
+        envy::from_iter(
+            variables
+                .into_iter()
+                .map(|(key, value)| (key.to_uppercase(), value.to_owned())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn disabled_provider_is_missing() {
+        assert!(provider(&configuration("none", Some("rates.json"))).is_none());
+    }
+
+    #[test]
+    fn provider_without_source_is_missing() {
+        assert!(provider(&configuration("file", None)).is_none());
+        assert!(provider(&configuration("http", Some(""))).is_none());
+    }
+
+    #[test]
+    fn provider_with_invalid_url_is_missing() {
+        assert!(provider(&configuration("http", Some("not a url"))).is_none());
+    }
+
+    #[test]
+    fn configured_provider_is_built() {
+        assert!(provider(&configuration("file", Some("rates.json"))).is_some());
+        assert!(provider(&configuration("http", Some("https://rates.example/latest"))).is_some());
+    }
+}
@@ -1,9 +1,23 @@
-use entity::blacklisted::{ActiveModel as BlacklistedActiveModel, Entity as BlacklistedEntity};
+use chrono::{NaiveDateTime, Utc};
+use entity::blacklisted::{
+    ActiveModel as BlacklistedActiveModel, Column as BlacklistedColumn,
+    Entity as BlacklistedEntity, Model as BlacklistedModel,
+};
 
 use entity::user::Entity as UserEntity;
-use sea_orm::{prelude::*, Set, TransactionTrait};
+use migration::{Query, SelectStatement};
+use sea_orm::{prelude::*, Condition, Set, TransactionTrait};
 
-use crate::errors::AppError;
+use crate::{
+    errors::AppError,
+    services::{
+        pagination::{
+            Page, PageRequest, Service as PaginationService, ServiceError as PaginationError, Sort,
+            SortKey,
+        },
+        sessions::{Service as SessionService, ServiceError as SessionError, Subject},
+    },
+};
 
 #[allow(dead_code)]
 pub struct Service;
@@ -18,6 +32,10 @@ pub enum ServiceError {
     UserAlreadyBlacklisted,
     #[error("User is not blacklisted")]
     UserNotBlacklisted,
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
 }
 
 impl From<ServiceError> for AppError {
@@ -27,35 +45,124 @@ impl From<ServiceError> for AppError {
             ServiceError::UserWasNotFound(id) => AppError::UserWasNotFound(id),
             ServiceError::UserAlreadyBlacklisted => AppError::UserAlreadyBlacklisted,
             ServiceError::UserNotBlacklisted => AppError::UserNotBlacklisted,
+            ServiceError::Pagination(cause) => cause.into(),
+            ServiceError::Session(cause) => cause.into(),
+        }
+    }
+}
+
+//? Parts of user api a ban can block
+//? A ban which blocks all of them blocks user api completely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Ordering,
+    Chatting,
+    Reviewing,
+}
+
+impl Capability {
+    pub fn is_blocked_by(&self, ban: &BlacklistedModel) -> bool {
+        match self {
+            Capability::Ordering => ban.blocks_ordering,
+            Capability::Chatting => ban.blocks_chatting,
+            Capability::Reviewing => ban.blocks_reviewing,
         }
     }
 }
 
+#[derive(Debug)]
+pub struct BlacklistUserParameters {
+    pub steam_id: i64,
+    pub reason: Option<String>,
+    //? None means permanent ban
+    pub expires_at: Option<NaiveDateTime>,
+    pub blocks_ordering: bool,
+    pub blocks_chatting: bool,
+    pub blocks_reviewing: bool,
+    pub admin_id: Option<i64>,
+}
+
 impl Service {
+    pub fn is_full(ban: &BlacklistedModel) -> bool {
+        ban.blocks_ordering && ban.blocks_chatting && ban.blocks_reviewing
+    }
+
+    fn active_condition() -> Condition {
+        Condition::any()
+            .add(BlacklistedColumn::ExpiresAt.is_null())
+            .add(BlacklistedColumn::ExpiresAt.gt(Utc::now().naive_local()))
+    }
+
+    //? Steam ids of users with a ban which has not expired yet
+    //? Used to hide blacklisted users from public lists
+    pub fn active_subquery() -> SelectStatement {
+        Query::select()
+            .column(BlacklistedColumn::SteamId)
+            .from(BlacklistedEntity)
+            .cond_where(Self::active_condition())
+            .to_owned()
+    }
+
     #[tracing::instrument(skip(connection))]
-    pub async fn blacklist_user<T>(steam_id: i64, connection: &T) -> Result<(), ServiceError>
+    pub async fn active<T>(
+        steam_id: i64,
+        connection: &T,
+    ) -> Result<Option<BlacklistedModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        match UserEntity::find_by_id(steam_id).one(connection).await? {
-            None => Err(ServiceError::UserWasNotFound(steam_id)),
-            Some(user) => match user.find_related(BlacklistedEntity).one(connection).await? {
-                Some(_) => Err(ServiceError::UserAlreadyBlacklisted),
-                None => {
-                    let user_to_be_blacklisted = BlacklistedActiveModel {
-                        steam_id: Set(steam_id),
-                        ..Default::default()
-                    };
-
-                    BlacklistedEntity::insert(user_to_be_blacklisted)
-                        .exec(connection)
-                        .await?;
-                    Ok(())
-                }
-            },
+        Ok(BlacklistedEntity::find()
+            .filter(BlacklistedColumn::SteamId.eq(steam_id))
+            .filter(Self::active_condition())
+            .one(connection)
+            .await?)
+    }
+
+    //? Expired bans are kept as history next to the new one
+    #[tracing::instrument(skip(connection))]
+    pub async fn blacklist_user<T>(
+        parameters: BlacklistUserParameters,
+        connection: &T,
+    ) -> Result<BlacklistedModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let user = match UserEntity::find_by_id(parameters.steam_id)
+            .one(connection)
+            .await?
+        {
+            Some(user) => user,
+            None => return Err(ServiceError::UserWasNotFound(parameters.steam_id)),
+        };
+
+        if Self::active(user.steam_id, connection).await?.is_some() {
+            return Err(ServiceError::UserAlreadyBlacklisted);
         }
+
+        let user_to_be_blacklisted = BlacklistedActiveModel {
+            steam_id: Set(user.steam_id),
+            reason: Set(parameters.reason),
+            expires_at: Set(parameters.expires_at),
+            blocks_ordering: Set(parameters.blocks_ordering),
+            blocks_chatting: Set(parameters.blocks_chatting),
+            blocks_reviewing: Set(parameters.blocks_reviewing),
+            admin_id: Set(parameters.admin_id),
+            ..Default::default()
+        };
+
+        let ban = BlacklistedEntity::insert(user_to_be_blacklisted)
+            .exec_with_returning(connection)
+            .await?;
+
+        //? Fully banned user can not refresh and their access tokens stop working
+        if Self::is_full(&ban) {
+            SessionService::revoke_all(Subject::User(ban.steam_id), connection).await?;
+        }
+
+        Ok(ban)
     }
 
+    //? Lifted ban expires now and stays in the history
     #[tracing::instrument(skip(connection))]
     pub async fn unblacklist_user<T>(steam_id: i64, connection: &T) -> Result<(), ServiceError>
     where
@@ -63,14 +170,12 @@ impl Service {
     {
         match UserEntity::find_by_id(steam_id).one(connection).await? {
             None => Err(ServiceError::UserWasNotFound(steam_id)),
-            Some(user) => match user.find_related(BlacklistedEntity).one(connection).await? {
+            Some(user) => match Self::active(user.steam_id, connection).await? {
                 None => Err(ServiceError::UserNotBlacklisted),
-                Some(blacklisted_user) => {
-                    let user_to_be_unblacklisted: BlacklistedActiveModel = blacklisted_user.into();
-
-                    BlacklistedEntity::delete(user_to_be_unblacklisted)
-                        .exec(connection)
-                        .await?;
+                Some(ban) => {
+                    let mut ban_to_be_lifted: BlacklistedActiveModel = ban.into();
+                    ban_to_be_lifted.expires_at = Set(Some(Utc::now().naive_local()));
+                    ban_to_be_lifted.update(connection).await?;
                     Ok(())
                 }
             },
@@ -78,26 +183,52 @@ impl Service {
     }
 
     #[tracing::instrument(skip(connection))]
-    pub async fn all<T>(connection: &T) -> Result<Vec<i64>, ServiceError>
+    pub async fn all<T>(
+        page: &PageRequest,
+        connection: &T,
+    ) -> Result<Page<BlacklistedModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        //? I thought that creating custom
-        //? select with partial columns
-        //? will be a bit overhead
-        //? We do not have high load
-        //? at this endpoint
-
         //* Sadly we cannot get rid of
         //* id as primary key because
         //* derive on entity fails to compile
         //* And we still need an ability
         //* to call user.find_related(BlacklistedEntity)
-        Ok(BlacklistedEntity::find()
-            .all(connection)
-            .await?
-            .into_iter()
-            .map(|model| model.steam_id)
-            .collect::<Vec<_>>())
+        Ok(PaginationService::paginate(
+            BlacklistedEntity::find().filter(Self::active_condition()),
+            BlacklistedColumn::Id,
+            Self::sort(),
+            page,
+            connection,
+        )
+        .await?)
+    }
+
+    //? Expired bans are included so admins can see the past ones
+    #[tracing::instrument(skip(connection))]
+    pub async fn entries<T>(
+        page: &PageRequest,
+        connection: &T,
+    ) -> Result<Page<BlacklistedModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(PaginationService::paginate(
+            BlacklistedEntity::find(),
+            BlacklistedColumn::Id,
+            Self::sort(),
+            page,
+            connection,
+        )
+        .await?)
+    }
+
+    fn sort() -> Sort<BlacklistedEntity> {
+        Sort {
+            name: "created_at",
+            column: BlacklistedColumn::CreatedAt,
+            key: |ban| SortKey::Timestamp(ban.created_at),
+        }
     }
 }
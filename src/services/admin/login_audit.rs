@@ -0,0 +1,138 @@
+use chrono::NaiveDateTime;
+use entity::{
+    admin::{Column as AdminColumn, Entity as AdminEntity},
+    sea_orm_active_enums::{LoginOutcome, LoginSource},
+    staff_login_audit::{
+        ActiveModel as StaffLoginAuditActiveModel, Column as StaffLoginAuditColumn,
+        Entity as StaffLoginAuditEntity, Model as StaffLoginAuditModel,
+    },
+};
+use sea_orm::{prelude::*, Set, TransactionTrait};
+
+use crate::{
+    errors::AppError,
+    services::pagination::{
+        Page, PageRequest, Service as PaginationService, ServiceError as PaginationError, Sort,
+        SortKey,
+    },
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::Pagination(cause) => cause.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoginAttempt {
+    pub login: String,
+    pub outcome: LoginOutcome,
+    pub source: LoginSource,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct LoginAuditFilter {
+    pub admin_id: Option<i64>,
+    pub login: Option<String>,
+    pub outcome: Option<LoginOutcome>,
+    pub source: Option<LoginSource>,
+    pub ip: Option<String>,
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct SearchLoginAuditParameters {
+    pub filter: LoginAuditFilter,
+    pub page: PageRequest,
+}
+
+pub struct Service;
+
+impl Service {
+    //? Account is looked up by login so attempts on unknown logins are kept too
+    #[tracing::instrument(skip(connection))]
+    pub async fn record<T>(attempt: LoginAttempt, connection: &T) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let admin_id = AdminEntity::find()
+            .filter(AdminColumn::Login.eq(&attempt.login))
+            .one(connection)
+            .await?
+            .map(|admin| admin.id);
+
+        let entry = StaffLoginAuditActiveModel {
+            admin_id: Set(admin_id),
+            login: Set(attempt.login),
+            outcome: Set(attempt.outcome),
+            source: Set(attempt.source),
+            ip: Set(attempt.ip),
+            user_agent: Set(attempt.user_agent),
+            ..Default::default()
+        };
+        entry.insert(connection).await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn search<T>(
+        parameters: SearchLoginAuditParameters,
+        connection: &T,
+    ) -> Result<Page<StaffLoginAuditModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let filter = parameters.filter;
+        let mut select = StaffLoginAuditEntity::find();
+
+        if let Some(admin_id) = filter.admin_id {
+            select = select.filter(StaffLoginAuditColumn::AdminId.eq(admin_id));
+        }
+        if let Some(login) = filter.login {
+            select = select.filter(StaffLoginAuditColumn::Login.eq(login));
+        }
+        if let Some(outcome) = filter.outcome {
+            select = select.filter(StaffLoginAuditColumn::Outcome.eq(outcome));
+        }
+        if let Some(source) = filter.source {
+            select = select.filter(StaffLoginAuditColumn::Source.eq(source));
+        }
+        if let Some(ip) = filter.ip {
+            select = select.filter(StaffLoginAuditColumn::Ip.eq(ip));
+        }
+        if let Some(created_from) = filter.created_from {
+            select = select.filter(StaffLoginAuditColumn::CreatedAt.gte(created_from));
+        }
+        if let Some(created_to) = filter.created_to {
+            select = select.filter(StaffLoginAuditColumn::CreatedAt.lte(created_to));
+        }
+
+        Ok(PaginationService::paginate(
+            select,
+            StaffLoginAuditColumn::Id,
+            Sort {
+                name: "created_at",
+                column: StaffLoginAuditColumn::CreatedAt,
+                key: |entry| SortKey::Timestamp(entry.created_at),
+            },
+            &parameters.page,
+            connection,
+        )
+        .await?)
+    }
+}
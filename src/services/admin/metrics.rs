@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use entity::{
+    admin::{Column as AdminColumn, Entity as AdminEntity},
+    order::{Column as OrderColumn, Entity as OrderEntity},
+    sea_orm_active_enums::{Permission, Status},
+};
+use migration::{Expr, Query};
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, Condition, ConnectionTrait, EntityTrait, FromQueryResult,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use crate::{errors::AppError, services::admin::permissions::Service as PermissionsService};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MetricsParameters {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+}
+
+#[derive(Debug, Default, FromQueryResult)]
+struct FinishedOrders {
+    moderator_id: i64,
+    succeeded: i64,
+    cancelled: i64,
+    converted_volume: Decimal,
+}
+
+#[derive(Debug)]
+pub struct ModeratorMetrics {
+    pub moderator_id: i64,
+    pub login: String,
+    //? Orders finished in the period by their final status
+    pub succeeded: i64,
+    pub cancelled: i64,
+    //? Orders which are open right now whatever the period is
+    pub open_orders: i64,
+    pub converted_volume: Decimal,
+    //? From order creation to the first message of the assigned moderator
+    pub median_first_response_seconds: Option<i64>,
+    //? From the user marking an order as paid to its success
+    pub median_confirmation_seconds: Option<i64>,
+}
+
+pub struct Service;
+
+impl Service {
+    //? Medians interpolate between the two middle durations for even amounts
+    //? Orders without a response or confirmation are left out of them
+    #[tracing::instrument(skip(connection))]
+    pub async fn metrics<T>(
+        parameters: MetricsParameters,
+        connection: &T,
+    ) -> Result<Vec<ModeratorMetrics>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        //? Admins may handle orders as well but are only listed once they actually do
+        let moderators = AdminEntity::find()
+            .filter(PermissionsService::held(Permission::HandleOrders))
+            .filter(
+                Condition::any().add(AdminColumn::OnShift.eq(true)).add(
+                    AdminColumn::Id.in_subquery(
+                        Query::select()
+                            .column(OrderColumn::ModeratorId)
+                            .from(OrderEntity)
+                            .and_where(OrderColumn::ModeratorId.is_not_null())
+                            .to_owned(),
+                    ),
+                ),
+            )
+            .order_by_asc(AdminColumn::Id)
+            .all(connection)
+            .await?;
+
+        let mut finished: HashMap<i64, FinishedOrders> = OrderEntity::find()
+            .filter(OrderColumn::ModeratorId.is_not_null())
+            .filter(OrderColumn::FinishedAt.between(parameters.from, parameters.to))
+            .select_only()
+            .column(OrderColumn::ModeratorId)
+            .column_as(
+                Expr::cust(r#"count(*) filter (where "order".status = 'succeeded')"#),
+                "succeeded",
+            )
+            .column_as(
+                Expr::cust(r#"count(*) filter (where "order".status = 'cancelled')"#),
+                "cancelled",
+            )
+            .column_as(
+                Expr::cust(
+                    r#"coalesce(sum("order".amount * "order".fixed_currency_rate) filter (where "order".status = 'succeeded'), 0)"#,
+                ),
+                "converted_volume",
+            )
+            .group_by(OrderColumn::ModeratorId)
+            .into_model::<FinishedOrders>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .map(|row| (row.moderator_id, row))
+            .collect();
+
+        let open_orders: HashMap<i64, i64> = OrderEntity::find()
+            .filter(OrderColumn::ModeratorId.is_not_null())
+            .filter(OrderColumn::Status.is_in([Status::Created, Status::Maybepayed]))
+            .select_only()
+            .column(OrderColumn::ModeratorId)
+            .column_as(OrderColumn::Id.count(), "open_orders")
+            .group_by(OrderColumn::ModeratorId)
+            .into_tuple::<(i64, i64)>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .collect();
+
+        //? Only messages written by the assigned moderator count, automessages have no sender
+        let first_responses: HashMap<i64, Option<i64>> = OrderEntity::find()
+            .filter(OrderColumn::ModeratorId.is_not_null())
+            .filter(OrderColumn::CreatedAt.between(parameters.from, parameters.to))
+            .select_only()
+            .column(OrderColumn::ModeratorId)
+            .column_as(
+                Expr::cust(
+                    r#"extract(epoch from percentile_cont(0.5) within group (order by (
+                        select min("message".created_at)
+                        from "message" join "chat" on "chat".id = "message".chat_id
+                        where "chat".order_id = "order".id
+                        and "message".sender = 'moderator'
+                        and "message".sender_id = "order".moderator_id
+                    ) - "order".created_at))::bigint"#,
+                ),
+                "seconds",
+            )
+            .group_by(OrderColumn::ModeratorId)
+            .into_tuple::<(i64, Option<i64>)>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .collect();
+
+        let confirmations: HashMap<i64, Option<i64>> = OrderEntity::find()
+            .filter(OrderColumn::ModeratorId.is_not_null())
+            .filter(OrderColumn::Status.eq(Status::Succeeded))
+            .filter(OrderColumn::FinishedAt.between(parameters.from, parameters.to))
+            .select_only()
+            .column(OrderColumn::ModeratorId)
+            .column_as(
+                Expr::cust(
+                    r#"extract(epoch from percentile_cont(0.5) within group (order by "order".finished_at - (
+                        select max("order_status_history".created_at)
+                        from "order_status_history"
+                        where "order_status_history".order_id = "order".id
+                        and "order_status_history".to_status = 'maybepayed'
+                    )))::bigint"#,
+                ),
+                "seconds",
+            )
+            .group_by(OrderColumn::ModeratorId)
+            .into_tuple::<(i64, Option<i64>)>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(moderators
+            .into_iter()
+            .map(|moderator| {
+                let orders = finished.remove(&moderator.id).unwrap_or_default();
+                ModeratorMetrics {
+                    moderator_id: moderator.id,
+                    login: moderator.login,
+                    succeeded: orders.succeeded,
+                    cancelled: orders.cancelled,
+                    open_orders: open_orders.get(&moderator.id).copied().unwrap_or(0),
+                    converted_volume: orders.converted_volume,
+                    median_first_response_seconds: first_responses
+                        .get(&moderator.id)
+                        .copied()
+                        .flatten(),
+                    median_confirmation_seconds: confirmations
+                        .get(&moderator.id)
+                        .copied()
+                        .flatten(),
+                }
+            })
+            .collect())
+    }
+}
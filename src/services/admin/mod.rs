@@ -1,2 +1,5 @@
 pub mod blacklist;
+pub mod login_audit;
+pub mod metrics;
 pub mod moderators;
+pub mod permissions;
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use chrono::{Duration, NaiveDateTime, Utc};
 use entity::{
     admin::{
         ActiveModel as AdminActiveModel, Column as AdminColumn, Entity as AdminEntity,
@@ -10,12 +11,34 @@ use entity::{
         ActiveModel as OrderActiveModel, Column as OrderColumn, Entity as OrderEntity,
         Model as OrderModel,
     },
-    sea_orm_active_enums::{Role, Status},
+    sea_orm_active_enums::{Permission, Role, Status},
 };
 use rand_core::OsRng;
-use sea_orm::{prelude::*, QueryOrder, Set, TransactionTrait};
+use sea_orm::{prelude::*, QuerySelect, Set, SqlErr, TransactionTrait};
 
-use crate::errors::AppError;
+use crate::{
+    errors::AppError,
+    services::{
+        admin::permissions::{Service as PermissionsService, ServiceError as PermissionsError},
+        orders::ListOrdersParameters,
+        pagination::{Page, Service as PaginationService, ServiceError as PaginationError},
+        sessions::{Service as SessionService, ServiceError as SessionError, Subject},
+    },
+};
+
+const ACTIVITY_RESOLUTION_SECONDS: i64 = 60;
+
+//? Unique index allowing one account per login
+const ADMIN_LOGIN_INDEX: &str = "IDX_admin_login";
+
+//? Login checks miss a login taken by a concurrent request, the unique index catches it
+fn login_occupied(cause: &DbErr) -> bool {
+    matches!(
+        cause.sql_err(),
+        Some(SqlErr::UniqueConstraintViolation(ref constraint))
+            if constraint.contains(ADMIN_LOGIN_INDEX)
+    )
+}
 
 #[allow(dead_code)]
 pub struct Service;
@@ -30,8 +53,8 @@ pub enum ServiceError {
     PasswordHashError(#[from] argon2::password_hash::Error),
     #[error("Admin or moderator was not found")]
     AdminNotFound,
-    #[error("Moderator has admin role")]
-    ModeratorIsAdmin,
+    #[error("Staff can not handle orders")]
+    CanNotHandleOrders,
     #[error("Moderator has already been assigned to this order")]
     ModeratorAlreadyAssigned,
     #[error("Order was not found")]
@@ -42,6 +65,18 @@ pub enum ServiceError {
     AnotherModeratorAlreadyAssigned,
     #[error("Order is completed or cancelled")]
     OrderIsCompletedOrCancelled,
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
+    #[error(transparent)]
+    Session(#[from] SessionError),
+    #[error(transparent)]
+    Permissions(#[from] PermissionsError),
+    #[error("Staff holds permissions which the manager does not have")]
+    StaffOutranksManager,
+    #[error("Staff is not disabled")]
+    StaffNotDisabled,
+    #[error("Staff is disabled")]
+    StaffDisabled,
 }
 
 impl From<ServiceError> for AppError {
@@ -53,12 +88,18 @@ impl From<ServiceError> for AppError {
                 AppError::InternalServerError(Box::new(cause))
             }
             ServiceError::AdminNotFound => AppError::AdminNotFound,
-            ServiceError::ModeratorIsAdmin => AppError::ModeratorIsAdmin,
+            ServiceError::CanNotHandleOrders => AppError::StaffCanNotHandleOrders,
             ServiceError::ModeratorAlreadyAssigned => AppError::ModeratorAlreadyAssigned,
             ServiceError::OrderWasNotFound => AppError::OrderWasNotFound,
             ServiceError::ModeratorNotAssigned => AppError::ModeratorNotAssigned,
             ServiceError::AnotherModeratorAlreadyAssigned => AppError::ModeratorAlreadyAssigned,
             ServiceError::OrderIsCompletedOrCancelled => AppError::OrderIsCompletedOrCancelled,
+            ServiceError::Pagination(cause) => cause.into(),
+            ServiceError::Session(cause) => cause.into(),
+            ServiceError::Permissions(cause) => cause.into(),
+            ServiceError::StaffOutranksManager => AppError::StaffOutranksManager,
+            ServiceError::StaffNotDisabled => AppError::StaffNotDisabled,
+            ServiceError::StaffDisabled => AppError::StaffDisabled,
         }
     }
 }
@@ -76,6 +117,19 @@ impl Debug for CreateModeratorParameters {
     }
 }
 
+pub struct CreateAdminParameters {
+    pub login: String,
+    pub password: String,
+}
+
+impl Debug for CreateAdminParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreateAdminParameters")
+            .field("login", &self.login)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct AssignModeratorParameters {
     pub moderator_id: i64,
@@ -88,17 +142,77 @@ pub struct UnassignModeratorParameters {
     pub order_id: i64,
 }
 
+#[derive(Debug)]
+pub struct ChangeLoginParameters {
+    pub manager_id: i64,
+    pub admin_id: i64,
+    pub login: String,
+}
+
+#[derive(Debug)]
+pub struct IssuePasswordResetParameters {
+    pub manager_id: i64,
+    pub moderator_id: i64,
+    pub ttl_seconds: i64,
+}
+
+pub struct PasswordReset {
+    pub token: String,
+    pub expires_at: NaiveDateTime,
+}
+
+//? This is manual for hiding the token in logs
+impl Debug for PasswordReset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordReset")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+//? Capacity of None means unlimited
+#[derive(Debug)]
+pub struct SetAssignmentParameters {
+    pub moderator_id: i64,
+    pub capacity: Option<i32>,
+    pub on_shift: bool,
+}
+
 impl Service {
     #[tracing::instrument(skip(connection))]
     pub async fn create_moderator<T>(
         params: CreateModeratorParameters,
         connection: &T,
     ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Self::create_staff(params.login, params.password, Role::Moderator, connection).await
+    }
+
+    //? Only used for bootstrapping, other admins are made by granting permissions
+    #[tracing::instrument(skip(connection))]
+    pub async fn create_admin<T>(
+        params: CreateAdminParameters,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Self::create_staff(params.login, params.password, Role::Admin, connection).await
+    }
+
+    async fn create_staff<T>(
+        login: String,
+        password: String,
+        role: Role,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
         match AdminEntity::find()
-            .filter(AdminColumn::Login.eq(&params.login))
+            .filter(AdminColumn::Login.eq(&login))
             .one(connection)
             .await?
         {
@@ -107,76 +221,265 @@ impl Service {
                 let salt = SaltString::generate(&mut OsRng);
 
                 let hashed_password = Argon2::default()
-                    .hash_password(params.password.as_bytes(), &salt)?
+                    .hash_password(password.as_bytes(), &salt)?
                     .to_string();
 
+                //? Admins hold the permission to handle orders too
+                //? but only get them assigned once they start a shift themselves
                 let admin_to_be_inserted = AdminActiveModel {
-                    login: Set(params.login),
+                    login: Set(login),
                     password: Set(hashed_password),
-                    role: Set(Role::Moderator),
+                    on_shift: Set(role == Role::Moderator),
+                    role: Set(role),
                     ..Default::default()
                 };
 
-                Ok(AdminEntity::insert(admin_to_be_inserted)
+                match AdminEntity::insert(admin_to_be_inserted)
                     .exec_with_returning(connection)
-                    .await?)
+                    .await
+                {
+                    Ok(staff) => Ok(staff),
+                    Err(cause) if login_occupied(&cause) => Err(ServiceError::LoginAlreadyExists),
+                    Err(cause) => Err(cause.into()),
+                }
             }
         }
     }
 
+    //? Accounts with a publicly known password hash are disabled and get a random password,
+    //? whoever manages staff has to issue them a password reset and enable them again
+    #[tracing::instrument(skip_all)]
+    pub async fn disable_by_password_hash<T>(
+        password_hash: &str,
+        connection: &T,
+    ) -> Result<Vec<AdminModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let exposed = AdminEntity::find()
+            .filter(AdminColumn::Password.eq(password_hash))
+            .all(connection)
+            .await?;
+
+        let mut disabled = Vec::with_capacity(exposed.len());
+        for admin_or_moderator in exposed {
+            let salt = SaltString::generate(&mut OsRng);
+            let random_password = uuid::Uuid::new_v4().simple().to_string();
+            let hashed_password = Argon2::default()
+                .hash_password(random_password.as_bytes(), &salt)?
+                .to_string();
+
+            let staff_id = admin_or_moderator.id;
+            let mut staff_to_be_disabled: AdminActiveModel = admin_or_moderator.into();
+            staff_to_be_disabled.password = Set(hashed_password);
+            staff_to_be_disabled.disabled_at = Set(Some(Utc::now().naive_local()));
+            staff_to_be_disabled.on_shift = Set(false);
+            staff_to_be_disabled.password_reset_token = Set(None);
+            staff_to_be_disabled.password_reset_expires_at = Set(None);
+            disabled.push(staff_to_be_disabled.update(connection).await?);
+
+            SessionService::revoke_all(Subject::Staff(staff_id), connection).await?;
+        }
+
+        Ok(disabled)
+    }
+
+    //? Disabled staff keep their finished orders and chats
+    //? Returned orders are the open ones which were unassigned
+    //? Anyone the manager outranks can be disabled except the last one able to manage staff
     #[tracing::instrument(skip(connection))]
-    pub async fn delete_moderator<T>(moderator_id: i64, connection: &T) -> Result<(), ServiceError>
+    pub async fn disable_moderator<T>(
+        manager_id: i64,
+        moderator_id: i64,
+        connection: &T,
+    ) -> Result<Vec<OrderModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        match AdminEntity::find_by_id(moderator_id)
+        let admin_or_moderator = Self::managed(manager_id, moderator_id, connection).await?;
+
+        let unassigned_orders = OrderEntity::update_many()
+            .col_expr(OrderColumn::ModeratorId, Expr::value::<Option<i64>>(None))
+            .filter(
+                OrderColumn::ModeratorId.eq(moderator_id).and(
+                    OrderColumn::Status
+                        .eq(Status::Created)
+                        .or(OrderColumn::Status.eq(Status::Maybepayed)),
+                ),
+            )
+            .exec_with_returning(connection)
+            .await?;
+
+        if admin_or_moderator.disabled_at.is_none() {
+            let mut moderator_to_be_disabled: AdminActiveModel = admin_or_moderator.into();
+            moderator_to_be_disabled.disabled_at = Set(Some(Utc::now().naive_local()));
+            moderator_to_be_disabled.on_shift = Set(false);
+            moderator_to_be_disabled.password_reset_token = Set(None);
+            moderator_to_be_disabled.password_reset_expires_at = Set(None);
+            moderator_to_be_disabled.update(connection).await?;
+        }
+
+        //? Callers roll the transaction back on error
+        if PermissionsService::staff_managers(connection).await? == 0 {
+            return Err(PermissionsError::LastStaffManager.into());
+        }
+
+        SessionService::revoke_all(Subject::Staff(moderator_id), connection).await?;
+
+        Ok(unassigned_orders)
+    }
+
+    //? Moderator stays off shift until they start it themselves
+    #[tracing::instrument(skip(connection))]
+    pub async fn enable_moderator<T>(
+        manager_id: i64,
+        moderator_id: i64,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderator = Self::managed(manager_id, moderator_id, connection).await?;
+
+        if moderator.disabled_at.is_none() {
+            return Err(ServiceError::StaffNotDisabled);
+        }
+
+        let mut moderator_to_be_enabled: AdminActiveModel = moderator.into();
+        moderator_to_be_enabled.disabled_at = Set(None);
+        Ok(moderator_to_be_enabled.update(connection).await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn change_login<T>(
+        parameters: ChangeLoginParameters,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let staff = Self::managed(parameters.manager_id, parameters.admin_id, connection).await?;
+
+        if AdminEntity::find()
+            .filter(AdminColumn::Login.eq(&parameters.login))
+            .filter(AdminColumn::Id.ne(staff.id))
             .one(connection)
             .await?
+            .is_some()
         {
-            Some(admin_or_moderator) => match admin_or_moderator.role {
-                Role::Admin => Err(ServiceError::ModeratorIsAdmin),
-                Role::Moderator => {
-                    OrderEntity::update_many()
-                        .col_expr(OrderColumn::ModeratorId, Expr::value::<Option<i64>>(None))
-                        .filter(
-                            OrderColumn::ModeratorId.eq(moderator_id).and(
-                                OrderColumn::Status
-                                    .eq(Status::Created)
-                                    .or(OrderColumn::Status.eq(Status::Maybepayed)),
-                            ),
-                        )
-                        .exec(connection)
-                        .await?;
-
-                    let moderator_to_be_deleted: AdminActiveModel = admin_or_moderator.into();
-                    moderator_to_be_deleted.delete(connection).await?;
-                    Ok(())
-                }
-            },
-            None => Err(ServiceError::AdminNotFound),
+            return Err(ServiceError::LoginAlreadyExists);
+        }
+
+        let mut staff_to_be_renamed: AdminActiveModel = staff.into();
+        staff_to_be_renamed.login = Set(parameters.login);
+
+        match staff_to_be_renamed.update(connection).await {
+            Ok(staff) => Ok(staff),
+            Err(cause) if login_occupied(&cause) => Err(ServiceError::LoginAlreadyExists),
+            Err(cause) => Err(cause.into()),
         }
     }
 
+    //? Only the hash of the token is stored, the token itself is shown once
+    //? Signing in with the old password is rejected until the token is used
+    //? Admins can be reset too as long as the manager holds their permissions
+    #[tracing::instrument(skip(connection))]
+    pub async fn issue_password_reset<T>(
+        parameters: IssuePasswordResetParameters,
+        connection: &T,
+    ) -> Result<PasswordReset, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderator =
+            Self::managed(parameters.manager_id, parameters.moderator_id, connection).await?;
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let expires_at = Utc::now().naive_local() + Duration::seconds(parameters.ttl_seconds);
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hashed_token = Argon2::default()
+            .hash_password(token.as_bytes(), &salt)?
+            .to_string();
+
+        let mut moderator_to_be_reset: AdminActiveModel = moderator.into();
+        moderator_to_be_reset.password_reset_token = Set(Some(hashed_token));
+        moderator_to_be_reset.password_reset_expires_at = Set(Some(expires_at));
+        moderator_to_be_reset.update(connection).await?;
+
+        //? Whoever knows the old password is logged out as well
+        SessionService::revoke_all(Subject::Staff(parameters.moderator_id), connection).await?;
+
+        Ok(PasswordReset { token, expires_at })
+    }
+
+    //? Activity is written at most once per resolution to spare the database
+    #[tracing::instrument(skip(staff, connection), fields(staff_id = staff.id))]
+    pub async fn touch<T>(staff: &AdminModel, connection: &T) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let now = Utc::now().naive_local();
+
+        if staff.last_activity_at.is_some_and(|last_activity_at| {
+            now - last_activity_at < Duration::seconds(ACTIVITY_RESOLUTION_SECONDS)
+        }) {
+            return Ok(());
+        }
+
+        AdminEntity::update_many()
+            .col_expr(AdminColumn::LastActivityAt, Expr::value(now))
+            .filter(AdminColumn::Id.eq(staff.id))
+            .exec(connection)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn set_assignment<T>(
+        parameters: SetAssignmentParameters,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderator = Self::order_handler(parameters.moderator_id, connection).await?;
+
+        let mut moderator_to_be_changed: AdminActiveModel = moderator.into();
+        moderator_to_be_changed.capacity = Set(parameters.capacity);
+        moderator_to_be_changed.on_shift = Set(parameters.on_shift);
+        Ok(moderator_to_be_changed.update(connection).await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn set_on_shift<T>(
+        moderator_id: i64,
+        on_shift: bool,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderator = Self::order_handler(moderator_id, connection).await?;
+
+        let mut moderator_to_be_changed: AdminActiveModel = moderator.into();
+        moderator_to_be_changed.on_shift = Set(on_shift);
+        Ok(moderator_to_be_changed.update(connection).await?)
+    }
+
     #[tracing::instrument(skip(connection))]
     pub async fn assign_moderator<T>(
         parameters: AssignModeratorParameters,
         connection: &T,
-    ) -> Result<(), ServiceError>
+    ) -> Result<OrderModel, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        let moderator = match AdminEntity::find_by_id(parameters.moderator_id)
-            .one(connection)
-            .await?
-        {
-            Some(moderator) => match moderator.role {
-                Role::Admin => Err(ServiceError::ModeratorIsAdmin),
-                Role::Moderator => Ok(moderator),
-            },
-            None => Err(ServiceError::AdminNotFound),
-        }?;
+        let moderator = Self::order_handler(parameters.moderator_id, connection).await?;
 
         let order = match OrderEntity::find_by_id(parameters.order_id)
+            .lock_exclusive()
             .one(connection)
             .await?
         {
@@ -195,19 +498,19 @@ impl Service {
         let mut order_to_be_updated: OrderActiveModel = order.into();
 
         order_to_be_updated.moderator_id = Set(Some(moderator.id));
-        order_to_be_updated.update(connection).await?;
-        Ok(())
+        Ok(order_to_be_updated.update(connection).await?)
     }
 
     #[tracing::instrument(skip(connection))]
     pub async fn unassign_moderator<T>(
         parameters: UnassignModeratorParameters,
         connection: &T,
-    ) -> Result<(), ServiceError>
+    ) -> Result<OrderModel, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
         let order = match OrderEntity::find_by_id(parameters.order_id)
+            .lock_exclusive()
             .one(connection)
             .await?
         {
@@ -229,53 +532,111 @@ impl Service {
         let mut order_to_be_updated: OrderActiveModel = order.into();
 
         order_to_be_updated.moderator_id = Set(None);
-        order_to_be_updated.update(connection).await?;
-        Ok(())
+        Ok(order_to_be_updated.update(connection).await?)
     }
 
+    //? Everyone who may handle orders whatever their role is
     pub async fn moderators<T>(connection: &T) -> Result<Vec<AdminModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
         Ok(AdminEntity::find()
-            .filter(AdminColumn::Role.eq(Role::Moderator))
+            .filter(PermissionsService::held(Permission::HandleOrders))
             .all(connection)
             .await?)
     }
 
+    //? Staff can only be managed by someone holding every permission they hold,
+    //? otherwise resetting a password would hand those permissions over
+    async fn managed<T>(
+        manager_id: i64,
+        staff_id: i64,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let manager = AdminEntity::find_by_id(manager_id)
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::AdminNotFound)?;
+        let staff = AdminEntity::find_by_id(staff_id)
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::AdminNotFound)?;
+
+        let held = PermissionsService::effective(&manager, connection).await?;
+        match PermissionsService::effective(&staff, connection)
+            .await?
+            .iter()
+            .all(|permission| held.contains(permission))
+        {
+            true => Ok(staff),
+            false => Err(ServiceError::StaffOutranksManager),
+        }
+    }
+
+    //? Staff who get orders and shifts must hold the permission to handle them
+    //? and must not be disabled
+    async fn order_handler<T>(staff_id: i64, connection: &T) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let staff = AdminEntity::find_by_id(staff_id)
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::AdminNotFound)?;
+
+        if staff.disabled_at.is_some() {
+            return Err(ServiceError::StaffDisabled);
+        }
+
+        match PermissionsService::holds(&staff, Permission::HandleOrders, connection).await? {
+            true => Ok(staff),
+            false => Err(ServiceError::CanNotHandleOrders),
+        }
+    }
+
     pub async fn moderators_orders<T>(
         moderator_id: i64,
+        parameters: ListOrdersParameters,
         connection: &T,
-    ) -> Result<Vec<OrderModel>, ServiceError>
+    ) -> Result<Page<OrderModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        let moderator = match AdminEntity::find_by_id(moderator_id)
+        let moderator = AdminEntity::find_by_id(moderator_id)
             .one(connection)
             .await?
-        {
-            Some(moderator_or_admin) => match moderator_or_admin.role {
-                Role::Admin => Err(ServiceError::ModeratorIsAdmin),
-                Role::Moderator => Ok(moderator_or_admin),
-            },
-            None => Err(ServiceError::AdminNotFound),
-        }?;
+            .ok_or(ServiceError::AdminNotFound)?;
 
-        Ok(moderator
-            .find_related(OrderEntity)
-            .order_by_desc(OrderColumn::CreatedAt)
-            .all(connection)
-            .await?)
+        Ok(PaginationService::paginate(
+            parameters.filter.apply(moderator.find_related(OrderEntity)),
+            OrderColumn::Id,
+            parameters.sort.sort(),
+            &parameters.page,
+            connection,
+        )
+        .await?)
     }
 
-    pub async fn unassigned_orders<T>(connection: &T) -> Result<Vec<OrderModel>, ServiceError>
+    #[tracing::instrument(skip(connection))]
+    pub async fn unassigned_orders<T>(
+        parameters: ListOrdersParameters,
+        connection: &T,
+    ) -> Result<Page<OrderModel>, ServiceError>
     where
         T: TransactionTrait + ConnectionTrait,
     {
-        Ok(OrderEntity::find()
-            .filter(OrderColumn::ModeratorId.is_null())
-            .order_by_desc(OrderColumn::CreatedAt)
-            .all(connection)
-            .await?)
+        Ok(PaginationService::paginate(
+            parameters
+                .filter
+                .apply(OrderEntity::find().filter(OrderColumn::ModeratorId.is_null())),
+            OrderColumn::Id,
+            parameters.sort.sort(),
+            &parameters.page,
+            connection,
+        )
+        .await?)
     }
 }
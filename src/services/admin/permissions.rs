@@ -0,0 +1,297 @@
+use entity::{
+    admin::{Column as AdminColumn, Entity as AdminEntity, Model as AdminModel},
+    admin_permission::{
+        ActiveModel as AdminPermissionActiveModel, Column as AdminPermissionColumn,
+        Entity as AdminPermissionEntity,
+    },
+    role_permission::{
+        ActiveModel as RolePermissionActiveModel, Column as RolePermissionColumn,
+        Entity as RolePermissionEntity,
+    },
+    sea_orm_active_enums::{Permission, Role},
+};
+use migration::Query;
+use sea_orm::{prelude::*, Condition, Iterable, QuerySelect, Set, TransactionTrait};
+
+use crate::errors::AppError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error("Admin or moderator was not found")]
+    AdminNotFound,
+    #[error("At least one staff account must be able to manage staff")]
+    LastStaffManager,
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::AdminNotFound => AppError::AdminNotFound,
+            ServiceError::LastStaffManager => AppError::LastStaffManager,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StaffPermissions {
+    //? Granted to the account itself
+    pub granted: Vec<Permission>,
+    //? Granted to the account or to its role
+    pub effective: Vec<Permission>,
+}
+
+//? Keeps the declaration order of permissions whatever the query order is
+fn ordered(permissions: Vec<Permission>) -> Vec<Permission> {
+    Permission::iter()
+        .filter(|permission| permissions.contains(permission))
+        .collect()
+}
+
+pub struct Service;
+
+impl Service {
+    //? Matches staff holding the permission through their role or individually
+    pub fn held(permission: Permission) -> Condition {
+        Condition::any()
+            .add(
+                AdminColumn::Role.in_subquery(
+                    Query::select()
+                        .column(RolePermissionColumn::Role)
+                        .from(RolePermissionEntity)
+                        .and_where(RolePermissionColumn::Permission.eq(permission.clone()))
+                        .to_owned(),
+                ),
+            )
+            .add(
+                AdminColumn::Id.in_subquery(
+                    Query::select()
+                        .column(AdminPermissionColumn::AdminId)
+                        .from(AdminPermissionEntity)
+                        .and_where(AdminPermissionColumn::Permission.eq(permission))
+                        .to_owned(),
+                ),
+            )
+    }
+
+    #[tracing::instrument(skip(staff, connection), fields(staff_id = staff.id))]
+    pub async fn holds<T>(
+        staff: &AdminModel,
+        permission: Permission,
+        connection: &T,
+    ) -> Result<bool, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if RolePermissionEntity::find_by_id((staff.role.clone(), permission.clone()))
+            .one(connection)
+            .await?
+            .is_some()
+        {
+            return Ok(true);
+        }
+
+        Ok(AdminPermissionEntity::find_by_id((staff.id, permission))
+            .one(connection)
+            .await?
+            .is_some())
+    }
+
+    #[tracing::instrument(skip(staff, connection), fields(staff_id = staff.id))]
+    pub async fn effective<T>(
+        staff: &AdminModel,
+        connection: &T,
+    ) -> Result<Vec<Permission>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let mut permissions = Self::role_permissions(staff.role.clone(), connection).await?;
+        permissions.extend(Self::granted(staff.id, connection).await?);
+        Ok(ordered(permissions))
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn role_permissions<T>(
+        role: Role,
+        connection: &T,
+    ) -> Result<Vec<Permission>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(ordered(
+            RolePermissionEntity::find()
+                .filter(RolePermissionColumn::Role.eq(role))
+                .select_only()
+                .column(RolePermissionColumn::Permission)
+                .into_tuple::<Permission>()
+                .all(connection)
+                .await?,
+        ))
+    }
+
+    async fn granted<T>(admin_id: i64, connection: &T) -> Result<Vec<Permission>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(ordered(
+            AdminPermissionEntity::find()
+                .filter(AdminPermissionColumn::AdminId.eq(admin_id))
+                .select_only()
+                .column(AdminPermissionColumn::Permission)
+                .into_tuple::<Permission>()
+                .all(connection)
+                .await?,
+        ))
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn staff_permissions<T>(
+        admin_id: i64,
+        connection: &T,
+    ) -> Result<StaffPermissions, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let staff = AdminEntity::find_by_id(admin_id)
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::AdminNotFound)?;
+
+        Ok(StaffPermissions {
+            granted: Self::granted(staff.id, connection).await?,
+            effective: Self::effective(&staff, connection).await?,
+        })
+    }
+
+    //? Granting twice is not an error
+    #[tracing::instrument(skip(connection))]
+    pub async fn grant_to_role<T>(
+        role: Role,
+        permission: Permission,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if RolePermissionEntity::find_by_id((role.clone(), permission.clone()))
+            .one(connection)
+            .await?
+            .is_none()
+        {
+            let grant_to_be_inserted = RolePermissionActiveModel {
+                role: Set(role),
+                permission: Set(permission),
+            };
+            RolePermissionEntity::insert(grant_to_be_inserted)
+                .exec_without_returning(connection)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn revoke_from_role<T>(
+        role: Role,
+        permission: Permission,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        RolePermissionEntity::delete_by_id((role, permission.clone()))
+            .exec(connection)
+            .await?;
+        Self::ensure_staff_manager(permission, connection).await
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn grant_to_staff<T>(
+        admin_id: i64,
+        permission: Permission,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if AdminEntity::find_by_id(admin_id)
+            .one(connection)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::AdminNotFound);
+        }
+
+        if AdminPermissionEntity::find_by_id((admin_id, permission.clone()))
+            .one(connection)
+            .await?
+            .is_none()
+        {
+            let grant_to_be_inserted = AdminPermissionActiveModel {
+                admin_id: Set(admin_id),
+                permission: Set(permission),
+            };
+            AdminPermissionEntity::insert(grant_to_be_inserted)
+                .exec_without_returning(connection)
+                .await?;
+        }
+        Ok(())
+    }
+
+    //? Only individual grants are revoked, role grants stay in place
+    #[tracing::instrument(skip(connection))]
+    pub async fn revoke_from_staff<T>(
+        admin_id: i64,
+        permission: Permission,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if AdminEntity::find_by_id(admin_id)
+            .one(connection)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::AdminNotFound);
+        }
+
+        AdminPermissionEntity::delete_by_id((admin_id, permission.clone()))
+            .exec(connection)
+            .await?;
+        Self::ensure_staff_manager(permission, connection).await
+    }
+
+    //? Nobody could grant anything back once the last staff manager is gone
+    //? Callers roll the transaction back on error
+    async fn ensure_staff_manager<T>(
+        revoked: Permission,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if revoked != Permission::ManageStaff {
+            return Ok(());
+        }
+
+        match Self::staff_managers(connection).await? {
+            0 => Err(ServiceError::LastStaffManager),
+            _ => Ok(()),
+        }
+    }
+
+    //? Enabled accounts which can manage staff, the instance is unusable without one
+    #[tracing::instrument(skip(connection))]
+    pub async fn staff_managers<T>(connection: &T) -> Result<u64, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(AdminEntity::find()
+            .filter(Self::held(Permission::ManageStaff))
+            .filter(AdminColumn::DisabledAt.is_null())
+            .count(connection)
+            .await?)
+    }
+}
@@ -0,0 +1,27 @@
+use axum::async_trait;
+
+use super::{AssignmentStrategy, Candidate};
+
+//? Ties are broken by id so the choice is stable
+#[derive(Debug)]
+pub struct LeastOpenOrders;
+
+impl LeastOpenOrders {
+    pub fn choose(candidates: &[Candidate]) -> Option<i64> {
+        candidates
+            .iter()
+            .min_by_key(|candidate| (candidate.open_orders, candidate.moderator_id))
+            .map(|candidate| candidate.moderator_id)
+    }
+}
+
+#[async_trait]
+impl AssignmentStrategy for LeastOpenOrders {
+    fn name(&self) -> &str {
+        "least_open_orders"
+    }
+
+    async fn pick(&self, candidates: Vec<Candidate>) -> Option<i64> {
+        Self::choose(&candidates)
+    }
+}
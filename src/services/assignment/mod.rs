@@ -0,0 +1,154 @@
+use axum::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use entity::{
+    admin::{ActiveModel as AdminActiveModel, Column as AdminColumn, Entity as AdminEntity},
+    order::{Column as OrderColumn, Entity as OrderEntity},
+    sea_orm_active_enums::{Permission, Status},
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect, Select, Set, TransactionTrait,
+};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{
+    config::{AssignmentStrategyKind, Configuration},
+    services::admin::permissions::Service as PermissionsService,
+};
+
+pub mod least_open_orders;
+pub mod online_only;
+pub mod round_robin;
+
+pub use least_open_orders::LeastOpenOrders;
+pub use online_only::OnlineOnly;
+pub use round_robin::RoundRobin;
+
+//? Staff member who handles orders, is on shift and still has free capacity
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub moderator_id: i64,
+    pub open_orders: i64,
+    pub last_assigned_at: Option<NaiveDateTime>,
+}
+
+#[async_trait]
+pub trait AssignmentStrategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    //? None leaves the order unassigned so admins can assign it manually
+    //? Picked id must be one of the candidates, unavailable ones are removed and picked again
+    async fn pick(&self, candidates: Vec<Candidate>) -> Option<i64>;
+}
+
+impl Debug for dyn AssignmentStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+//? Key is prefixed so it never collides with user status keys
+pub fn presence_key(moderator_id: i64) -> String {
+    format!("moderator:{}", moderator_id)
+}
+
+pub fn strategy(
+    configuration: &Configuration,
+    redis_client: &redis::Client,
+) -> Arc<dyn AssignmentStrategy> {
+    match configuration.assignment_strategy() {
+        AssignmentStrategyKind::LeastOpenOrders => Arc::new(LeastOpenOrders),
+        AssignmentStrategyKind::RoundRobin => Arc::new(RoundRobin),
+        AssignmentStrategyKind::OnlineOnly => Arc::new(OnlineOnly::new(redis_client.clone())),
+    }
+}
+
+pub struct Service;
+
+impl Service {
+    //? Admins hold the permission too, they are off shift unless they start one
+    fn eligible() -> Select<AdminEntity> {
+        AdminEntity::find()
+            .filter(PermissionsService::held(Permission::HandleOrders))
+            .filter(AdminColumn::OnShift.eq(true))
+            .filter(AdminColumn::DisabledAt.is_null())
+    }
+
+    //? Read without locks, the picked one is checked again by `claim`
+    #[tracing::instrument(skip(connection))]
+    pub async fn candidates<T>(connection: &T) -> Result<Vec<Candidate>, sea_orm::DbErr>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderators = Self::eligible().all(connection).await?;
+
+        let open_orders: HashMap<i64, i64> = OrderEntity::find()
+            .select_only()
+            .column(OrderColumn::ModeratorId)
+            .column_as(OrderColumn::Id.count(), "open_orders")
+            .filter(OrderColumn::ModeratorId.is_not_null())
+            .filter(OrderColumn::Status.is_in([Status::Created, Status::Maybepayed]))
+            .group_by(OrderColumn::ModeratorId)
+            .into_tuple::<(i64, i64)>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(moderators
+            .into_iter()
+            .map(|moderator| {
+                let open_orders = open_orders.get(&moderator.id).copied().unwrap_or(0);
+                (moderator, open_orders)
+            })
+            .filter(|(moderator, open_orders)| {
+                moderator
+                    .capacity
+                    .is_none_or(|capacity| *open_orders < capacity as i64)
+            })
+            .map(|(moderator, open_orders)| Candidate {
+                moderator_id: moderator.id,
+                open_orders,
+                last_assigned_at: moderator.last_assigned_at,
+            })
+            .collect())
+    }
+
+    //? Locks only the picked moderator and checks again that they may take one more order
+    //? The lock is held until the order is committed so concurrent orders count it
+    //? Returns false when the moderator became unavailable meanwhile
+    #[tracing::instrument(skip(connection))]
+    pub async fn claim<T>(moderator_id: i64, connection: &T) -> Result<bool, sea_orm::DbErr>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let moderator = match Self::eligible()
+            .filter(AdminColumn::Id.eq(moderator_id))
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(moderator) => moderator,
+            None => return Ok(false),
+        };
+
+        let open_orders = OrderEntity::find()
+            .filter(OrderColumn::ModeratorId.eq(moderator_id))
+            .filter(OrderColumn::Status.is_in([Status::Created, Status::Maybepayed]))
+            .count(connection)
+            .await?;
+
+        if moderator
+            .capacity
+            .is_some_and(|capacity| open_orders >= capacity as u64)
+        {
+            return Ok(false);
+        }
+
+        let mut moderator_to_be_updated: AdminActiveModel = moderator.into();
+        moderator_to_be_updated.last_assigned_at = Set(Some(Utc::now().naive_local()));
+        moderator_to_be_updated.update(connection).await?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,61 @@
+use axum::async_trait;
+use redis::AsyncCommands;
+
+use super::{presence_key, AssignmentStrategy, Candidate, LeastOpenOrders};
+
+//? Only moderators who have refreshed their status recently are picked
+//? Among them the one with the fewest open orders wins
+pub struct OnlineOnly {
+    redis_client: redis::Client,
+}
+
+impl OnlineOnly {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    async fn online(&self, candidates: &[Candidate]) -> redis::RedisResult<Vec<bool>> {
+        let keys: Vec<_> = candidates
+            .iter()
+            .map(|candidate| presence_key(candidate.moderator_id))
+            .collect();
+
+        let mut connection = self.redis_client.get_async_connection().await?;
+        let statuses: Vec<Option<bool>> = connection.mget(keys).await?;
+
+        Ok(statuses
+            .into_iter()
+            .map(|status| status.unwrap_or(false))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AssignmentStrategy for OnlineOnly {
+    fn name(&self) -> &str {
+        "online_only"
+    }
+
+    async fn pick(&self, candidates: Vec<Candidate>) -> Option<i64> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let online = match self.online(&candidates).await {
+            Ok(online) => online,
+            Err(cause) => {
+                //? Presence is unknown so we do not leave orders unassigned
+                tracing::warn!(%cause, "Failed to read moderators presence!");
+                return LeastOpenOrders::choose(&candidates);
+            }
+        };
+
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .zip(online)
+            .filter_map(|(candidate, online)| online.then_some(candidate))
+            .collect();
+
+        LeastOpenOrders::choose(&candidates)
+    }
+}
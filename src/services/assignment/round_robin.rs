@@ -0,0 +1,22 @@
+use axum::async_trait;
+
+use super::{AssignmentStrategy, Candidate};
+
+//? Moderator who has waited the longest gets the order
+//? Those who have never been assigned go first
+#[derive(Debug)]
+pub struct RoundRobin;
+
+#[async_trait]
+impl AssignmentStrategy for RoundRobin {
+    fn name(&self) -> &str {
+        "round_robin"
+    }
+
+    async fn pick(&self, candidates: Vec<Candidate>) -> Option<i64> {
+        candidates
+            .iter()
+            .min_by_key(|candidate| (candidate.last_assigned_at, candidate.moderator_id))
+            .map(|candidate| candidate.moderator_id)
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashMap, path::Path};
+
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+
+use crate::config::{Configuration, JwtAlgorithmKind};
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeysError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    JWTError(#[from] jsonwebtoken::errors::Error),
+    #[error("{0} has to be set for asymmetric jwt algorithm")]
+    MissingKeyPath(&'static str),
+    #[error("Retired key {0} is not kid:algorithm:value")]
+    BadRetiredKey(String),
+    #[error("Key id {0} is used more than once")]
+    DuplicateKeyId(String),
+}
+
+impl From<JwtAlgorithmKind> for Algorithm {
+    fn from(value: JwtAlgorithmKind) -> Self {
+        match value {
+            JwtAlgorithmKind::Hs256 => Algorithm::HS256,
+            JwtAlgorithmKind::Rs256 => Algorithm::RS256,
+            JwtAlgorithmKind::Eddsa => Algorithm::EdDSA,
+        }
+    }
+}
+
+//? Public half of an asymmetric key, hs256 secrets are never published
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+    pub kid: String,
+    pub alg: Algorithm,
+    pub pem: String,
+}
+
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+//? One key signs, every key verifies
+//? Rotation is done by moving the signing key to jwt_retired_keys
+//? and keeping it there until tokens signed with it expire
+#[derive(Clone)]
+pub struct Keys {
+    kid: String,
+    algorithm: Algorithm,
+    signing: EncodingKey,
+    verifying: HashMap<String, VerificationKey>,
+    published: Vec<PublicKey>,
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, KeysError> {
+    Ok(std::fs::read(path)?)
+}
+
+impl Keys {
+    pub fn from_configuration(configuration: &Configuration) -> Result<Self, KeysError> {
+        let algorithm = configuration.jwt_algorithm();
+        let kid = configuration.jwt_key_id().to_owned();
+
+        let signing = match algorithm {
+            JwtAlgorithmKind::Hs256 => {
+                EncodingKey::from_secret(configuration.jwt_secret().as_bytes())
+            }
+            JwtAlgorithmKind::Rs256 | JwtAlgorithmKind::Eddsa => {
+                let private_pem = read(
+                    configuration
+                        .jwt_private_key_path()
+                        .ok_or(KeysError::MissingKeyPath("JWT_PRIVATE_KEY_PATH"))?,
+                )?;
+                match algorithm {
+                    JwtAlgorithmKind::Rs256 => EncodingKey::from_rsa_pem(&private_pem)?,
+                    _ => EncodingKey::from_ed_pem(&private_pem)?,
+                }
+            }
+        };
+
+        let mut keys = Self {
+            kid: kid.clone(),
+            algorithm: algorithm.into(),
+            signing,
+            verifying: HashMap::new(),
+            published: vec![],
+        };
+
+        let active_value = match algorithm {
+            JwtAlgorithmKind::Hs256 => configuration.jwt_secret().to_owned(),
+            _ => configuration
+                .jwt_public_key_path()
+                .ok_or(KeysError::MissingKeyPath("JWT_PUBLIC_KEY_PATH"))?
+                .to_string_lossy()
+                .into_owned(),
+        };
+        keys.accept(kid, algorithm, &active_value)?;
+
+        for retired in configuration.jwt_retired_keys() {
+            let mut parts = retired.splitn(3, ':');
+            let (kid, algorithm, value) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kid), Some(algorithm), Some(value)) if !kid.is_empty() => {
+                    let algorithm = match algorithm {
+                        "hs256" => JwtAlgorithmKind::Hs256,
+                        "rs256" => JwtAlgorithmKind::Rs256,
+                        "eddsa" => JwtAlgorithmKind::Eddsa,
+                        _ => return Err(KeysError::BadRetiredKey(kid.to_owned())),
+                    };
+                    (kid, algorithm, value)
+                }
+                _ => {
+                    let kid = retired.split(':').next().unwrap_or_default();
+                    return Err(KeysError::BadRetiredKey(kid.to_owned()));
+                }
+            };
+            keys.accept(kid.to_owned(), algorithm, value)?;
+        }
+
+        Ok(keys)
+    }
+
+    //? Value is the secret itself for hs256 and a path to public PEM otherwise
+    fn accept(
+        &mut self,
+        kid: String,
+        algorithm: JwtAlgorithmKind,
+        value: &str,
+    ) -> Result<(), KeysError> {
+        if self.verifying.contains_key(&kid) {
+            return Err(KeysError::DuplicateKeyId(kid));
+        }
+
+        let key = match algorithm {
+            JwtAlgorithmKind::Hs256 => DecodingKey::from_secret(value.as_bytes()),
+            JwtAlgorithmKind::Rs256 | JwtAlgorithmKind::Eddsa => {
+                let public_pem = read(Path::new(value))?;
+                let key = match algorithm {
+                    JwtAlgorithmKind::Rs256 => DecodingKey::from_rsa_pem(&public_pem)?,
+                    _ => DecodingKey::from_ed_pem(&public_pem)?,
+                };
+                self.published.push(PublicKey {
+                    kid: kid.clone(),
+                    alg: algorithm.into(),
+                    pem: String::from_utf8_lossy(&public_pem).into_owned(),
+                });
+                key
+            }
+        };
+
+        self.verifying.insert(
+            kid,
+            VerificationKey {
+                algorithm: algorithm.into(),
+                key,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn signing(&self) -> &EncodingKey {
+        &self.signing
+    }
+
+    pub fn verifying(&self, kid: &str) -> Option<(Algorithm, &DecodingKey)> {
+        self.verifying
+            .get(kid)
+            .map(|verification| (verification.algorithm, &verification.key))
+    }
+
+    pub fn published(&self) -> &[PublicKey] {
+        &self.published
+    }
+}
@@ -1,16 +1,20 @@
-use crate::errors::AppError;
+use crate::{errors::AppError, services::sessions::Audience};
 use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono::{Duration, Utc};
 use entity::admin::{
     ActiveModel as AdminActiveModel, Column as AdminColumn, Entity as AdminEntity,
     Model as AdminModel,
 };
-use jsonwebtoken::{DecodingKey, Validation};
+use jsonwebtoken::{Header, Validation};
+use keys::Keys;
 use rand_core::OsRng;
 use sea_orm::{prelude::*, ColumnTrait, ConnectionTrait, Set, TransactionTrait};
 use std::fmt::Debug;
 use thiserror::Error;
 
+pub mod keys;
+pub mod two_factor;
+
 pub type Jwt = String;
 
 #[derive(Error, Debug)]
@@ -23,6 +27,14 @@ pub enum ServiceError {
     DbErr(#[from] sea_orm::DbErr),
     #[error(transparent)]
     PasswordHashError(#[from] argon2::password_hash::Error),
+    #[error("Account is disabled")]
+    Disabled,
+    #[error("Password has to be reset")]
+    PasswordResetRequired,
+    #[error("Password reset token has expired")]
+    ResetTokenExpired,
+    #[error("Token was signed with an unknown key")]
+    UnknownKey,
 }
 
 impl From<ServiceError> for AppError {
@@ -34,6 +46,10 @@ impl From<ServiceError> for AppError {
             ServiceError::PasswordHashError(cause) => {
                 AppError::InternalServerError(Box::new(cause))
             }
+            ServiceError::Disabled => AppError::StaffDisabled,
+            ServiceError::PasswordResetRequired => AppError::PasswordResetRequired,
+            ServiceError::ResetTokenExpired => AppError::ResetTokenExpired,
+            ServiceError::UnknownKey => AppError::JwtError(Box::new(value)),
         }
     }
 }
@@ -41,6 +57,11 @@ impl From<ServiceError> for AppError {
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct TokenClaims {
     pub sub: i64,
+    //? Session the token was issued for, checked on every request
+    pub sid: i64,
+    pub jti: String,
+    //? User tokens are never accepted where staff tokens are and vice versa
+    pub aud: Audience,
     pub iat: u64,
     pub exp: u64,
 }
@@ -49,7 +70,8 @@ pub struct Service;
 
 pub struct JwtCheckParams<'a> {
     pub token: Jwt,
-    pub secret: &'a str,
+    pub audience: Audience,
+    pub keys: &'a Keys,
 }
 
 pub struct AdminCredentials {
@@ -68,12 +90,14 @@ impl Debug for AdminCredentials {
 
 pub struct GenerateUserJwtParameters<'a> {
     pub steam_id: i64,
-    pub secret: &'a str,
+    pub session_id: i64,
+    pub keys: &'a Keys,
     pub ttl: i64,
 }
 pub struct GenerateAdminJwtParameters<'a> {
     pub admin_id: i64,
-    pub secret: &'a str,
+    pub session_id: i64,
+    pub keys: &'a Keys,
     pub ttl: i64,
 }
 
@@ -91,48 +115,92 @@ impl Debug for ResetPasswordParameters<'_> {
     }
 }
 
+pub struct RedeemPasswordResetParameters {
+    pub login: String,
+    pub token: String,
+    pub new_password: String,
+}
+
+impl Debug for RedeemPasswordResetParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedeemPasswordResetParameters")
+            .field("login", &self.login)
+            .finish()
+    }
+}
+
+fn verify(secret: &str, hash: &str) -> Result<(), ServiceError> {
+    match PasswordHash::new(hash) {
+        Ok(parsed_hash) => Argon2::default()
+            .verify_password(secret.as_bytes(), &parsed_hash)
+            .map_err(|_| ServiceError::Unauthorized),
+        Err(_) => Err(ServiceError::Unauthorized),
+    }
+}
+
 impl Service {
+    //? Key is picked by kid so tokens signed before rotation stay valid
     #[tracing::instrument(skip(jwt_params))]
     pub fn check(jwt_params: JwtCheckParams<'_>) -> Result<TokenClaims, ServiceError> {
-        let validation = Validation::default();
+        let header = jsonwebtoken::decode_header(&jwt_params.token)?;
+
+        let (algorithm, key) = header
+            .kid
+            .as_deref()
+            .and_then(|kid| jwt_params.keys.verifying(kid))
+            .ok_or(ServiceError::UnknownKey)?;
 
-        let decoded = jsonwebtoken::decode::<TokenClaims>(
-            &jwt_params.token,
-            &DecodingKey::from_secret(jwt_params.secret.as_bytes()),
-            &validation,
-        )?;
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&[jwt_params.audience.as_str()]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+
+        let decoded = jsonwebtoken::decode::<TokenClaims>(&jwt_params.token, key, &validation)?;
 
         Ok(decoded.claims)
     }
 
-    #[tracing::instrument(skip(parameters))]
-    pub fn user_jwt(parameters: GenerateUserJwtParameters<'_>) -> Result<Jwt, ServiceError> {
+    fn sign(
+        subject: i64,
+        session_id: i64,
+        audience: Audience,
+        keys: &Keys,
+        ttl: i64,
+    ) -> Result<Jwt, ServiceError> {
         let claims: TokenClaims = TokenClaims {
-            sub: parameters.steam_id,
-            exp: (Utc::now() + Duration::minutes(parameters.ttl)).timestamp() as u64,
+            sub: subject,
+            sid: session_id,
+            jti: uuid::Uuid::new_v4().to_string(),
+            aud: audience,
+            exp: (Utc::now() + Duration::minutes(ttl)).timestamp() as u64,
             iat: Utc::now().timestamp() as u64,
         };
 
-        Ok(jsonwebtoken::encode(
-            &jsonwebtoken::Header::default(),
-            &claims,
-            &jsonwebtoken::EncodingKey::from_secret(parameters.secret.as_bytes()),
-        )?)
+        let mut header = Header::new(keys.algorithm());
+        header.kid = Some(keys.kid().to_owned());
+
+        Ok(jsonwebtoken::encode(&header, &claims, keys.signing())?)
     }
 
     #[tracing::instrument(skip(parameters))]
-    pub fn admin_jwt(parameters: GenerateAdminJwtParameters<'_>) -> Result<Jwt, ServiceError> {
-        let claims: TokenClaims = TokenClaims {
-            sub: parameters.admin_id,
-            exp: (Utc::now() + Duration::minutes(parameters.ttl)).timestamp() as u64,
-            iat: Utc::now().timestamp() as u64,
-        };
+    pub fn user_jwt(parameters: GenerateUserJwtParameters<'_>) -> Result<Jwt, ServiceError> {
+        Self::sign(
+            parameters.steam_id,
+            parameters.session_id,
+            Audience::User,
+            parameters.keys,
+            parameters.ttl,
+        )
+    }
 
-        Ok(jsonwebtoken::encode(
-            &jsonwebtoken::Header::default(),
-            &claims,
-            &jsonwebtoken::EncodingKey::from_secret(parameters.secret.as_bytes()),
-        )?)
+    #[tracing::instrument(skip(parameters))]
+    pub fn admin_jwt(parameters: GenerateAdminJwtParameters<'_>) -> Result<Jwt, ServiceError> {
+        Self::sign(
+            parameters.admin_id,
+            parameters.session_id,
+            Audience::Staff,
+            parameters.keys,
+            parameters.ttl,
+        )
     }
 
     #[tracing::instrument(skip(connection))]
@@ -152,16 +220,31 @@ impl Service {
             None => Err(ServiceError::Unauthorized),
         }?;
 
-        match PasswordHash::new(&admin.password) {
-            Ok(parsed_hash) => match Argon2::default()
-                .verify_password(credentials.password.as_bytes(), &parsed_hash)
-            {
-                Ok(()) => Ok(admin),
-                Err(_) => Err(ServiceError::Unauthorized),
-            },
+        verify(&credentials.password, &admin.password)?;
 
-            Err(_) => Err(ServiceError::Unauthorized),
+        if admin.disabled_at.is_some() {
+            return Err(ServiceError::Disabled);
+        }
+
+        if admin.password_reset_token.is_some() {
+            return Err(ServiceError::PasswordResetRequired);
         }
+
+        Ok(admin)
+    }
+
+    //? Called once the session is opened, after the second step when 2fa is on
+    #[tracing::instrument(skip(admin, connection), fields(admin_id = admin.id))]
+    pub async fn record_login<T>(
+        admin: AdminModel,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let mut admin_to_be_updated: AdminActiveModel = admin.into();
+        admin_to_be_updated.last_login_at = Set(Some(Utc::now().naive_local()));
+        Ok(admin_to_be_updated.update(connection).await?)
     }
 
     #[tracing::instrument(skip(connection))]
@@ -205,4 +288,49 @@ impl Service {
             Err(_) => Err(ServiceError::Unauthorized),
         }
     }
+
+    //? Token is single use, it is cleared together with the expiration
+    #[tracing::instrument(skip(connection))]
+    pub async fn redeem_password_reset<T>(
+        parameters: RedeemPasswordResetParameters,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let admin = AdminEntity::find()
+            .filter(AdminColumn::Login.eq(&parameters.login))
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::Unauthorized)?;
+
+        let (hashed_token, expires_at) =
+            match (&admin.password_reset_token, admin.password_reset_expires_at) {
+                (Some(hashed_token), Some(expires_at)) => Ok((hashed_token, expires_at)),
+                _ => Err(ServiceError::Unauthorized),
+            }?;
+
+        verify(&parameters.token, hashed_token)?;
+
+        if admin.disabled_at.is_some() {
+            return Err(ServiceError::Disabled);
+        }
+
+        if expires_at < Utc::now().naive_local() {
+            return Err(ServiceError::ResetTokenExpired);
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let hashed_password = Argon2::default()
+            .hash_password(parameters.new_password.as_bytes(), &salt)?
+            .to_string();
+
+        let mut admin_to_be_updated: AdminActiveModel = admin.into();
+        admin_to_be_updated.password = Set(hashed_password);
+        admin_to_be_updated.password_reset_token = Set(None);
+        admin_to_be_updated.password_reset_expires_at = Set(None);
+        admin_to_be_updated.update(connection).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,419 @@
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use chrono::Utc;
+use entity::{
+    admin::{
+        ActiveModel as AdminActiveModel, Column as AdminColumn, Entity as AdminEntity,
+        Model as AdminModel,
+    },
+    staff_policy::{ActiveModel as StaffPolicyActiveModel, Entity as StaffPolicyEntity},
+};
+use rand_core::OsRng;
+use redis::AsyncCommands;
+use sea_orm::{prelude::*, QuerySelect, Set, TransactionTrait};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    errors::AppError,
+    services::sessions::{Service as SessionService, ServiceError as SessionServiceError, Subject},
+};
+
+const RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+const POLICY_ID: i64 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    PasswordHashError(#[from] argon2::password_hash::Error),
+    #[error(transparent)]
+    RedisError(#[from] redis::RedisError),
+    #[error(transparent)]
+    SessionError(#[from] SessionServiceError),
+    #[error("Stored totp secret is not valid base32")]
+    BadSecret,
+    #[error("Two-factor authentication is already enabled")]
+    AlreadyEnabled,
+    #[error("Two-factor authentication is not enrolled")]
+    NotEnrolled,
+    #[error("Bad two-factor code")]
+    BadCode,
+    #[error("Two-factor authentication is required for all staff")]
+    Required,
+    #[error("Login challenge has expired")]
+    ChallengeExpired,
+    #[error("Account is disabled")]
+    Disabled,
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::PasswordHashError(cause) => {
+                AppError::InternalServerError(Box::new(cause))
+            }
+            ServiceError::RedisError(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::SessionError(cause) => cause.into(),
+            ServiceError::BadSecret => AppError::InternalServerError(Box::new(value)),
+            ServiceError::AlreadyEnabled => AppError::TwoFactorAlreadyEnabled,
+            ServiceError::NotEnrolled => AppError::TwoFactorNotEnrolled,
+            ServiceError::BadCode => AppError::BadTwoFactorCode,
+            ServiceError::Required => AppError::TwoFactorRequired,
+            ServiceError::ChallengeExpired => AppError::LoginChallengeExpired,
+            ServiceError::Disabled => AppError::StaffDisabled,
+        }
+    }
+}
+
+//? Secret and recovery codes are shown once, only hashes of the codes are kept
+pub struct Enrolment {
+    pub secret: String,
+    pub provisioning_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+pub struct Service;
+
+fn totp(secret: &str, issuer: Option<&str>, login: &str) -> Result<TOTP, ServiceError> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(|_| ServiceError::BadSecret)?;
+
+    //? Unchecked because logins are not restricted to what totp-rs accepts as account name
+    //? Skew is zero because neighbouring steps are checked one by one in accepted_step
+    Ok(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        6,
+        0,
+        30,
+        secret,
+        issuer.map(ToOwned::to_owned),
+        login.to_owned(),
+    ))
+}
+
+//? Code of the previous, current or next time step is accepted once,
+//? steps up to the last accepted one are skipped so a seen code can not be replayed
+fn accepted_step(
+    secret: &str,
+    code: &str,
+    last_used_step: Option<i64>,
+) -> Result<Option<i64>, ServiceError> {
+    let totp = totp(secret, None, "")?;
+    let period = totp.step as i64;
+    let current_step = Utc::now().timestamp() / period;
+
+    Ok((current_step - 1..=current_step + 1)
+        .filter(|step| last_used_step.is_none_or(|last_used_step| *step > last_used_step))
+        .find(|step| totp.check(code, (step * period) as u64)))
+}
+
+//? Recovery codes are cut from a uuid, anything else is not worth hashing ten times
+fn is_recovery_code(code: &str) -> bool {
+    code.len() == RECOVERY_CODE_LENGTH && code.chars().all(|char| char.is_ascii_hexdigit())
+}
+
+fn challenge_key(token: &str) -> String {
+    format!("two-factor-challenge:{}", token)
+}
+
+impl Service {
+    //? Conditional so only one of two concurrent logins with the same code wins
+    async fn use_step<T>(staff_id: i64, step: i64, connection: &T) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let used = AdminEntity::update_many()
+            .col_expr(AdminColumn::TotpLastUsedStep, Expr::value(Some(step)))
+            .filter(AdminColumn::Id.eq(staff_id))
+            .filter(
+                AdminColumn::TotpLastUsedStep
+                    .is_null()
+                    .or(AdminColumn::TotpLastUsedStep.lt(step)),
+            )
+            .exec(connection)
+            .await?;
+
+        match used.rows_affected {
+            0 => Err(ServiceError::BadCode),
+            _ => Ok(()),
+        }
+    }
+
+    //? Account could have been disabled between the two login steps
+    #[tracing::instrument(skip(connection))]
+    pub async fn challenged<T>(staff_id: i64, connection: &T) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        match AdminEntity::find_by_id(staff_id).one(connection).await? {
+            Some(staff) if staff.disabled_at.is_some() => Err(ServiceError::Disabled),
+            Some(staff) => Ok(staff),
+            None => Err(ServiceError::ChallengeExpired),
+        }
+    }
+
+    //? Enrolment can be restarted until it is confirmed
+    #[tracing::instrument(skip(staff, connection), fields(staff_id = staff.id))]
+    pub async fn begin_enrolment<T>(
+        staff: AdminModel,
+        issuer: &str,
+        connection: &T,
+    ) -> Result<Enrolment, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if staff.totp_enabled_at.is_some() {
+            return Err(ServiceError::AlreadyEnabled);
+        }
+
+        let secret = match Secret::generate_secret().to_encoded() {
+            Secret::Encoded(secret) => secret,
+            Secret::Raw(_) => unreachable!(),
+        };
+        let provisioning_uri = totp(&secret, Some(issuer), &staff.login)?.get_url();
+
+        let recovery_codes = (0..RECOVERY_CODES)
+            .map(|_| uuid::Uuid::new_v4().simple().to_string()[..RECOVERY_CODE_LENGTH].to_owned())
+            .collect::<Vec<_>>();
+
+        let hashed_recovery_codes = recovery_codes
+            .iter()
+            .map(|code| {
+                let salt = SaltString::generate(&mut OsRng);
+                Argon2::default()
+                    .hash_password(code.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut staff_to_be_enrolled: AdminActiveModel = staff.into();
+        staff_to_be_enrolled.totp_secret = Set(Some(secret.clone()));
+        staff_to_be_enrolled.totp_recovery_codes = Set(hashed_recovery_codes);
+        staff_to_be_enrolled.update(connection).await?;
+
+        Ok(Enrolment {
+            secret,
+            provisioning_uri,
+            recovery_codes,
+        })
+    }
+
+    #[tracing::instrument(skip(staff, code, connection), fields(staff_id = staff.id))]
+    pub async fn confirm_enrolment<T>(
+        staff: AdminModel,
+        code: &str,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if staff.totp_enabled_at.is_some() {
+            return Err(ServiceError::AlreadyEnabled);
+        }
+
+        let secret = staff
+            .totp_secret
+            .as_deref()
+            .ok_or(ServiceError::NotEnrolled)?;
+
+        let step =
+            accepted_step(secret, code, staff.totp_last_used_step)?.ok_or(ServiceError::BadCode)?;
+        Self::use_step(staff.id, step, connection).await?;
+
+        let mut staff_to_be_enrolled: AdminActiveModel = staff.into();
+        staff_to_be_enrolled.totp_enabled_at = Set(Some(Utc::now().naive_local()));
+        Ok(staff_to_be_enrolled.update(connection).await?)
+    }
+
+    //? Recovery code is accepted instead of the current code and is burnt on use
+    #[tracing::instrument(skip(staff, code, connection), fields(staff_id = staff.id))]
+    pub async fn verify<T>(
+        staff: AdminModel,
+        code: &str,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let secret = match (&staff.totp_secret, staff.totp_enabled_at) {
+            (Some(secret), Some(_)) => Ok(secret),
+            _ => Err(ServiceError::NotEnrolled),
+        }?;
+
+        if let Some(step) = accepted_step(secret, code, staff.totp_last_used_step)? {
+            return Self::use_step(staff.id, step, connection).await;
+        }
+
+        if !is_recovery_code(code) {
+            return Err(ServiceError::BadCode);
+        }
+
+        //? Row is locked so two logins can not burn the same recovery code
+        let staff = AdminEntity::find_by_id(staff.id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::NotEnrolled)?;
+
+        let used = staff.totp_recovery_codes.iter().position(|hash| {
+            PasswordHash::new(hash)
+                .map(|parsed_hash| {
+                    Argon2::default()
+                        .verify_password(code.as_bytes(), &parsed_hash)
+                        .is_ok()
+                })
+                .unwrap_or(false)
+        });
+
+        match used {
+            Some(position) => {
+                let mut recovery_codes = staff.totp_recovery_codes.clone();
+                recovery_codes.remove(position);
+
+                let mut staff_to_be_updated: AdminActiveModel = staff.into();
+                staff_to_be_updated.totp_recovery_codes = Set(recovery_codes);
+                staff_to_be_updated.update(connection).await?;
+                Ok(())
+            }
+            None => Err(ServiceError::BadCode),
+        }
+    }
+
+    #[tracing::instrument(skip(staff, code, connection), fields(staff_id = staff.id))]
+    pub async fn disable<T>(
+        staff: AdminModel,
+        code: &str,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if Self::is_required(connection).await? {
+            return Err(ServiceError::Required);
+        }
+
+        Self::verify(staff.clone(), code, connection).await?;
+
+        let mut staff_to_be_updated: AdminActiveModel = staff.into();
+        staff_to_be_updated.totp_secret = Set(None);
+        staff_to_be_updated.totp_enabled_at = Set(None);
+        staff_to_be_updated.totp_recovery_codes = Set(vec![]);
+        staff_to_be_updated.update(connection).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn is_required<T>(connection: &T) -> Result<bool, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(StaffPolicyEntity::find_by_id(POLICY_ID)
+            .one(connection)
+            .await?
+            .map(|policy| policy.require_two_factor)
+            .unwrap_or(false))
+    }
+
+    //? Staff without 2fa are logged out when it becomes required
+    //? so they have to enrol on their next login
+    #[tracing::instrument(skip(connection))]
+    pub async fn set_required<T>(required: bool, connection: &T) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let policy = StaffPolicyActiveModel {
+            id: Set(POLICY_ID),
+            require_two_factor: Set(required),
+        };
+        policy.update(connection).await?;
+
+        if required {
+            let unenrolled = AdminEntity::find()
+                .filter(AdminColumn::TotpEnabledAt.is_null())
+                .all(connection)
+                .await?;
+
+            for staff in unenrolled {
+                SessionService::revoke_all(Subject::Staff(staff.id), connection).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //? Password was accepted, the session is opened only after the second step
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn open_challenge(
+        staff_id: i64,
+        ttl_seconds: u64,
+        redis_client: &redis::Client,
+    ) -> Result<String, ServiceError> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+
+        let mut connection = redis_client.get_async_connection().await?;
+        connection
+            .set_ex::<_, _, ()>(challenge_key(&token), staff_id, ttl_seconds)
+            .await?;
+
+        Ok(token)
+    }
+
+    #[tracing::instrument(skip(token, redis_client))]
+    pub async fn peek_challenge(
+        token: &str,
+        redis_client: &redis::Client,
+    ) -> Result<i64, ServiceError> {
+        let mut connection = redis_client.get_async_connection().await?;
+        let staff_id: Option<i64> = connection.get(challenge_key(token)).await?;
+
+        staff_id.ok_or(ServiceError::ChallengeExpired)
+    }
+
+    //? Challenge is single use so a wrong code sends staff back to the password step
+    #[tracing::instrument(skip(token, redis_client))]
+    pub async fn take_challenge(
+        token: &str,
+        redis_client: &redis::Client,
+    ) -> Result<i64, ServiceError> {
+        let mut connection = redis_client.get_async_connection().await?;
+        let staff_id: Option<i64> = connection.get_del(challenge_key(token)).await?;
+
+        staff_id.ok_or(ServiceError::ChallengeExpired)
+    }
+
+    //? Staff which has to enrol before its first login with 2fa required
+    #[tracing::instrument(skip(connection))]
+    pub async fn enrol_on_login<T>(
+        staff_id: i64,
+        issuer: &str,
+        connection: &T,
+    ) -> Result<Enrolment, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let staff = Self::challenged(staff_id, connection).await?;
+        Self::begin_enrolment(staff, issuer, connection).await
+    }
+
+    //? Code confirms a pending enrolment or is verified against the enabled one
+    #[tracing::instrument(skip(staff, code, connection), fields(staff_id = staff.id))]
+    pub async fn complete_login<T>(
+        staff: AdminModel,
+        code: &str,
+        connection: &T,
+    ) -> Result<AdminModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        match staff.totp_enabled_at {
+            Some(_) => {
+                Self::verify(staff.clone(), code, connection).await?;
+                Ok(staff)
+            }
+            None => Self::confirm_enrolment(staff, code, connection).await,
+        }
+    }
+}
@@ -38,9 +38,11 @@ pub struct GetChatParameters {
     pub order_id: i64,
 }
 
+//? Ids are kept to attribute messages, automessages are shown as the moderator's
 pub enum Sender {
-    Moderator,
-    User,
+    Moderator(i64),
+    User(i64),
+    Automessage,
 }
 
 pub struct UploadImagesData<'a> {
@@ -114,6 +116,7 @@ impl Service {
             let mut file = fs::OpenOptions::new()
                 .write(true)
                 .create(true)
+                .truncate(true)
                 .open(&real_filepath)
                 .await?;
             let _ = file.write(&parameters.image.unwrap().contents).await?;
@@ -151,8 +154,12 @@ impl Service {
             chat_id: Set(params.chat_id),
             text: Set(params.text),
             sender: match params.sender {
-                Sender::Moderator => Set(MessageSender::Moderator),
-                Sender::User => Set(MessageSender::User),
+                Sender::Moderator(_) | Sender::Automessage => Set(MessageSender::Moderator),
+                Sender::User(_) => Set(MessageSender::User),
+            },
+            sender_id: match params.sender {
+                Sender::Moderator(id) | Sender::User(id) => Set(Some(id)),
+                Sender::Automessage => Set(None),
             },
             ..Default::default()
         };
@@ -1,9 +1,23 @@
-use crate::errors::AppError;
-use entity::currency_rate::{
-    ActiveModel as CurrencyRateActiveModel, Column as CurrencyRateColumn,
-    Entity as CurrencyRateEntity, Model as CurrencyRateModel,
+use crate::{
+    errors::{AppError, FieldError},
+    services::pagination::{
+        Page, PageRequest, Service as PaginationService, ServiceError as PaginationError, Sort,
+        SortKey,
+    },
 };
-use sea_orm::{prelude::*, Set, TransactionTrait};
+use chrono::{Duration, NaiveDateTime, Utc};
+use entity::{
+    currency_rate::{
+        ActiveModel as CurrencyRateActiveModel, Column as CurrencyRateColumn,
+        Entity as CurrencyRateEntity, Model as CurrencyRateModel,
+    },
+    currency_rate_history::{
+        ActiveModel as RateHistoryActiveModel, Column as RateHistoryColumn,
+        Entity as RateHistoryEntity, Model as RateHistoryModel,
+    },
+    sea_orm_active_enums::RateSource,
+};
+use sea_orm::{prelude::*, FromQueryResult, QueryOrder, QuerySelect, Set, TransactionTrait};
 use std::fmt::Debug;
 
 #[allow(dead_code)]
@@ -17,6 +31,18 @@ pub enum ServiceError {
     SymbolNotFound,
     #[error("Currency symbol already exists")]
     SymbolAlreadyExists,
+    #[error("Currency has no rate waiting for approval")]
+    NoPendingRate,
+    #[error("Currency settings are invalid")]
+    SettingsInvalid(Vec<FieldError>),
+    #[error("Currency limits are invalid")]
+    LimitsInvalid(Vec<FieldError>),
+    #[error("Rate history request is invalid")]
+    HistoryInvalid(Vec<FieldError>),
+    #[error("Provider rate {0} is not positive")]
+    ProviderRateNotPositive(Decimal),
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
 }
 
 impl From<ServiceError> for AppError {
@@ -25,6 +51,14 @@ impl From<ServiceError> for AppError {
             ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
             ServiceError::SymbolNotFound => AppError::SymbolNotFound,
             ServiceError::SymbolAlreadyExists => AppError::SymbolAlreadyExists,
+            ServiceError::NoPendingRate => AppError::NoPendingRate,
+            ServiceError::SettingsInvalid(errors) => AppError::ValidationFailed(errors),
+            ServiceError::LimitsInvalid(errors) => AppError::ValidationFailed(errors),
+            ServiceError::HistoryInvalid(errors) => AppError::ValidationFailed(errors),
+            ServiceError::ProviderRateNotPositive(raw) => {
+                AppError::InternalServerError(Box::new(ServiceError::ProviderRateNotPositive(raw)))
+            }
+            ServiceError::Pagination(cause) => cause.into(),
         }
     }
 }
@@ -33,12 +67,247 @@ impl From<ServiceError> for AppError {
 pub struct CreateCurrencyRateParameters {
     pub symbol: String,
     pub rate: Decimal,
+    pub admin_id: Option<i64>,
 }
 
 #[derive(Debug)]
 pub struct SetCurrencyRateParameters {
     pub id: i64,
     pub rate: Decimal,
+    pub admin_id: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SetCurrencySettingsParameters {
+    pub id: i64,
+    pub auto_update: bool,
+    pub markup: Decimal,
+    pub max_jump: Option<Decimal>,
+    pub manual_override: bool,
+}
+
+impl SetCurrencySettingsParameters {
+    //? Markup of -100% or less would make the rate zero or negative
+    fn violations(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+
+        if self.markup <= -Decimal::ONE_HUNDRED {
+            errors.push(FieldError {
+                field: String::from("markup"),
+                code: String::from("out_of_range"),
+                message: String::from("Markup must be greater than -100"),
+                limit: Some(String::from("-100")),
+            });
+        }
+
+        if matches!(self.max_jump, Some(max_jump) if max_jump < Decimal::ZERO) {
+            errors.push(FieldError {
+                field: String::from("max_jump"),
+                code: String::from("negative"),
+                message: String::from("Max jump must not be negative"),
+                limit: None,
+            });
+        }
+
+        errors
+    }
+}
+
+#[derive(Debug)]
+pub struct SetCurrencyLimitsParameters {
+    pub id: i64,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub scale: Option<i32>,
+    pub daily_limit: Option<Decimal>,
+}
+
+impl SetCurrencyLimitsParameters {
+    //? Decimal can not hold more than 28 digits after the point
+    const MAX_SCALE: i32 = 28;
+
+    fn violations(&self) -> Vec<FieldError> {
+        let error = |field: &str, code: &str, message: &str| FieldError {
+            field: String::from(field),
+            code: String::from(code),
+            message: String::from(message),
+            limit: None,
+        };
+
+        let mut errors = vec![];
+
+        for (field, value) in [
+            ("min_amount", self.min_amount),
+            ("max_amount", self.max_amount),
+            ("daily_limit", self.daily_limit),
+        ] {
+            if matches!(value, Some(value) if value <= Decimal::ZERO) {
+                errors.push(error(
+                    field,
+                    "not_positive",
+                    "Limit must be greater than zero",
+                ));
+            }
+        }
+
+        if let (Some(minimum), Some(maximum)) = (self.min_amount, self.max_amount) {
+            if minimum > maximum {
+                errors.push(error(
+                    "min_amount",
+                    "above_maximum",
+                    "Minimum amount must not be greater than maximum amount",
+                ));
+            }
+        }
+
+        if matches!(self.scale, Some(scale) if !(0..=Self::MAX_SCALE).contains(&scale)) {
+            errors.push(FieldError {
+                limit: Some(Self::MAX_SCALE.to_string()),
+                ..error("scale", "out_of_range", "Scale must be between 0 and 28")
+            });
+        }
+
+        errors
+    }
+}
+
+#[derive(Debug)]
+pub enum ProviderRateOutcome {
+    Updated(CurrencyRateModel),
+    Unchanged,
+    //? Jump was bigger than allowed so an admin has to approve it
+    PendingApproval(CurrencyRateModel),
+    //? Auto update is disabled or rate was set manually
+    Skipped,
+}
+
+//? What has to be done with a raw provider value before touching the database
+#[derive(Debug, PartialEq)]
+enum ProviderRateDecision {
+    Skip,
+    Unchanged,
+    Pending(Decimal),
+    //? Applied even when equal to the current rate to refresh it and lift staleness
+    Apply(Decimal),
+}
+
+impl ProviderRateDecision {
+    //? Applies markup to the raw provider value and checks it against max jump
+    //? Jump is measured in percents of the current rate
+    fn new(rate: &CurrencyRateModel, raw: Decimal) -> Result<Self, ServiceError> {
+        //? Zero or negative value means provider is broken, not that the currency is free
+        if raw <= Decimal::ZERO {
+            return Err(ServiceError::ProviderRateNotPositive(raw));
+        }
+
+        if !rate.auto_update || rate.manual_override {
+            return Ok(Self::Skip);
+        }
+
+        let hundred = Decimal::ONE_HUNDRED;
+        let new_rate = (raw * (hundred + rate.markup) / hundred)
+            .round_dp(8)
+            .normalize();
+
+        let jump_exceeded = match rate.max_jump {
+            Some(max_jump) if !rate.rate.is_zero() => {
+                (new_rate - rate.rate).abs() / rate.rate * hundred > max_jump
+            }
+            _ => false,
+        };
+
+        match jump_exceeded {
+            true if rate.pending_rate == Some(new_rate) => Ok(Self::Unchanged),
+            true => Ok(Self::Pending(new_rate)),
+            false => Ok(Self::Apply(new_rate)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    //? Used inside of the query so it must stay a fixed literal
+    fn date_trunc(&self) -> &'static str {
+        match self {
+            Bucket::Hour => "date_trunc('hour', created_at)",
+            Bucket::Day => "date_trunc('day', created_at)",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateHistoryParameters {
+    pub symbol: String,
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub bucket: Option<Bucket>,
+}
+
+impl RateHistoryParameters {
+    //? History is public so a single request must stay cheap
+    //? Every change is returned only up to this count, longer series need a bucket
+    const MAX_CHANGES: u64 = 1000;
+
+    //? Hourly buckets are limited to about 750 points, daily ones to about 370
+    fn max_range_days(&self) -> i64 {
+        match self.bucket {
+            None | Some(Bucket::Hour) => 31,
+            Some(Bucket::Day) => 366,
+        }
+    }
+
+    fn range_violation(&self) -> Option<FieldError> {
+        let max_range_days = self.max_range_days();
+
+        (self.to - self.from > Duration::days(max_range_days)).then(|| FieldError {
+            field: String::from("from"),
+            code: String::from("range_too_long"),
+            message: format!("Range must not be longer than {max_range_days} days"),
+            limit: Some(max_range_days.to_string()),
+        })
+    }
+
+    fn changes_violation() -> FieldError {
+        FieldError {
+            field: String::from("bucket"),
+            code: String::from("too_many_points"),
+            message: String::from("Range holds too many changes, request a bucket"),
+            limit: Some(Self::MAX_CHANGES.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct RatePoint {
+    pub timestamp: NaiveDateTime,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+impl From<RateHistoryModel> for RatePoint {
+    fn from(value: RateHistoryModel) -> Self {
+        Self {
+            timestamp: value.created_at,
+            open: value.rate,
+            high: value.rate,
+            low: value.rate,
+            close: value.rate,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateHistory {
+    //? Rate which was in effect at the start of the range
+    pub initial: Option<Decimal>,
+    pub points: Vec<RatePoint>,
 }
 
 impl Service {
@@ -80,9 +349,18 @@ impl Service {
                     rate: Set(parameters.rate),
                     ..Default::default()
                 };
-                Ok(CurrencyRateEntity::insert(currency_rate_to_be_inserted)
+                let created = CurrencyRateEntity::insert(currency_rate_to_be_inserted)
                     .exec_with_returning(connection)
-                    .await?)
+                    .await?;
+
+                Self::record(
+                    &created,
+                    parameters.admin_id,
+                    RateSource::Manual,
+                    connection,
+                )
+                .await?;
+                Ok(created)
             }
         }
     }
@@ -100,9 +378,22 @@ impl Service {
             .await?
         {
             Some(rate) => {
+                //? Rate set by hand wins over provider until override is lifted
                 let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
                 currency_rate_to_be_updated.rate = Set(parameters.rate);
-                currency_rate_to_be_updated.update(connection).await?;
+                currency_rate_to_be_updated.manual_override = Set(true);
+                currency_rate_to_be_updated.pending_rate = Set(None);
+                currency_rate_to_be_updated.stale = Set(false);
+                currency_rate_to_be_updated.updated_at = Set(Utc::now().naive_local());
+                let updated = currency_rate_to_be_updated.update(connection).await?;
+
+                Self::record(
+                    &updated,
+                    parameters.admin_id,
+                    RateSource::Manual,
+                    connection,
+                )
+                .await?;
                 Ok(())
             }
             None => Err(ServiceError::SymbolNotFound),
@@ -141,4 +432,405 @@ impl Service {
             None => Err(ServiceError::SymbolNotFound),
         }
     }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn set_settings<T>(
+        parameters: SetCurrencySettingsParameters,
+        connection: &T,
+    ) -> Result<CurrencyRateModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let violations = parameters.violations();
+        if !violations.is_empty() {
+            return Err(ServiceError::SettingsInvalid(violations));
+        }
+
+        let rate = Self::get(parameters.id, connection).await?;
+
+        let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
+        currency_rate_to_be_updated.auto_update = Set(parameters.auto_update);
+        currency_rate_to_be_updated.markup = Set(parameters.markup);
+        currency_rate_to_be_updated.max_jump = Set(parameters.max_jump);
+        currency_rate_to_be_updated.manual_override = Set(parameters.manual_override);
+
+        Ok(currency_rate_to_be_updated.update(connection).await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn set_limits<T>(
+        parameters: SetCurrencyLimitsParameters,
+        connection: &T,
+    ) -> Result<CurrencyRateModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let violations = parameters.violations();
+        if !violations.is_empty() {
+            return Err(ServiceError::LimitsInvalid(violations));
+        }
+
+        let rate = Self::get(parameters.id, connection).await?;
+
+        let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
+        currency_rate_to_be_updated.min_amount = Set(parameters.min_amount);
+        currency_rate_to_be_updated.max_amount = Set(parameters.max_amount);
+        currency_rate_to_be_updated.scale = Set(parameters.scale);
+        currency_rate_to_be_updated.daily_limit = Set(parameters.daily_limit);
+
+        Ok(currency_rate_to_be_updated.update(connection).await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn auto_updated<T>(connection: &T) -> Result<Vec<CurrencyRateModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(CurrencyRateEntity::find()
+            .filter(CurrencyRateColumn::AutoUpdate.eq(true))
+            .filter(CurrencyRateColumn::ManualOverride.eq(false))
+            .all(connection)
+            .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn apply_provider_rate<T>(
+        id: i64,
+        raw: Decimal,
+        connection: &T,
+    ) -> Result<ProviderRateOutcome, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let rate = match CurrencyRateEntity::find_by_id(id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(rate) => rate,
+            None => return Err(ServiceError::SymbolNotFound),
+        };
+
+        let new_rate = match ProviderRateDecision::new(&rate, raw)? {
+            ProviderRateDecision::Skip => return Ok(ProviderRateOutcome::Skipped),
+            ProviderRateDecision::Unchanged => return Ok(ProviderRateOutcome::Unchanged),
+            ProviderRateDecision::Pending(pending_rate) => {
+                let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
+                currency_rate_to_be_updated.pending_rate = Set(Some(pending_rate));
+                let updated = currency_rate_to_be_updated.update(connection).await?;
+                return Ok(ProviderRateOutcome::PendingApproval(updated));
+            }
+            ProviderRateDecision::Apply(new_rate) => new_rate,
+        };
+
+        let changed = new_rate != rate.rate;
+
+        let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
+        currency_rate_to_be_updated.rate = Set(new_rate);
+        currency_rate_to_be_updated.pending_rate = Set(None);
+        currency_rate_to_be_updated.stale = Set(false);
+        currency_rate_to_be_updated.updated_at = Set(Utc::now().naive_local());
+        let updated = currency_rate_to_be_updated.update(connection).await?;
+
+        if !changed {
+            return Ok(ProviderRateOutcome::Unchanged);
+        }
+
+        Self::record(&updated, None, RateSource::Provider, connection).await?;
+        Ok(ProviderRateOutcome::Updated(updated))
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn approve_pending<T>(
+        id: i64,
+        admin_id: i64,
+        connection: &T,
+    ) -> Result<CurrencyRateModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let rate = match CurrencyRateEntity::find_by_id(id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(rate) => rate,
+            None => return Err(ServiceError::SymbolNotFound),
+        };
+
+        let pending_rate = match rate.pending_rate {
+            Some(pending_rate) => pending_rate,
+            None => return Err(ServiceError::NoPendingRate),
+        };
+
+        let mut currency_rate_to_be_updated: CurrencyRateActiveModel = rate.into();
+        currency_rate_to_be_updated.rate = Set(pending_rate);
+        currency_rate_to_be_updated.pending_rate = Set(None);
+        currency_rate_to_be_updated.stale = Set(false);
+        currency_rate_to_be_updated.updated_at = Set(Utc::now().naive_local());
+        let updated = currency_rate_to_be_updated.update(connection).await?;
+
+        Self::record(&updated, Some(admin_id), RateSource::Approval, connection).await?;
+        Ok(updated)
+    }
+
+    //? Provider has not delivered a fresh value for too long
+    //? Manually overridden rates are never stale
+    #[tracing::instrument(skip(connection))]
+    pub async fn mark_stale<T>(
+        updated_before: NaiveDateTime,
+        connection: &T,
+    ) -> Result<u64, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(CurrencyRateEntity::update_many()
+            .col_expr(CurrencyRateColumn::Stale, Expr::value(true))
+            .filter(CurrencyRateColumn::AutoUpdate.eq(true))
+            .filter(CurrencyRateColumn::ManualOverride.eq(false))
+            .filter(CurrencyRateColumn::Stale.eq(false))
+            .filter(CurrencyRateColumn::UpdatedAt.lt(updated_before))
+            .exec(connection)
+            .await?
+            .rows_affected)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    async fn record<T>(
+        rate: &CurrencyRateModel,
+        admin_id: Option<i64>,
+        source: RateSource,
+        connection: &T,
+    ) -> Result<RateHistoryModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let record_to_be_inserted = RateHistoryActiveModel {
+            symbol: Set(rate.symbol.clone()),
+            rate: Set(rate.rate),
+            admin_id: Set(admin_id),
+            source: Set(source),
+            ..Default::default()
+        };
+
+        Ok(RateHistoryEntity::insert(record_to_be_inserted)
+            .exec_with_returning(connection)
+            .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn changes<T>(
+        symbol: &str,
+        page: &PageRequest,
+        connection: &T,
+    ) -> Result<Page<RateHistoryModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(PaginationService::paginate(
+            RateHistoryEntity::find().filter(RateHistoryColumn::Symbol.eq(symbol)),
+            RateHistoryColumn::Id,
+            Sort {
+                name: "created_at",
+                column: RateHistoryColumn::CreatedAt,
+                key: |change| SortKey::Timestamp(change.created_at),
+            },
+            page,
+            connection,
+        )
+        .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn history<T>(
+        parameters: RateHistoryParameters,
+        connection: &T,
+    ) -> Result<RateHistory, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if let Some(violation) = parameters.range_violation() {
+            return Err(ServiceError::HistoryInvalid(vec![violation]));
+        }
+
+        let initial = RateHistoryEntity::find()
+            .filter(RateHistoryColumn::Symbol.eq(&parameters.symbol))
+            .filter(RateHistoryColumn::CreatedAt.lt(parameters.from))
+            .order_by_desc(RateHistoryColumn::CreatedAt)
+            .order_by_desc(RateHistoryColumn::Id)
+            .one(connection)
+            .await?
+            .map(|record| record.rate);
+
+        let in_range = RateHistoryEntity::find()
+            .filter(RateHistoryColumn::Symbol.eq(&parameters.symbol))
+            .filter(RateHistoryColumn::CreatedAt.between(parameters.from, parameters.to));
+
+        let points = match parameters.bucket {
+            Some(bucket) => {
+                in_range
+                    .select_only()
+                    .column_as(Expr::cust(bucket.date_trunc()), "timestamp")
+                    .column_as(
+                        Expr::cust("(array_agg(rate ORDER BY created_at ASC, id ASC))[1]"),
+                        "open",
+                    )
+                    .column_as(RateHistoryColumn::Rate.max(), "high")
+                    .column_as(RateHistoryColumn::Rate.min(), "low")
+                    .column_as(
+                        Expr::cust("(array_agg(rate ORDER BY created_at DESC, id DESC))[1]"),
+                        "close",
+                    )
+                    .group_by(Expr::cust(bucket.date_trunc()))
+                    .order_by_asc(Expr::cust(bucket.date_trunc()))
+                    .into_model::<RatePoint>()
+                    .all(connection)
+                    .await?
+            }
+            None => {
+                //? One extra row tells whether the range holds more than allowed
+                let changes = in_range
+                    .order_by_asc(RateHistoryColumn::CreatedAt)
+                    .order_by_asc(RateHistoryColumn::Id)
+                    .limit(RateHistoryParameters::MAX_CHANGES + 1)
+                    .all(connection)
+                    .await?;
+
+                if changes.len() as u64 > RateHistoryParameters::MAX_CHANGES {
+                    return Err(ServiceError::HistoryInvalid(vec![
+                        RateHistoryParameters::changes_violation(),
+                    ]));
+                }
+
+                changes.into_iter().map(Into::<RatePoint>::into).collect()
+            }
+        };
+
+        Ok(RateHistory { initial, points })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn currency(rate: &str, markup: &str, max_jump: Option<&str>) -> CurrencyRateModel {
+        CurrencyRateModel {
+            id: 1,
+            symbol: String::from("USD"),
+            rate: rate.parse().unwrap(),
+            auto_update: true,
+            markup: markup.parse().unwrap(),
+            max_jump: max_jump.map(|max_jump| max_jump.parse().unwrap()),
+            pending_rate: None,
+            manual_override: false,
+            stale: false,
+            updated_at: Utc::now().naive_local(),
+            min_amount: None,
+            max_amount: None,
+            scale: None,
+            daily_limit: None,
+        }
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn markup_is_applied_to_provider_rate() {
+        let rate = currency("90", "2.5", None);
+
+        let decision = ProviderRateDecision::new(&rate, decimal("100"));
+
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Apply(decimal("102.5"))
+        );
+    }
+
+    #[test]
+    fn negative_markup_lowers_provider_rate() {
+        let rate = currency("90", "-1", None);
+
+        let decision = ProviderRateDecision::new(&rate, decimal("91.123456789"));
+
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Apply(decimal("90.21222222"))
+        );
+    }
+
+    #[test]
+    fn jump_above_maximum_waits_for_approval() {
+        let mut rate = currency("100", "0", Some("10"));
+
+        let decision = ProviderRateDecision::new(&rate, decimal("150"));
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Pending(decimal("150"))
+        );
+
+        //? Same value is not stored again while it waits for an admin
+        rate.pending_rate = Some(decimal("150"));
+        let decision = ProviderRateDecision::new(&rate, decimal("150"));
+        assert_eq!(decision.unwrap(), ProviderRateDecision::Unchanged);
+
+        //? Markup counts towards the jump
+        rate.markup = decimal("5");
+        let decision = ProviderRateDecision::new(&rate, decimal("104"));
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Apply(decimal("109.2"))
+        );
+    }
+
+    #[test]
+    fn jump_within_maximum_is_applied() {
+        let rate = currency("100", "0", Some("10"));
+
+        let decision = ProviderRateDecision::new(&rate, decimal("90"));
+
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Apply(decimal("90"))
+        );
+    }
+
+    #[test]
+    fn not_positive_provider_rate_is_rejected() {
+        let rate = currency("100", "0", None);
+
+        for raw in ["0", "-1"] {
+            let decision = ProviderRateDecision::new(&rate, decimal(raw));
+
+            assert!(matches!(
+                decision,
+                Err(ServiceError::ProviderRateNotPositive(value)) if value == decimal(raw)
+            ));
+        }
+    }
+
+    #[test]
+    fn stale_rate_is_refreshed_by_the_same_value() {
+        let mut rate = currency("100", "0", None);
+        rate.stale = true;
+
+        let decision = ProviderRateDecision::new(&rate, decimal("100"));
+
+        assert_eq!(
+            decision.unwrap(),
+            ProviderRateDecision::Apply(decimal("100"))
+        );
+    }
+
+    #[test]
+    fn manually_set_rate_is_skipped() {
+        let mut rate = currency("100", "0", None);
+        rate.manual_override = true;
+
+        let decision = ProviderRateDecision::new(&rate, decimal("200"));
+
+        assert_eq!(decision.unwrap(), ProviderRateDecision::Skip);
+    }
 }
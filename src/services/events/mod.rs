@@ -0,0 +1,86 @@
+use chrono::{NaiveDateTime, Utc};
+use entity::order::Model as OrderModel;
+use redis::AsyncCommands;
+use std::fmt::Debug;
+
+//? Bump this every time the payload changes in a non additive way
+//? Consumers must skip events with a version they do not know
+pub const ORDER_EVENT_VERSION: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderEventKind {
+    Created,
+    Maybepayed,
+    Succeeded,
+    Cancelled,
+    //? Maybepayed order has been waiting for a moderator for too long
+    Flagged,
+    ModeratorAssigned { moderator_id: i64 },
+    ModeratorUnassigned { moderator_id: i64 },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OrderEvent {
+    pub version: u16,
+    pub kind: OrderEventKind,
+    pub order: OrderModel,
+    pub emitted_at: NaiveDateTime,
+}
+
+impl OrderEvent {
+    pub fn new(kind: OrderEventKind, order: OrderModel) -> Self {
+        Self {
+            version: ORDER_EVENT_VERSION,
+            kind,
+            order,
+            emitted_at: Utc::now().naive_utc(),
+        }
+    }
+
+    //? Returns None for malformed payloads and unknown versions
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(payload)
+            .ok()
+            .filter(|event| event.version == ORDER_EVENT_VERSION)
+    }
+}
+
+pub struct Service;
+
+impl Service {
+    //? Events are published only after the transaction was committed
+    //? Failing to publish must not fail the request so we only warn
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn publish(event: &OrderEvent, channel: &str, redis_client: &redis::Client) {
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(cause) => {
+                tracing::warn!(%cause, "Failed to serialize order event!");
+                return;
+            }
+        };
+
+        match redis_client.get_async_connection().await {
+            Ok(mut connection) => {
+                if let Err(cause) = connection.publish::<_, _, ()>(channel, payload).await {
+                    tracing::warn!(%cause, "Failed to publish order event!");
+                }
+            }
+            Err(cause) => {
+                // Not very important
+                tracing::warn!(%cause, "Failed to connect to redis!");
+            }
+        };
+    }
+
+    pub async fn publish_all(
+        events: impl IntoIterator<Item = OrderEvent> + Debug,
+        channel: &str,
+        redis_client: &redis::Client,
+    ) {
+        for event in events {
+            Self::publish(&event, channel, redis_client).await;
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use std::borrow::Cow;
+
+use chrono::NaiveDateTime;
+use entity::{
+    admin::Column as AdminColumn,
+    order::{Column as OrderColumn, Entity as OrderEntity, Relation as OrderRelation},
+    requisites::Column as RequisitesColumn,
+    user::{Column as UserColumn, Entity as UserEntity, Relation as UserRelation},
+};
+use futures_util::{stream, Stream};
+use migration::Expr;
+use rust_xlsxwriter::Workbook;
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    FromQueryResult, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+};
+
+use crate::{
+    errors::AppError,
+    services::{
+        orders::{OrderSearch, OrderSort},
+        pagination::{Cursor, Direction, PageRequest, SortKey},
+    },
+};
+
+//? Rows are read by keyset batches so exports never hold the whole table
+const BATCH_SIZE: u64 = 500;
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+//? Spreadsheets treat text starting with these as a formula
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Number {0} can not be written to a spreadsheet")]
+    NumberOutOfRange(Decimal),
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::Csv(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::Xlsx(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::Io(cause) => AppError::InternalServerError(Box::new(cause)),
+            cause @ ServiceError::NumberOutOfRange(_) => {
+                AppError::InternalServerError(Box::new(cause))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Cell {
+    //? Ids are written as text as steam ids do not fit into spreadsheet numbers
+    Text(String),
+    Number(Decimal),
+    Empty,
+}
+
+impl Cell {
+    fn text(value: impl ToString) -> Self {
+        Cell::Text(value.to_string())
+    }
+
+    fn datetime(value: NaiveDateTime) -> Self {
+        Cell::Text(value.format(DATETIME_FORMAT).to_string())
+    }
+
+    fn optional(value: Option<Cell>) -> Self {
+        value.unwrap_or(Cell::Empty)
+    }
+}
+
+//? User supplied text like usernames must not run as a formula when the export is opened
+fn escape_formula(text: &str) -> Cow<'_, str> {
+    match text.starts_with(FORMULA_PREFIXES) {
+        true => Cow::Owned(format!("'{}", text)),
+        false => Cow::Borrowed(text),
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => f.write_str(&escape_formula(text)),
+            Cell::Number(number) => write!(f, "{}", number),
+            Cell::Empty => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct OrderRow {
+    id: i64,
+    created_at: NaiveDateTime,
+    finished_at: Option<NaiveDateTime>,
+    status: String,
+    steam_id: i64,
+    username: Option<String>,
+    moderator_id: Option<i64>,
+    moderator_login: Option<String>,
+    requisites_name: Option<String>,
+    payment_method: String,
+    currency_symbol: String,
+    amount: Decimal,
+    fixed_currency_rate: Decimal,
+}
+
+impl OrderRow {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "created_at",
+        "finished_at",
+        "status",
+        "steam_id",
+        "username",
+        "moderator_id",
+        "moderator_login",
+        "requisites",
+        "payment_method",
+        "currency",
+        "amount",
+        "fixed_currency_rate",
+        "converted_amount",
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        vec![
+            Cell::text(self.id),
+            Cell::datetime(self.created_at),
+            Cell::optional(self.finished_at.map(Cell::datetime)),
+            Cell::Text(self.status),
+            Cell::text(self.steam_id),
+            Cell::optional(self.username.map(Cell::Text)),
+            Cell::optional(self.moderator_id.map(Cell::text)),
+            Cell::optional(self.moderator_login.map(Cell::Text)),
+            Cell::optional(self.requisites_name.map(Cell::Text)),
+            Cell::Text(self.payment_method),
+            Cell::Text(self.currency_symbol),
+            Cell::Number(self.amount),
+            Cell::Number(self.fixed_currency_rate),
+            Cell::Number(self.amount * self.fixed_currency_rate),
+        ]
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct UserRow {
+    steam_id: i64,
+    username: Option<String>,
+    email: Option<String>,
+    trade_url: Option<String>,
+    registered_at: NaiveDateTime,
+    succeeded_amount: Decimal,
+}
+
+impl OrderRow {
+    fn sort_key(&self, sort: OrderSort) -> SortKey {
+        match sort {
+            OrderSort::CreatedAt => SortKey::Timestamp(self.created_at),
+            OrderSort::Amount => SortKey::Decimal(self.amount),
+        }
+    }
+}
+
+impl UserRow {
+    const HEADER: &'static [&'static str] = &[
+        "steam_id",
+        "username",
+        "email",
+        "trade_url",
+        "registered_at",
+        "succeeded_amount",
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        vec![
+            Cell::text(self.steam_id),
+            Cell::optional(self.username.map(Cell::Text)),
+            Cell::optional(self.email.map(Cell::Text)),
+            Cell::optional(self.trade_url.map(Cell::Text)),
+            Cell::datetime(self.registered_at),
+            Cell::Number(self.succeeded_amount),
+        ]
+    }
+}
+
+//? Registration range is inclusive
+#[derive(Debug, Clone, Default)]
+pub struct UserExportFilter {
+    pub steam_id: Option<i64>,
+    pub registered_from: Option<NaiveDateTime>,
+    pub registered_to: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Export {
+    //? Orders go in ascending order of the requested sort column
+    Orders(OrderSearch, OrderSort),
+    Users(UserExportFilter),
+}
+
+impl Export {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Export::Orders(..) => "orders",
+            Export::Users(_) => "users",
+        }
+    }
+
+    fn header(&self) -> &'static [&'static str] {
+        match self {
+            Export::Orders(..) => OrderRow::HEADER,
+            Export::Users(_) => UserRow::HEADER,
+        }
+    }
+
+    //? Returns rows after the given cursor together with the cursor of the last row
+    async fn batch<T>(
+        &self,
+        after: Option<&Cursor>,
+        connection: &T,
+    ) -> Result<(Vec<Vec<Cell>>, Option<Cursor>), ServiceError>
+    where
+        T: ConnectionTrait,
+    {
+        let page = PageRequest {
+            cursor: None,
+            limit: BATCH_SIZE,
+            direction: Direction::Asc,
+            direction_requested: true,
+        };
+
+        match self {
+            Export::Orders(search, order_sort) => {
+                let sort = order_sort.sort();
+                let mut select = search.clone().apply(OrderEntity::find());
+                if let Some(after) = after {
+                    select = select.filter(page.condition(after, sort.column, OrderColumn::Id));
+                }
+
+                let rows = select
+                    .select_only()
+                    .columns([
+                        OrderColumn::Id,
+                        OrderColumn::CreatedAt,
+                        OrderColumn::FinishedAt,
+                        OrderColumn::SteamId,
+                        OrderColumn::ModeratorId,
+                        OrderColumn::PaymentMethod,
+                        OrderColumn::CurrencySymbol,
+                        OrderColumn::Amount,
+                        OrderColumn::FixedCurrencyRate,
+                    ])
+                    .column_as(Expr::cust(r#""order".status::text"#), "status")
+                    .column(UserColumn::Username)
+                    .column_as(AdminColumn::Login, "moderator_login")
+                    .column_as(RequisitesColumn::Name, "requisites_name")
+                    .join(JoinType::LeftJoin, OrderRelation::User.def())
+                    .join(JoinType::LeftJoin, OrderRelation::Admin.def())
+                    .join(JoinType::LeftJoin, OrderRelation::Requisites.def())
+                    .order_by_asc(sort.column)
+                    .order_by_asc(OrderColumn::Id)
+                    .limit(page.limit)
+                    .into_model::<OrderRow>()
+                    .all(connection)
+                    .await?;
+
+                let last = rows.last().map(|row| Cursor {
+                    sort: String::from(sort.name),
+                    direction: page.direction,
+                    key: row.sort_key(*order_sort),
+                    id: row.id,
+                });
+                Ok((rows.into_iter().map(OrderRow::cells).collect(), last))
+            }
+            Export::Users(filter) => {
+                let mut select = UserEntity::find();
+                if let Some(after) = after {
+                    select = select.filter(UserColumn::SteamId.gt(after.id));
+                }
+                if let Some(steam_id) = filter.steam_id {
+                    select = select.filter(UserColumn::SteamId.eq(steam_id));
+                }
+                if let Some(registered_from) = filter.registered_from {
+                    select = select.filter(UserColumn::RegisteredAt.gte(registered_from));
+                }
+                if let Some(registered_to) = filter.registered_to {
+                    select = select.filter(UserColumn::RegisteredAt.lte(registered_to));
+                }
+
+                //? Same sum as in top users but users without orders are kept
+                let rows = select
+                    .select_only()
+                    .columns([
+                        UserColumn::SteamId,
+                        UserColumn::Username,
+                        UserColumn::Email,
+                        UserColumn::TradeUrl,
+                        UserColumn::RegisteredAt,
+                    ])
+                    .column_as(
+                        Expr::cust(
+                            r#"coalesce(sum("order".amount) filter (where "order".status = 'succeeded'), 0)"#,
+                        ),
+                        "succeeded_amount",
+                    )
+                    .join(JoinType::LeftJoin, UserRelation::Order.def())
+                    .group_by(UserColumn::SteamId)
+                    .order_by_asc(UserColumn::SteamId)
+                    .limit(page.limit)
+                    .into_model::<UserRow>()
+                    .all(connection)
+                    .await?;
+
+                let last = rows.last().map(|row| Cursor {
+                    sort: String::from("steam_id"),
+                    direction: page.direction,
+                    key: SortKey::Integer(row.steam_id),
+                    id: row.steam_id,
+                });
+                Ok((rows.into_iter().map(UserRow::cells).collect(), last))
+            }
+        }
+    }
+}
+
+pub struct Service;
+
+impl Service {
+    //? Every item of the stream is one encoded batch, the first one starts with the header
+    pub fn csv(
+        export: Export,
+        connection: DatabaseConnection,
+    ) -> impl Stream<Item = Result<Vec<u8>, ServiceError>> {
+        stream::unfold(Some((export, connection, None, true)), |state| async move {
+            let (export, connection, after, first) = state?;
+
+            match Self::csv_batch(&export, after.as_ref(), first, &connection).await {
+                Ok((bytes, Some(last))) => {
+                    Some((Ok(bytes), Some((export, connection, Some(last), false))))
+                }
+                Ok((bytes, None)) => Some((Ok(bytes), None)),
+                Err(cause) => {
+                    tracing::error!(%cause, "Failed to export batch!");
+                    Some((Err(cause), None))
+                }
+            }
+        })
+    }
+
+    async fn csv_batch(
+        export: &Export,
+        after: Option<&Cursor>,
+        first: bool,
+        connection: &DatabaseConnection,
+    ) -> Result<(Vec<u8>, Option<Cursor>), ServiceError> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+
+        if first {
+            writer.write_record(export.header())?;
+        }
+
+        let (rows, last) = export.batch(after, connection).await?;
+        let full = rows.len() as u64 == BATCH_SIZE;
+        for row in rows {
+            writer.write_record(row.iter().map(ToString::to_string))?;
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|cause| ServiceError::Io(cause.into_error()))?;
+
+        //? A short batch is the last one
+        Ok((bytes, last.filter(|_| full)))
+    }
+
+    //? Xlsx is a zip archive so it can not be sent before it is complete
+    //? Rows are kept in a temporary file and the archive is written to another one
+    #[tracing::instrument(skip(connection))]
+    pub async fn xlsx(
+        export: Export,
+        connection: &DatabaseConnection,
+    ) -> Result<tokio::fs::File, ServiceError> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet_with_constant_memory();
+        worksheet.set_name(export.name())?;
+
+        for (column, title) in export.header().iter().enumerate() {
+            worksheet.write_string(0, column as u16, *title)?;
+        }
+
+        let mut row_number = 1;
+        let mut after = None;
+        loop {
+            let (rows, last) = export.batch(after.as_ref(), connection).await?;
+            let full = rows.len() as u64 == BATCH_SIZE;
+
+            for row in rows {
+                for (column, cell) in row.into_iter().enumerate() {
+                    let column = column as u16;
+                    match cell {
+                        Cell::Text(text) => {
+                            worksheet.write_string(row_number, column, escape_formula(&text))?;
+                        }
+                        Cell::Number(number) => {
+                            let number = f64::try_from(number)
+                                .map_err(|_| ServiceError::NumberOutOfRange(number))?;
+                            worksheet.write_number(row_number, column, number)?;
+                        }
+                        Cell::Empty => {}
+                    }
+                }
+                row_number += 1;
+            }
+
+            match last.filter(|_| full) {
+                Some(last) => after = Some(last),
+                None => break,
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("{}.xlsx", uuid::Uuid::new_v4()));
+        let destination = path.clone();
+        tokio::task::spawn_blocking(move || workbook.save(destination))
+            .await
+            .map_err(std::io::Error::other)??;
+        let file = tokio::fs::File::open(&path).await?;
+
+        //? Opened file stays readable after it was unlinked
+        tokio::fs::remove_file(&path).await?;
+        Ok(file)
+    }
+}
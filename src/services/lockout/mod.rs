@@ -0,0 +1,146 @@
+use redis::AsyncCommands;
+
+//? Lockouts in a row are counted within this time
+const LEVEL_TTL_SECONDS: u64 = 24 * 60 * 60; // 1 day
+
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    //? Zero disables the counter
+    pub attempts_per_login: u64,
+    pub attempts_per_ip: u64,
+    pub window_seconds: u64,
+    pub base_seconds: u64,
+    pub max_seconds: u64,
+}
+
+#[derive(Debug)]
+pub struct Attempt<'a> {
+    pub login: &'a str,
+    //? Unknown when the request did not come over tcp
+    pub ip: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Check {
+    Allowed,
+    Locked { retry_after: u64 },
+}
+
+pub struct Service;
+
+impl Attempt<'_> {
+    //? Login and address are counted separately so neither guessing passwords
+    //? of one account nor spraying one password over many accounts goes unnoticed
+    fn subjects(&self, policy: &LockoutPolicy) -> Vec<(String, u64)> {
+        let mut subjects = vec![(format!("login:{}", self.login), policy.attempts_per_login)];
+        if let Some(ip) = self.ip {
+            subjects.push((format!("ip:{}", ip), policy.attempts_per_ip));
+        }
+        subjects
+    }
+}
+
+impl LockoutPolicy {
+    //? Doubles with every lockout in a row
+    fn duration(&self, level: u64) -> u64 {
+        let exponent = level.saturating_sub(1).min(32) as u32;
+        self.base_seconds
+            .saturating_mul(2u64.saturating_pow(exponent))
+            .min(self.max_seconds)
+            .max(1)
+    }
+}
+
+impl Service {
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn check(
+        attempt: &Attempt<'_>,
+        policy: &LockoutPolicy,
+        redis_client: &redis::Client,
+    ) -> Result<Check, redis::RedisError> {
+        let mut connection = redis_client.get_async_connection().await?;
+
+        let mut retry_after = 0;
+        for (subject, _) in attempt.subjects(policy) {
+            let ttl: i64 = connection.ttl(format!("login-lockout:{}", subject)).await?;
+            retry_after = retry_after.max(ttl.max(0) as u64);
+        }
+
+        match retry_after {
+            0 => Ok(Check::Allowed),
+            retry_after => Ok(Check::Locked { retry_after }),
+        }
+    }
+
+    //? Reaching the limit locks the subject out and starts counting from zero
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn failed(
+        attempt: &Attempt<'_>,
+        policy: &LockoutPolicy,
+        redis_client: &redis::Client,
+    ) -> Result<Check, redis::RedisError> {
+        let mut connection = redis_client.get_async_connection().await?;
+
+        let mut retry_after = 0;
+        for (subject, limit) in attempt.subjects(policy) {
+            if limit == 0 {
+                continue;
+            }
+
+            let failures_key = format!("login-failures:{}", subject);
+            let (failures,): (u64,) = redis::pipe()
+                .atomic()
+                .incr(&failures_key, 1)
+                .expire(&failures_key, policy.window_seconds as i64)
+                .ignore()
+                .query_async(&mut connection)
+                .await?;
+
+            if failures < limit {
+                continue;
+            }
+
+            let level_key = format!("login-lockout-level:{}", subject);
+            let (level,): (u64,) = redis::pipe()
+                .atomic()
+                .incr(&level_key, 1)
+                .expire(&level_key, LEVEL_TTL_SECONDS as i64)
+                .ignore()
+                .del(&failures_key)
+                .ignore()
+                .query_async(&mut connection)
+                .await?;
+
+            let duration = policy.duration(level);
+            connection
+                .set_ex::<_, _, ()>(format!("login-lockout:{}", subject), level, duration)
+                .await?;
+            retry_after = retry_after.max(duration);
+        }
+
+        match retry_after {
+            0 => Ok(Check::Allowed),
+            retry_after => Ok(Check::Locked { retry_after }),
+        }
+    }
+
+    //? Address keeps its level, one correct password does not vouch for the others
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn succeeded(
+        attempt: &Attempt<'_>,
+        redis_client: &redis::Client,
+    ) -> Result<(), redis::RedisError> {
+        let mut connection = redis_client.get_async_connection().await?;
+
+        let login = format!("login:{}", attempt.login);
+        redis::pipe()
+            .del(format!("login-failures:{}", login))
+            .ignore()
+            .del(format!("login-lockout-level:{}", login))
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,9 +1,20 @@
 pub mod admin;
+pub mod assignment;
 pub mod auth;
 pub mod chat;
 pub mod currency;
+pub mod events;
+pub mod exports;
+pub mod lockout;
 pub mod orders;
+pub mod pagination;
+pub mod quotes;
+pub mod rate_limit;
+pub mod rates;
+pub mod reports;
 pub mod requisites;
 pub mod reviews;
+pub mod seed;
+pub mod sessions;
 pub mod social;
 pub mod users;
@@ -1,22 +1,56 @@
-use crate::errors::AppError;
+use crate::{
+    errors::{AppError, FieldError},
+    services::{
+        admin::permissions::ServiceError as PermissionsError,
+        assignment::{AssignmentStrategy, Service as AssignmentService},
+        pagination::{
+            Page, PageRequest, Service as PaginationService, ServiceError as PaginationError, Sort,
+            SortKey,
+        },
+    },
+};
 
-use chrono::Utc;
+use chrono::{Duration, NaiveDateTime, Utc};
 use entity::{
-    admin::{Column as AdminColumn, Entity as AdminEntity},
+    admin::{Entity as AdminEntity, Model as AdminModel},
+    chat::{Entity as ChatEntity, Model as ChatModel},
+    currency_rate::{
+        Column as CurrencyRateColumn, Entity as CurrencyRateEntity, Model as CurrencyRateModel,
+    },
+    message::Entity as MessageEntity,
     order::{
         ActiveModel as OrderActiveModel, Column as OrderColumn, Entity as OrderEntity,
         Model as OrderModel,
     },
-    requisites::Entity as RequisitesEntity,
-    sea_orm_active_enums::{Role, Status},
+    order_status_history::{
+        Column as HistoryColumn, Entity as HistoryEntity, Model as HistoryModel,
+    },
+    requisites::{Entity as RequisitesEntity, Model as RequisitesModel},
+    sea_orm_active_enums::Status,
+    user::{Entity as UserEntity, Model as UserModel},
 };
-use migration::{Expr, Func};
-use sea_orm::IntoSimpleExpr;
 use sea_orm::{
-    prelude::Decimal, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
-    QueryOrder, QuerySelect, Set, TransactionTrait, TryIntoModel,
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Select, Set,
+    SqlErr, TransactionTrait,
 };
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
+
+pub mod state_machine;
+
+use state_machine::{Actor, OrderStateMachine};
+
+//? Unique index allowing one order per quote
+const ORDER_QUOTE_INDEX: &str = "IDX_order_quote_jti";
+
+//? Quote redeemed by a concurrent request is caught by the unique index
+fn quote_used(cause: &DbErr) -> bool {
+    matches!(
+        cause.sql_err(),
+        Some(SqlErr::UniqueConstraintViolation(ref constraint))
+            if constraint.contains(ORDER_QUOTE_INDEX)
+    )
+}
 
 pub struct Service;
 
@@ -32,6 +66,22 @@ pub enum ServiceError {
     OrderAlreadyCanceled,
     #[error("Requisites were not found by id")]
     RequisitesNotFound,
+    #[error("This status transition can not be triggered by the actor")]
+    TransitionForbidden,
+    #[error("Order status was changed by someone else")]
+    StatusChanged,
+    #[error("Currency symbol was not found")]
+    SymbolNotFound,
+    #[error("Order amount is invalid")]
+    AmountInvalid(Vec<AmountViolation>),
+    #[error("User has too many open orders")]
+    TooManyOpenOrders { limit: u64, retry_after: u64 },
+    #[error("Quote was already used")]
+    QuoteUsed,
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
+    #[error(transparent)]
+    Permissions(#[from] PermissionsError),
 }
 
 impl From<ServiceError> for AppError {
@@ -42,6 +92,69 @@ impl From<ServiceError> for AppError {
             ServiceError::OrderAlreadySucceeded => AppError::OrderAlreadySucceeded,
             ServiceError::OrderAlreadyCanceled => AppError::OrderAlreadyCanceled,
             ServiceError::RequisitesNotFound => AppError::RequisitesWereNotFound,
+            ServiceError::TransitionForbidden => AppError::OrderTransitionForbidden,
+            ServiceError::StatusChanged => AppError::OrderStatusChanged,
+            ServiceError::SymbolNotFound => AppError::SymbolNotFound,
+            ServiceError::AmountInvalid(violations) => AppError::ValidationFailed(
+                violations
+                    .into_iter()
+                    .map(Into::<FieldError>::into)
+                    .collect(),
+            ),
+            ServiceError::TooManyOpenOrders { limit, retry_after } => {
+                AppError::TooManyOpenOrders { limit, retry_after }
+            }
+            ServiceError::QuoteUsed => AppError::QuoteUsed,
+            ServiceError::Pagination(cause) => cause.into(),
+            ServiceError::Permissions(cause) => cause.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountViolation {
+    NotPositive,
+    BelowMinimum(Decimal),
+    AboveMaximum(Decimal),
+    TooPrecise(u32),
+    DailyLimitExceeded { limit: Decimal, remaining: Decimal },
+}
+
+impl From<AmountViolation> for FieldError {
+    fn from(value: AmountViolation) -> Self {
+        let (code, message, limit) = match value {
+            AmountViolation::NotPositive => (
+                "not_positive",
+                String::from("Amount must be greater than zero"),
+                None,
+            ),
+            AmountViolation::BelowMinimum(minimum) => (
+                "below_minimum",
+                format!("Amount must be at least {}", minimum),
+                Some(minimum),
+            ),
+            AmountViolation::AboveMaximum(maximum) => (
+                "above_maximum",
+                format!("Amount must be at most {}", maximum),
+                Some(maximum),
+            ),
+            AmountViolation::TooPrecise(scale) => (
+                "too_precise",
+                format!("Amount must have at most {} digits after the point", scale),
+                Some(Decimal::from(scale)),
+            ),
+            AmountViolation::DailyLimitExceeded { limit, remaining } => (
+                "daily_limit_exceeded",
+                format!("Daily limit is {}, only {} is left", limit, remaining),
+                Some(remaining),
+            ),
+        };
+
+        Self {
+            field: String::from("amount"),
+            code: String::from(code),
+            message,
+            limit: limit.map(|limit| limit.to_string()),
         }
     }
 }
@@ -54,12 +167,158 @@ pub struct CreateOrderParameters {
     pub symbol: String,
     pub currency_rate: Decimal,
     pub requisites_id: i64,
+    //? Zero disables the cap
+    pub max_open_orders: u64,
+    //? Used to tell user when the oldest unpaid order expires
+    pub created_order_ttl_seconds: i64,
+    pub strategy: Arc<dyn AssignmentStrategy>,
+    //? Id of the redeemed quote, the unique index lets it buy only one order
+    pub quote_jti: Option<String>,
+}
+
+//? Date range is inclusive and applied to created_at
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub status: Option<Status>,
+    pub currency: Option<String>,
+    pub payment_method: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl OrderFilter {
+    pub fn apply(self, mut select: Select<OrderEntity>) -> Select<OrderEntity> {
+        if let Some(status) = self.status {
+            select = select.filter(OrderColumn::Status.eq(status));
+        }
+        if let Some(currency) = self.currency {
+            select = select.filter(OrderColumn::CurrencySymbol.eq(currency));
+        }
+        if let Some(payment_method) = self.payment_method {
+            select = select.filter(OrderColumn::PaymentMethod.eq(payment_method));
+        }
+        if let Some(from) = self.from {
+            select = select.filter(OrderColumn::CreatedAt.gte(from));
+        }
+        if let Some(to) = self.to {
+            select = select.filter(OrderColumn::CreatedAt.lte(to));
+        }
+        select
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum OrderSort {
+    #[default]
+    CreatedAt,
+    Amount,
+}
+
+impl OrderSort {
+    pub fn sort(&self) -> Sort<OrderEntity> {
+        match self {
+            OrderSort::CreatedAt => Sort {
+                name: "created_at",
+                column: OrderColumn::CreatedAt,
+                key: |order| SortKey::Timestamp(order.created_at),
+            },
+            OrderSort::Amount => Sort {
+                name: "amount",
+                column: OrderColumn::Amount,
+                key: |order| SortKey::Decimal(order.amount),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListOrdersParameters {
+    pub filter: OrderFilter,
+    pub sort: OrderSort,
+    pub page: PageRequest,
+}
+
+//? Everything an admin may search orders by, ranges are inclusive
+#[derive(Debug, Clone, Default)]
+pub struct OrderSearch {
+    pub filter: OrderFilter,
+    pub id: Option<i64>,
+    pub steam_id: Option<i64>,
+    pub moderator_id: Option<i64>,
+    pub requisites_id: Option<i64>,
+    pub amount_min: Option<Decimal>,
+    pub amount_max: Option<Decimal>,
+    pub finished_from: Option<NaiveDateTime>,
+    pub finished_to: Option<NaiveDateTime>,
+}
+
+impl OrderSearch {
+    pub fn apply(self, select: Select<OrderEntity>) -> Select<OrderEntity> {
+        let mut select = self.filter.apply(select);
+        if let Some(id) = self.id {
+            select = select.filter(OrderColumn::Id.eq(id));
+        }
+        if let Some(steam_id) = self.steam_id {
+            select = select.filter(OrderColumn::SteamId.eq(steam_id));
+        }
+        if let Some(moderator_id) = self.moderator_id {
+            select = select.filter(OrderColumn::ModeratorId.eq(moderator_id));
+        }
+        if let Some(requisites_id) = self.requisites_id {
+            select = select.filter(OrderColumn::RequisitesId.eq(requisites_id));
+        }
+        if let Some(amount_min) = self.amount_min {
+            select = select.filter(OrderColumn::Amount.gte(amount_min));
+        }
+        if let Some(amount_max) = self.amount_max {
+            select = select.filter(OrderColumn::Amount.lte(amount_max));
+        }
+        if let Some(finished_from) = self.finished_from {
+            select = select.filter(OrderColumn::FinishedAt.gte(finished_from));
+        }
+        if let Some(finished_to) = self.finished_to {
+            select = select.filter(OrderColumn::FinishedAt.lte(finished_to));
+        }
+        select
+    }
+}
+
+#[derive(Debug)]
+pub struct SearchOrdersParameters {
+    pub search: OrderSearch,
+    pub sort: OrderSort,
+    pub page: PageRequest,
+}
+
+//? Order with everything staff usually need to handle it
+#[derive(Debug)]
+pub struct OrderDetails {
+    pub order: OrderModel,
+    pub user: Option<UserModel>,
+    pub moderator: Option<AdminModel>,
+    pub requisites: Option<RequisitesModel>,
+    pub chat: Option<ChatModel>,
+    pub messages_count: u64,
 }
 
 #[derive(Debug)]
 pub struct CancelOrderParameters {
     pub steam_id: i64,
     pub order_id: i64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CancelOrderByIdParameters {
+    pub order_id: i64,
+    pub actor: Actor,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct FinishOrderParameters {
+    pub order_id: i64,
+    pub actor: Actor,
 }
 
 #[derive(Debug)]
@@ -73,6 +332,14 @@ pub struct GetUserOrderParameters {
     pub order_id: i64,
 }
 
+//? `changed` is false when the order has already been
+//? in the requested status and nothing was written
+#[derive(Debug)]
+pub struct StatusChange {
+    pub order: OrderModel,
+    pub changed: bool,
+}
+
 #[derive(Debug)]
 pub struct SetOrderRequisitesParameters {
     pub steam_id: i64,
@@ -91,18 +358,44 @@ impl Service {
     {
         let params: CreateOrderParameters = parameters.into();
 
-        let moderator = AdminEntity::find()
-            .filter(AdminColumn::Role.eq(Role::Moderator))
-            .left_join(OrderEntity)
-            .group_by(AdminColumn::Id)
-            .order_by_asc(Expr::value(Func::coalesce([
-                OrderColumn::Id.count(),
-                Expr::val(0).into_simple_expr(),
-            ])))
-            .limit(1)
+        let currency = match CurrencyRateEntity::find()
+            .filter(CurrencyRateColumn::Symbol.eq(&params.symbol))
             .one(connection)
             .await?
-            .map(|admin| admin.id);
+        {
+            Some(currency) => currency,
+            None => return Err(ServiceError::SymbolNotFound),
+        };
+
+        if params.max_open_orders > 0 {
+            Self::check_open_orders(
+                params.steam_id,
+                params.max_open_orders,
+                params.created_order_ttl_seconds,
+                connection,
+            )
+            .await?;
+        }
+
+        let violations =
+            Self::validate_amount(&currency, params.steam_id, params.amount, connection).await?;
+        if !violations.is_empty() {
+            return Err(ServiceError::AmountInvalid(violations));
+        }
+
+        //? Picked moderator may have been taken by a concurrent order, then the next one is picked
+        let mut candidates = AssignmentService::candidates(connection).await?;
+        let moderator = loop {
+            match params.strategy.pick(candidates.clone()).await {
+                Some(moderator_id) => {
+                    if AssignmentService::claim(moderator_id, connection).await? {
+                        break Some(moderator_id);
+                    }
+                    candidates.retain(|candidate| candidate.moderator_id != moderator_id);
+                }
+                None => break None,
+            }
+        };
 
         let requisites = match RequisitesEntity::find_by_id(params.requisites_id)
             .one(connection)
@@ -121,19 +414,147 @@ impl Service {
             fixed_currency_rate: Set(params.currency_rate),
             moderator_id: Set(moderator),
             requisites_id: Set(requisites.id),
+            quote_jti: Set(params.quote_jti),
             ..Default::default()
         };
 
-        Ok(OrderEntity::insert(order_to_be_inserted)
+        let created = match OrderEntity::insert(order_to_be_inserted)
             .exec_with_returning(connection)
-            .await?)
+            .await
+        {
+            Ok(created) => created,
+            Err(cause) if quote_used(&cause) => return Err(ServiceError::QuoteUsed),
+            Err(cause) => return Err(cause.into()),
+        };
+
+        OrderStateMachine::record(
+            created.id,
+            None,
+            Status::Created,
+            Actor::User(params.steam_id),
+            None,
+            connection,
+        )
+        .await?;
+
+        Ok(created)
+    }
+
+    //? Retry-After points to the moment the oldest unpaid order expires
+    //? User row stays locked until the order is inserted so parallel requests
+    //? can not all pass the check before any of them is counted
+    #[tracing::instrument(skip(connection))]
+    async fn check_open_orders<T>(
+        steam_id: i64,
+        limit: u64,
+        created_order_ttl_seconds: i64,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        UserEntity::find_by_id(steam_id)
+            .lock_exclusive()
+            .one(connection)
+            .await?;
+
+        let open = OrderEntity::find()
+            .filter(OrderColumn::SteamId.eq(steam_id))
+            .filter(OrderColumn::Status.is_in([Status::Created, Status::Maybepayed]))
+            .count(connection)
+            .await?;
+
+        if open < limit {
+            return Ok(());
+        }
+
+        let oldest_created = OrderEntity::find()
+            .filter(OrderColumn::SteamId.eq(steam_id))
+            .filter(OrderColumn::Status.eq(Status::Created))
+            .order_by_asc(OrderColumn::CreatedAt)
+            .one(connection)
+            .await?;
+
+        let retry_after = match oldest_created {
+            Some(order) => {
+                let expires_at = order.created_at + Duration::seconds(created_order_ttl_seconds);
+                (expires_at - Utc::now().naive_local()).num_seconds().max(1) as u64
+            }
+            None => created_order_ttl_seconds.max(1) as u64,
+        };
+
+        Err(ServiceError::TooManyOpenOrders { limit, retry_after })
+    }
+
+    //? Collects every violated limit so user can fix them all at once
+    //? Daily limit counts every not cancelled order of the last 24 hours
+    #[tracing::instrument(skip(connection))]
+    pub async fn validate_amount<T>(
+        currency: &CurrencyRateModel,
+        steam_id: i64,
+        amount: Decimal,
+        connection: &T,
+    ) -> Result<Vec<AmountViolation>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if amount <= Decimal::ZERO {
+            return Ok(vec![AmountViolation::NotPositive]);
+        }
+
+        let mut violations = vec![];
+
+        if let Some(scale) = currency.scale {
+            let scale = scale.max(0) as u32;
+            if amount.normalize().scale() > scale {
+                violations.push(AmountViolation::TooPrecise(scale));
+            }
+        }
+
+        if let Some(minimum) = currency.min_amount {
+            if amount < minimum {
+                violations.push(AmountViolation::BelowMinimum(minimum));
+            }
+        }
+
+        if let Some(maximum) = currency.max_amount {
+            if amount > maximum {
+                violations.push(AmountViolation::AboveMaximum(maximum));
+            }
+        }
+
+        if let Some(limit) = currency.daily_limit {
+            let since = Utc::now().naive_local() - Duration::days(1);
+
+            let spent = OrderEntity::find()
+                .select_only()
+                .column_as(OrderColumn::Amount.sum(), "spent")
+                .filter(OrderColumn::SteamId.eq(steam_id))
+                .filter(OrderColumn::CurrencySymbol.eq(&currency.symbol))
+                .filter(OrderColumn::Status.ne(Status::Cancelled))
+                .filter(OrderColumn::CreatedAt.gte(since))
+                .into_tuple::<Option<Decimal>>()
+                .one(connection)
+                .await?
+                .flatten()
+                .unwrap_or_default();
+
+            if spent + amount > limit {
+                violations.push(AmountViolation::DailyLimitExceeded {
+                    limit,
+                    remaining: (limit - spent).max(Decimal::ZERO),
+                });
+            }
+        }
+
+        Ok(violations)
     }
 
     #[tracing::instrument(skip(connection))]
     pub async fn cancel_order<T>(
         parameters: impl Into<CancelOrderParameters> + Debug,
         connection: &T,
-    ) -> Result<(), ServiceError>
+    ) -> Result<StatusChange, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
@@ -145,41 +566,49 @@ impl Service {
                     .eq(params.steam_id)
                     .and(OrderColumn::Id.eq(params.order_id)),
             )
+            .lock_exclusive()
             .one(connection)
             .await?
         {
-            Some(order) => match order.status {
-                Status::Succeeded => Err(ServiceError::OrderAlreadySucceeded),
-                Status::Cancelled => Ok(()), // Reducing database calls
-                _ => {
-                    let mut order_to_be_changed: OrderActiveModel = order.into();
-                    order_to_be_changed.status = Set(Status::Cancelled);
-                    order_to_be_changed.finished_at = Set(Some(Utc::now().naive_local()));
-                    order_to_be_changed.save(connection).await?;
-                    Ok(())
-                }
-            },
+            Some(order) => {
+                OrderStateMachine::transition(
+                    order,
+                    Status::Cancelled,
+                    Actor::User(params.steam_id),
+                    params.reason,
+                    connection,
+                )
+                .await
+            }
             None => Err(ServiceError::OrderNotFound),
         }
     }
 
     #[tracing::instrument(skip(connection))]
-    pub async fn cancel_order_by_id<T>(order_id: i64, connection: &T) -> Result<(), ServiceError>
+    pub async fn cancel_order_by_id<T>(
+        parameters: impl Into<CancelOrderByIdParameters> + Debug,
+        connection: &T,
+    ) -> Result<StatusChange, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        match OrderEntity::find_by_id(order_id).one(connection).await? {
-            Some(order) => match order.status {
-                Status::Succeeded => Err(ServiceError::OrderAlreadySucceeded),
-                Status::Cancelled => Ok(()),
-                _ => {
-                    let mut order_to_be_changed: OrderActiveModel = order.into();
-                    order_to_be_changed.status = Set(Status::Cancelled);
-                    order_to_be_changed.finished_at = Set(Some(Utc::now().naive_local()));
-                    order_to_be_changed.save(connection).await?;
-                    Ok(())
-                }
-            },
+        let params = parameters.into();
+
+        match OrderEntity::find_by_id(params.order_id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(order) => {
+                OrderStateMachine::transition(
+                    order,
+                    Status::Cancelled,
+                    params.actor,
+                    params.reason,
+                    connection,
+                )
+                .await
+            }
             None => Err(ServiceError::OrderNotFound),
         }
     }
@@ -187,15 +616,24 @@ impl Service {
     #[tracing::instrument(skip(connection))]
     pub async fn user_orders<T>(
         steam_id: i64,
+        parameters: ListOrdersParameters,
         connection: &T,
-    ) -> Result<Vec<OrderModel>, ServiceError>
+    ) -> Result<Page<OrderModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        Ok(OrderEntity::find()
-            .filter(OrderColumn::SteamId.eq(steam_id))
-            .all(connection)
-            .await?)
+        let select = parameters
+            .filter
+            .apply(OrderEntity::find().filter(OrderColumn::SteamId.eq(steam_id)));
+
+        Ok(PaginationService::paginate(
+            select,
+            OrderColumn::Id,
+            parameters.sort.sort(),
+            &parameters.page,
+            connection,
+        )
+        .await?)
     }
 
     #[tracing::instrument(skip(connection))]
@@ -219,33 +657,81 @@ impl Service {
 
     #[tracing::instrument(skip(connection))]
     pub async fn finish_order_by_id<T>(
-        order_id: i64,
+        parameters: impl Into<FinishOrderParameters> + Debug,
         connection: &T,
-    ) -> Result<OrderModel, ServiceError>
+    ) -> Result<StatusChange, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        match OrderEntity::find_by_id(order_id).one(connection).await? {
-            Some(order) => match order.status {
-                Status::Succeeded => Err(ServiceError::OrderAlreadySucceeded),
-                Status::Cancelled => Err(ServiceError::OrderAlreadyCanceled),
-                _ => {
-                    let mut order_to_be_changed: OrderActiveModel = order.into();
-                    order_to_be_changed.status = Set(Status::Succeeded);
-                    order_to_be_changed.finished_at = Set(Some(Utc::now().naive_local()));
-
-                    let updated = order_to_be_changed
-                        .save(connection)
-                        .await?
-                        .try_into_model()
-                        .unwrap(); // Trust
-                    Ok(updated)
-                }
-            },
+        let params = parameters.into();
+
+        match OrderEntity::find_by_id(params.order_id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(order) => {
+                OrderStateMachine::transition(
+                    order,
+                    Status::Succeeded,
+                    params.actor,
+                    None,
+                    connection,
+                )
+                .await
+            }
             None => Err(ServiceError::OrderNotFound),
         }
     }
 
+    #[tracing::instrument(skip(connection))]
+    pub async fn search<T>(
+        parameters: SearchOrdersParameters,
+        connection: &T,
+    ) -> Result<Page<OrderModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(PaginationService::paginate(
+            parameters.search.apply(OrderEntity::find()),
+            OrderColumn::Id,
+            parameters.sort.sort(),
+            &parameters.page,
+            connection,
+        )
+        .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn details<T>(order_id: i64, connection: &T) -> Result<OrderDetails, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let order = OrderEntity::find_by_id(order_id)
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::OrderNotFound)?;
+
+        let user = order.find_related(UserEntity).one(connection).await?;
+        let moderator = order.find_related(AdminEntity).one(connection).await?;
+        let requisites = order.find_related(RequisitesEntity).one(connection).await?;
+        let chat = order.find_related(ChatEntity).one(connection).await?;
+
+        let messages_count = match &chat {
+            Some(chat) => chat.find_related(MessageEntity).count(connection).await?,
+            None => 0,
+        };
+
+        Ok(OrderDetails {
+            order,
+            user,
+            moderator,
+            requisites,
+            chat,
+            messages_count,
+        })
+    }
+
     pub async fn all_in_period<T>(
         period: (chrono::NaiveDateTime, chrono::NaiveDateTime),
         connection: &T,
@@ -264,7 +750,7 @@ impl Service {
     pub async fn maybepayed<T>(
         parameters: impl Into<MayBePayedOrderParameters> + Debug,
         connection: &T,
-    ) -> Result<OrderModel, ServiceError>
+    ) -> Result<StatusChange, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
@@ -276,25 +762,135 @@ impl Service {
                     .eq(params.steam_id)
                     .and(OrderColumn::Id.eq(params.order_id)),
             )
+            .lock_exclusive()
             .one(connection)
             .await?
         {
-            Some(order) => match order.status {
-                Status::Succeeded => Err(ServiceError::OrderAlreadySucceeded),
-                Status::Cancelled => Err(ServiceError::OrderAlreadyCanceled),
-                Status::Maybepayed => Ok(order),
-                _ => {
-                    let mut order_to_be_changed: OrderActiveModel = order.into();
-                    order_to_be_changed.status = Set(Status::Maybepayed);
-
-                    let updated = order_to_be_changed
-                        .save(connection)
-                        .await?
-                        .try_into_model()
-                        .unwrap(); // Trust
-                    Ok(updated)
-                }
-            },
+            Some(order) => {
+                OrderStateMachine::transition(
+                    order,
+                    Status::Maybepayed,
+                    Actor::User(params.steam_id),
+                    None,
+                    connection,
+                )
+                .await
+            }
+            None => Err(ServiceError::OrderNotFound),
+        }
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn history<T>(
+        order_id: i64,
+        connection: &T,
+    ) -> Result<Vec<HistoryModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(HistoryEntity::find()
+            .filter(HistoryColumn::OrderId.eq(order_id))
+            .order_by_asc(HistoryColumn::CreatedAt)
+            .order_by_asc(HistoryColumn::Id)
+            .all(connection)
+            .await?)
+    }
+
+    //? Orders which are still created after the threshold
+    #[tracing::instrument(skip(connection))]
+    pub async fn expired_orders<T>(
+        created_before: NaiveDateTime,
+        connection: &T,
+    ) -> Result<Vec<OrderModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(OrderEntity::find()
+            .filter(OrderColumn::Status.eq(Status::Created))
+            .filter(OrderColumn::CreatedAt.lt(created_before))
+            .all(connection)
+            .await?)
+    }
+
+    //? Maybepayed orders that have not been flagged yet
+    //? and were marked as payed before the threshold
+    #[tracing::instrument(skip(connection))]
+    pub async fn stale_orders<T>(
+        maybepayed_before: NaiveDateTime,
+        connection: &T,
+    ) -> Result<Vec<OrderModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let recently_payed = HistoryEntity::find()
+            .select_only()
+            .column(HistoryColumn::OrderId)
+            .filter(HistoryColumn::ToStatus.eq(Status::Maybepayed))
+            .filter(HistoryColumn::CreatedAt.gte(maybepayed_before))
+            .into_query();
+
+        Ok(OrderEntity::find()
+            .filter(OrderColumn::Status.eq(Status::Maybepayed))
+            .filter(OrderColumn::FlaggedAt.is_null())
+            .filter(OrderColumn::CreatedAt.lt(maybepayed_before))
+            .filter(OrderColumn::Id.not_in_subquery(recently_payed))
+            .all(connection)
+            .await?)
+    }
+
+    //? Cancels the order only if it is still created
+    //? Row is locked so a concurrent payment can not slip in
+    #[tracing::instrument(skip(connection))]
+    pub async fn expire_order<T>(
+        order_id: i64,
+        connection: &T,
+    ) -> Result<StatusChange, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        match OrderEntity::find_by_id(order_id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(order) if order.status == Status::Created => {
+                OrderStateMachine::transition(
+                    order,
+                    Status::Cancelled,
+                    Actor::System,
+                    Some(String::from("Order has expired")),
+                    connection,
+                )
+                .await
+            }
+            Some(order) => Ok(StatusChange {
+                order,
+                changed: false,
+            }),
+            None => Err(ServiceError::OrderNotFound),
+        }
+    }
+
+    //? Returns None if the order is not maybepayed anymore or was already flagged
+    #[tracing::instrument(skip(connection))]
+    pub async fn flag_order<T>(
+        order_id: i64,
+        connection: &T,
+    ) -> Result<Option<OrderModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        match OrderEntity::find_by_id(order_id)
+            .lock_exclusive()
+            .one(connection)
+            .await?
+        {
+            Some(order) if order.status == Status::Maybepayed && order.flagged_at.is_none() => {
+                let mut order_to_be_changed: OrderActiveModel = order.into();
+                order_to_be_changed.flagged_at = Set(Some(Utc::now().naive_local()));
+                Ok(Some(order_to_be_changed.update(connection).await?))
+            }
+            Some(_) => Ok(None),
             None => Err(ServiceError::OrderNotFound),
         }
     }
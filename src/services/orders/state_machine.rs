@@ -0,0 +1,190 @@
+use chrono::Utc;
+use entity::{
+    admin::Model as AdminModel,
+    order::{
+        ActiveModel as OrderActiveModel, Column as OrderColumn, Entity as OrderEntity,
+        Model as OrderModel,
+    },
+    order_status_history::{
+        ActiveModel as HistoryActiveModel, Entity as HistoryEntity, Model as HistoryModel,
+    },
+    sea_orm_active_enums::{Actor as ActorKind, Permission, Status},
+};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+
+use crate::services::admin::permissions::Service as PermissionsService;
+
+use super::{ServiceError, StatusChange};
+
+//? Who triggers a transition
+//? Users are identified by steam id, staff by admin id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    User(i64),
+    Moderator(i64),
+    Admin(i64),
+    System,
+}
+
+impl Actor {
+    pub fn kind(&self) -> ActorKind {
+        match self {
+            Actor::User(_) => ActorKind::User,
+            Actor::Moderator(_) => ActorKind::Moderator,
+            Actor::Admin(_) => ActorKind::Admin,
+            Actor::System => ActorKind::System,
+        }
+    }
+
+    pub fn id(&self) -> Option<i64> {
+        match self {
+            Actor::User(id) | Actor::Moderator(id) | Actor::Admin(id) => Some(*id),
+            Actor::System => None,
+        }
+    }
+}
+
+impl Actor {
+    //? Power over orders comes from permissions whatever the role is
+    //? Handling orders allows acting on assigned orders, managing staff on top of it on any order
+    pub async fn staff<T>(staff: &AdminModel, connection: &T) -> Result<Self, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let permissions = PermissionsService::effective(staff, connection).await?;
+
+        match (
+            permissions.contains(&Permission::HandleOrders),
+            permissions.contains(&Permission::ManageStaff),
+        ) {
+            (true, true) => Ok(Actor::Admin(staff.id)),
+            (true, false) => Ok(Actor::Moderator(staff.id)),
+            (false, _) => Err(ServiceError::TransitionForbidden),
+        }
+    }
+}
+
+//? Transitions table
+//?
+//? created    -> maybepayed  : owner
+//? created    -> cancelled   : owner, assigned moderator, admin, system
+//? maybepayed -> cancelled   : owner, assigned moderator, admin, system
+//? created    -> succeeded   : assigned moderator, admin
+//? maybepayed -> succeeded   : assigned moderator, admin
+//?
+//? Admin is whoever handles orders and manages staff, see `Actor::staff`
+//?
+//? Requesting the status the order already has is a no-op
+//? for anyone who could have made that transition
+pub struct OrderStateMachine;
+
+impl OrderStateMachine {
+    fn is_owner(order: &OrderModel, actor: &Actor) -> bool {
+        matches!(actor, Actor::User(steam_id) if *steam_id == order.steam_id)
+    }
+
+    fn is_assigned_moderator(order: &OrderModel, actor: &Actor) -> bool {
+        matches!(actor, Actor::Moderator(id) if Some(*id) == order.moderator_id)
+    }
+
+    fn may_trigger(order: &OrderModel, to: &Status, actor: &Actor) -> bool {
+        match to {
+            Status::Created => false,
+            Status::Maybepayed => Self::is_owner(order, actor),
+            Status::Cancelled => {
+                Self::is_owner(order, actor)
+                    || Self::is_assigned_moderator(order, actor)
+                    || matches!(actor, Actor::Admin(_) | Actor::System)
+            }
+            Status::Succeeded => {
+                Self::is_assigned_moderator(order, actor) || matches!(actor, Actor::Admin(_))
+            }
+        }
+    }
+
+    //? Returns false when the order is already in the requested status
+    pub fn check(order: &OrderModel, to: &Status, actor: &Actor) -> Result<bool, ServiceError> {
+        if !Self::may_trigger(order, to, actor) {
+            return Err(ServiceError::TransitionForbidden);
+        }
+
+        match (&order.status, to) {
+            (from, to) if from == to => Ok(false),
+            (Status::Succeeded, _) => Err(ServiceError::OrderAlreadySucceeded),
+            (Status::Cancelled, _) => Err(ServiceError::OrderAlreadyCanceled),
+            _ => Ok(true),
+        }
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn transition<T>(
+        order: OrderModel,
+        to: Status,
+        actor: Actor,
+        reason: Option<String>,
+        connection: &T,
+    ) -> Result<StatusChange, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        if !Self::check(&order, &to, &actor)? {
+            return Ok(StatusChange {
+                order,
+                changed: false,
+            });
+        }
+
+        //? Callers must pass the order row locked FOR UPDATE in the same transaction,
+        //? the status filter only guards against a caller that did not
+        let (order_id, from) = (order.id, order.status.clone());
+        let mut order_to_be_changed: OrderActiveModel = order.into();
+        order_to_be_changed.status = Set(to.clone());
+
+        if matches!(to, Status::Succeeded | Status::Cancelled) {
+            order_to_be_changed.finished_at = Set(Some(Utc::now().naive_local()));
+        }
+
+        let updated = OrderEntity::update_many()
+            .set(order_to_be_changed)
+            .filter(OrderColumn::Id.eq(order_id))
+            .filter(OrderColumn::Status.eq(from.clone()))
+            .exec_with_returning(connection)
+            .await?
+            .pop()
+            .ok_or(ServiceError::StatusChanged)?;
+
+        Self::record(updated.id, Some(from), to, actor, reason, connection).await?;
+
+        Ok(StatusChange {
+            order: updated,
+            changed: true,
+        })
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn record<T>(
+        order_id: i64,
+        from: Option<Status>,
+        to: Status,
+        actor: Actor,
+        reason: Option<String>,
+        connection: &T,
+    ) -> Result<HistoryModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let record_to_be_inserted = HistoryActiveModel {
+            order_id: Set(order_id),
+            from_status: Set(from),
+            to_status: Set(to),
+            actor: Set(actor.kind()),
+            actor_id: Set(actor.id()),
+            reason: Set(reason),
+            ..Default::default()
+        };
+
+        Ok(HistoryEntity::insert(record_to_be_inserted)
+            .exec_with_returning(connection)
+            .await?)
+    }
+}
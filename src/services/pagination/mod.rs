@@ -0,0 +1,326 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDateTime;
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, ColumnType, Condition, ConnectionTrait, EntityTrait, ModelTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select, Value,
+};
+
+use crate::errors::AppError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error("Cursor is malformed")]
+    CursorMalformed,
+    #[error("Cursor was issued for another sorting")]
+    CursorMismatch,
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::CursorMalformed | ServiceError::CursorMismatch => AppError::InvalidCursor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Asc,
+    #[default]
+    Desc,
+}
+
+//? Value of the sort column of the last item on the previous page
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum SortKey {
+    Integer(i64),
+    Decimal(Decimal),
+    Timestamp(NaiveDateTime),
+}
+
+impl SortKey {
+    //? Key is compared with the sort column so it must be of the same type
+    pub fn fits(&self, column_type: &ColumnType) -> bool {
+        matches!(
+            (self, column_type),
+            (
+                SortKey::Integer(_),
+                ColumnType::TinyInteger
+                    | ColumnType::SmallInteger
+                    | ColumnType::Integer
+                    | ColumnType::BigInteger
+            ) | (
+                SortKey::Decimal(_),
+                ColumnType::Decimal(_) | ColumnType::Money(_)
+            ) | (
+                SortKey::Timestamp(_),
+                ColumnType::DateTime | ColumnType::Timestamp
+            )
+        )
+    }
+}
+
+impl From<SortKey> for Value {
+    fn from(value: SortKey) -> Self {
+        match value {
+            SortKey::Integer(key) => key.into(),
+            SortKey::Decimal(key) => key.into(),
+            SortKey::Timestamp(key) => key.into(),
+        }
+    }
+}
+
+//? Cursor is opaque for clients, it is just base64 encoded json
+//? Id breaks ties between items with equal sort keys
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Cursor {
+    pub sort: String,
+    pub direction: Direction,
+    pub key: SortKey,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, ServiceError> {
+        URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(ServiceError::CursorMalformed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub cursor: Option<Cursor>,
+    pub limit: u64,
+    pub direction: Direction,
+    //? False when the direction is the default one
+    pub direction_requested: bool,
+}
+
+impl PageRequest {
+    //? Queues are worked through from the oldest item unless asked otherwise
+    pub fn oldest_first(mut self) -> Self {
+        if !self.direction_requested {
+            self.direction = Direction::Asc;
+        }
+        self
+    }
+
+    //? Cursor must come from the same listing with the same sorting
+    //? and hold a key of the sort column type
+    pub fn after(
+        &self,
+        sort: &str,
+        column_type: &ColumnType,
+    ) -> Result<Option<&Cursor>, ServiceError> {
+        match &self.cursor {
+            Some(cursor)
+                if cursor.sort != sort
+                    || cursor.direction != self.direction
+                    || !cursor.key.fits(column_type) =>
+            {
+                Err(ServiceError::CursorMismatch)
+            }
+            cursor => Ok(cursor.as_ref()),
+        }
+    }
+
+    //? Condition selecting everything after the cursor
+    pub fn condition<C: ColumnTrait, I: ColumnTrait>(
+        &self,
+        cursor: &Cursor,
+        column: C,
+        id: I,
+    ) -> Condition {
+        let key = Value::from(cursor.key.clone());
+        match self.direction {
+            Direction::Asc => Condition::any()
+                .add(column.gt(key.clone()))
+                .add(Condition::all().add(column.eq(key)).add(id.gt(cursor.id))),
+            Direction::Desc => Condition::any()
+                .add(column.lt(key.clone()))
+                .add(Condition::all().add(column.eq(key)).add(id.lt(cursor.id))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+    //? Amount of items matching filters on all pages
+    pub total: u64,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+//? Column a listing can be sorted by and how to read it from a model
+pub struct Sort<E: EntityTrait> {
+    pub name: &'static str,
+    pub column: E::Column,
+    pub key: fn(&E::Model) -> SortKey,
+}
+
+pub struct Service;
+
+impl Service {
+    //? Keyset pagination, one extra item is fetched to know
+    //? whether there is a next page at all
+    pub async fn paginate<E, T>(
+        select: Select<E>,
+        id: E::Column,
+        sort: Sort<E>,
+        request: &PageRequest,
+        connection: &T,
+    ) -> Result<Page<E::Model>, ServiceError>
+    where
+        E: EntityTrait,
+        E::Model: Sync,
+        T: ConnectionTrait,
+    {
+        let total = select.clone().count(connection).await?;
+
+        let mut select = select;
+        if let Some(cursor) = request.after(sort.name, sort.column.def().get_column_type())? {
+            select = select.filter(request.condition(cursor, sort.column, id));
+        }
+
+        select = match request.direction {
+            Direction::Asc => select.order_by_asc(sort.column).order_by_asc(id),
+            Direction::Desc => select.order_by_desc(sort.column).order_by_desc(id),
+        };
+
+        let mut items = select.limit(request.limit + 1).all(connection).await?;
+
+        let next_cursor = if items.len() as u64 > request.limit {
+            items.truncate(request.limit as usize);
+            items.last().and_then(|last| match last.get(id) {
+                Value::BigInt(Some(last_id)) => Some(Cursor {
+                    sort: String::from(sort.name),
+                    direction: request.direction,
+                    key: (sort.key)(last),
+                    id: last_id,
+                }),
+                _ => None,
+            })
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items,
+            next_cursor,
+            total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(key: SortKey) -> Cursor {
+        Cursor {
+            sort: String::from("created_at"),
+            direction: Direction::Desc,
+            key,
+            id: 42,
+        }
+    }
+
+    fn request(cursor: Cursor) -> PageRequest {
+        PageRequest {
+            cursor: Some(cursor),
+            limit: 20,
+            direction: Direction::Desc,
+            direction_requested: false,
+        }
+    }
+
+    fn timestamp() -> SortKey {
+        SortKey::Timestamp(
+            NaiveDateTime::parse_from_str("2024-03-01 12:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        )
+    }
+
+    #[test]
+    fn cursor_survives_encoding() {
+        for key in [
+            SortKey::Integer(-7),
+            SortKey::Decimal("1234.5678".parse().unwrap()),
+            timestamp(),
+        ] {
+            let cursor = cursor(key);
+
+            assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        }
+    }
+
+    #[test]
+    fn malformed_cursor_is_rejected() {
+        let json = URL_SAFE_NO_PAD.encode(br#"{"sort":"created_at"}"#);
+
+        for token in ["", "not base64!", json.as_str()] {
+            assert!(matches!(
+                Cursor::decode(token),
+                Err(ServiceError::CursorMalformed)
+            ));
+        }
+    }
+
+    #[test]
+    fn cursor_of_the_same_sorting_is_accepted() {
+        let request = request(cursor(timestamp()));
+
+        let after = request.after("created_at", &ColumnType::DateTime).unwrap();
+
+        assert_eq!(after, request.cursor.as_ref());
+    }
+
+    #[test]
+    fn cursor_of_another_sorting_is_rejected() {
+        let mut request = request(cursor(timestamp()));
+        assert!(matches!(
+            request.after("amount", &ColumnType::DateTime),
+            Err(ServiceError::CursorMismatch)
+        ));
+
+        request.direction = Direction::Asc;
+        assert!(matches!(
+            request.after("created_at", &ColumnType::DateTime),
+            Err(ServiceError::CursorMismatch)
+        ));
+    }
+
+    #[test]
+    fn cursor_with_key_of_another_type_is_rejected() {
+        for (key, column_type) in [
+            (SortKey::Integer(1), ColumnType::DateTime),
+            (SortKey::Decimal(Decimal::ONE), ColumnType::BigInteger),
+            (timestamp(), ColumnType::Decimal(None)),
+        ] {
+            assert!(matches!(
+                request(cursor(key)).after("created_at", &column_type),
+                Err(ServiceError::CursorMismatch)
+            ));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::errors::AppError;
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+use jsonwebtoken::{errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
+use sea_orm::prelude::Decimal;
+use std::fmt::Debug;
+
+pub type QuoteId = String;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error("Quote has expired")]
+    Expired,
+    #[error("Quote is invalid")]
+    Invalid,
+    #[error(transparent)]
+    JWTError(#[from] jsonwebtoken::errors::Error),
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::Expired => AppError::QuoteExpired,
+            ServiceError::Invalid => AppError::QuoteInvalid,
+            ServiceError::JWTError(cause) => AppError::InternalServerError(Box::new(cause)),
+        }
+    }
+}
+
+//? Quote is a jwt so we do not need to store it anywhere
+//? It is bound to the user, symbol and amount it was issued for
+//? Used quotes are recorded on the order they bought, see `quote_jti`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct QuoteClaims {
+    pub jti: String,
+    pub sub: i64,
+    pub symbol: String,
+    pub amount: Decimal,
+    pub rate: Decimal,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl QuoteClaims {
+    pub fn expires_at(&self) -> NaiveDateTime {
+        Utc.timestamp_opt(self.exp as i64, 0)
+            .single()
+            .map(|expires_at| expires_at.naive_utc())
+            .unwrap_or_default()
+    }
+}
+
+pub struct IssueQuoteParameters<'a> {
+    pub steam_id: i64,
+    pub symbol: String,
+    pub amount: Decimal,
+    pub rate: Decimal,
+    pub secret: &'a str,
+    pub ttl: i64,
+}
+
+pub struct RedeemQuoteParameters<'a> {
+    pub quote_id: QuoteId,
+    pub steam_id: i64,
+    pub symbol: &'a str,
+    pub amount: Decimal,
+    pub secret: &'a str,
+}
+
+pub struct Service;
+
+impl Service {
+    //? Quotes are signed with a key derived from jwt secret even with asymmetric
+    //? auth tokens, so a quote can never be accepted as an auth token
+    fn key(secret: &str) -> Vec<u8> {
+        [secret.as_bytes(), b":quote"].concat()
+    }
+
+    #[tracing::instrument(skip(parameters))]
+    pub fn issue(
+        parameters: IssueQuoteParameters<'_>,
+    ) -> Result<(QuoteId, QuoteClaims), ServiceError> {
+        let now = Utc::now();
+
+        let claims = QuoteClaims {
+            jti: uuid::Uuid::new_v4().simple().to_string(),
+            sub: parameters.steam_id,
+            symbol: parameters.symbol,
+            amount: parameters.amount,
+            rate: parameters.rate,
+            iat: now.timestamp() as u64,
+            exp: (now + Duration::seconds(parameters.ttl)).timestamp() as u64,
+        };
+
+        let quote_id = jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&Self::key(parameters.secret)),
+        )?;
+
+        Ok((quote_id, claims))
+    }
+
+    #[tracing::instrument(skip(parameters))]
+    pub fn redeem(parameters: RedeemQuoteParameters<'_>) -> Result<QuoteClaims, ServiceError> {
+        let mut validation = Validation::default();
+        validation.leeway = 0; // Quotes are short-lived
+
+        let claims = match jsonwebtoken::decode::<QuoteClaims>(
+            &parameters.quote_id,
+            &DecodingKey::from_secret(&Self::key(parameters.secret)),
+            &validation,
+        ) {
+            Ok(decoded) => decoded.claims,
+            Err(cause) if matches!(cause.kind(), ErrorKind::ExpiredSignature) => {
+                return Err(ServiceError::Expired)
+            }
+            Err(_) => return Err(ServiceError::Invalid),
+        };
+
+        if claims.sub != parameters.steam_id
+            || claims.symbol != parameters.symbol
+            || claims.amount != parameters.amount
+        {
+            return Err(ServiceError::Invalid);
+        }
+
+        Ok(claims)
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::Utc;
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    //? Zero disables the limit
+    pub requests: u64,
+    pub window_seconds: u64,
+}
+
+#[derive(Debug)]
+pub struct HitParameters<'a> {
+    pub group: &'a str,
+    pub subject: String,
+    pub limit: RateLimit,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Hit {
+    Allowed,
+    Limited { retry_after: u64 },
+}
+
+pub struct Service;
+
+impl Service {
+    //? Fixed window counter. Every window has its own key
+    //? which expires together with the window
+    #[tracing::instrument(skip(redis_client))]
+    pub async fn hit(
+        parameters: HitParameters<'_>,
+        redis_client: &redis::Client,
+    ) -> Result<Hit, redis::RedisError> {
+        let limit = parameters.limit;
+        if limit.requests == 0 || limit.window_seconds == 0 {
+            return Ok(Hit::Allowed);
+        }
+
+        let now = Utc::now().timestamp().max(0) as u64;
+        let window = now / limit.window_seconds;
+        let key = format!(
+            "rate-limit:{}:{}:{}",
+            parameters.group, parameters.subject, window
+        );
+
+        let mut connection = redis_client.get_async_connection().await?;
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire(&key, limit.window_seconds as i64)
+            .ignore()
+            .query_async(&mut connection)
+            .await?;
+
+        if count > limit.requests {
+            let retry_after = (window + 1) * limit.window_seconds - now;
+            return Ok(Hit::Limited { retry_after });
+        }
+
+        Ok(Hit::Allowed)
+    }
+}
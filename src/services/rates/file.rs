@@ -0,0 +1,30 @@
+use axum::async_trait;
+use std::path::PathBuf;
+
+use super::{parse, ProviderError, RateProvider, Rates};
+
+//? Useful for tests and offline deployments
+//? File is reread on every fetch so it can be edited in place
+#[derive(Debug)]
+pub struct FileRateProvider {
+    path: PathBuf,
+}
+
+impl FileRateProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl RateProvider for FileRateProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    #[tracing::instrument]
+    async fn fetch(&self) -> Result<Rates, ProviderError> {
+        let contents = tokio::fs::read_to_string(&self.path).await?;
+        parse(&contents)
+    }
+}
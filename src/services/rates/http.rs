@@ -0,0 +1,43 @@
+use axum::async_trait;
+use std::time::Duration;
+use url::Url;
+
+use super::{parse, ProviderError, RateProvider, Rates};
+
+#[derive(Debug)]
+pub struct HttpRateProvider {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl HttpRateProvider {
+    pub fn new(url: Url) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self { url, client }
+    }
+}
+
+#[async_trait]
+impl RateProvider for HttpRateProvider {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    #[tracing::instrument]
+    async fn fetch(&self) -> Result<Rates, ProviderError> {
+        let contents = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        parse(&contents)
+    }
+}
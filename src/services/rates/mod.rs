@@ -0,0 +1,35 @@
+use axum::async_trait;
+use sea_orm::prelude::Decimal;
+use std::collections::HashMap;
+
+pub mod file;
+pub mod http;
+
+pub use file::FileRateProvider;
+pub use http::HttpRateProvider;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProviderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_json::Error),
+}
+
+//? Rates are raw values for one unit of a symbol
+//? Markup and approval rules are applied by currency service
+pub type Rates = HashMap<String, Decimal>;
+
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn fetch(&self) -> Result<Rates, ProviderError>;
+}
+
+//? Both providers read a flat json object like {"USD": "91.5", "EUR": 99.1}
+fn parse(contents: &str) -> Result<Rates, ProviderError> {
+    Ok(serde_json::from_str::<Rates>(contents)?)
+}
@@ -0,0 +1,201 @@
+use chrono::NaiveDateTime;
+use entity::{
+    admin::Column as AdminColumn,
+    order::{Column as OrderColumn, Entity as OrderEntity, Relation as OrderRelation},
+    sea_orm_active_enums::Status,
+};
+use migration::Expr;
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, JoinType,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait, Select, TransactionTrait,
+};
+
+use crate::errors::AppError;
+
+//? Volume converted through the rate fixed at creation time
+const CONVERTED_VOLUME: &str = r#"sum("order".amount * "order".fixed_currency_rate)"#;
+const AVERAGE_COMPLETION: &str =
+    r#"round(avg(extract(epoch from "order".finished_at - "order".created_at)))::bigint"#;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Bucket {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    //? Postgres weeks start on monday
+    fn truncate(&self) -> String {
+        let field = match self {
+            Bucket::Day => "day",
+            Bucket::Week => "week",
+            Bucket::Month => "month",
+        };
+        format!(r#"date_trunc('{}', "order".finished_at)"#, field)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReportParameters {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub bucket: Bucket,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct CurrencyVolume {
+    pub currency_symbol: String,
+    pub orders: i64,
+    pub volume: Decimal,
+    pub converted_volume: Decimal,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct BucketVolume {
+    pub bucket: NaiveDateTime,
+    pub currency_symbol: String,
+    pub orders: i64,
+    pub volume: Decimal,
+    pub converted_volume: Decimal,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct ModeratorVolume {
+    //? None stands for orders finished without a moderator
+    pub moderator_id: Option<i64>,
+    pub login: Option<String>,
+    pub orders: i64,
+    pub converted_volume: Decimal,
+    pub average_completion_seconds: Option<i64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct PaymentMethodVolume {
+    pub payment_method: String,
+    pub orders: i64,
+    pub converted_volume: Decimal,
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub currencies: Vec<CurrencyVolume>,
+    pub buckets: Vec<BucketVolume>,
+    pub moderators: Vec<ModeratorVolume>,
+    pub payment_methods: Vec<PaymentMethodVolume>,
+    //? Orders created in the range whatever their status is
+    pub statuses: Vec<(Status, i64)>,
+    pub average_completion_seconds: Option<i64>,
+}
+
+pub struct Service;
+
+impl Service {
+    //? Succeeded orders are attributed to the moment they were finished
+    fn succeeded(parameters: &ReportParameters) -> Select<OrderEntity> {
+        OrderEntity::find()
+            .filter(OrderColumn::Status.eq(Status::Succeeded))
+            .filter(OrderColumn::FinishedAt.between(parameters.from, parameters.to))
+            .select_only()
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn report<T>(
+        parameters: ReportParameters,
+        connection: &T,
+    ) -> Result<Report, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let currencies = Self::succeeded(&parameters)
+            .column(OrderColumn::CurrencySymbol)
+            .column_as(OrderColumn::Id.count(), "orders")
+            .column_as(OrderColumn::Amount.sum(), "volume")
+            .column_as(Expr::cust(CONVERTED_VOLUME), "converted_volume")
+            .group_by(OrderColumn::CurrencySymbol)
+            .order_by_asc(OrderColumn::CurrencySymbol)
+            .into_model::<CurrencyVolume>()
+            .all(connection)
+            .await?;
+
+        let bucket = parameters.bucket.truncate();
+        let buckets = Self::succeeded(&parameters)
+            .column_as(Expr::cust(bucket.as_str()), "bucket")
+            .column(OrderColumn::CurrencySymbol)
+            .column_as(OrderColumn::Id.count(), "orders")
+            .column_as(OrderColumn::Amount.sum(), "volume")
+            .column_as(Expr::cust(CONVERTED_VOLUME), "converted_volume")
+            .group_by(Expr::cust(bucket.as_str()))
+            .group_by(OrderColumn::CurrencySymbol)
+            .order_by_asc(Expr::cust(bucket.as_str()))
+            .order_by_asc(OrderColumn::CurrencySymbol)
+            .into_model::<BucketVolume>()
+            .all(connection)
+            .await?;
+
+        let moderators = Self::succeeded(&parameters)
+            .column(OrderColumn::ModeratorId)
+            .column(AdminColumn::Login)
+            .column_as(OrderColumn::Id.count(), "orders")
+            .column_as(Expr::cust(CONVERTED_VOLUME), "converted_volume")
+            .column_as(Expr::cust(AVERAGE_COMPLETION), "average_completion_seconds")
+            .join(JoinType::LeftJoin, OrderRelation::Admin.def())
+            .group_by(OrderColumn::ModeratorId)
+            .group_by(AdminColumn::Login)
+            .order_by_asc(OrderColumn::ModeratorId)
+            .into_model::<ModeratorVolume>()
+            .all(connection)
+            .await?;
+
+        let payment_methods = Self::succeeded(&parameters)
+            .column(OrderColumn::PaymentMethod)
+            .column_as(OrderColumn::Id.count(), "orders")
+            .column_as(Expr::cust(CONVERTED_VOLUME), "converted_volume")
+            .group_by(OrderColumn::PaymentMethod)
+            .order_by_asc(OrderColumn::PaymentMethod)
+            .into_model::<PaymentMethodVolume>()
+            .all(connection)
+            .await?;
+
+        let average_completion_seconds = Self::succeeded(&parameters)
+            .column_as(Expr::cust(AVERAGE_COMPLETION), "average_completion_seconds")
+            .into_tuple::<Option<i64>>()
+            .one(connection)
+            .await?
+            .flatten();
+
+        let statuses = OrderEntity::find()
+            .filter(OrderColumn::CreatedAt.between(parameters.from, parameters.to))
+            .select_only()
+            .column(OrderColumn::Status)
+            .column_as(OrderColumn::Id.count(), "orders")
+            .group_by(OrderColumn::Status)
+            .into_tuple::<(Status, i64)>()
+            .all(connection)
+            .await?;
+
+        Ok(Report {
+            currencies,
+            buckets,
+            moderators,
+            payment_methods,
+            statuses,
+            average_completion_seconds,
+        })
+    }
+}
@@ -1,18 +1,39 @@
-use crate::errors::{AppError, Details};
+use crate::{
+    errors::{AppError, Details},
+    services::{
+        admin::blacklist::Service as BlacklistService,
+        pagination::{
+            Page, PageRequest, Service as PaginationService, ServiceError as PaginationError, Sort,
+            SortKey,
+        },
+    },
+};
+use chrono::{Datelike, Duration, Months, NaiveDateTime, Utc};
 use entity::{
+    order::Entity as OrderEntity,
     review::{
         ActiveModel as ReviewActiveModel, Column as ReviewColumn, Entity as ReviewEntity,
         Model as ReviewModel,
     },
+    sea_orm_active_enums::{ReviewStatus, Status},
     user::Entity as UserEntity,
     video_review::{
         ActiveModel as VideoReviewActiveModel, Column as VideoReviewColumn,
         Entity as VideoReviewEntity, Model as VideoReviewModel,
     },
 };
-use migration::Query;
-use sea_orm::{prelude::*, Condition, QueryOrder, QuerySelect, Set, TransactionTrait};
-use std::fmt::Debug;
+use migration::Expr;
+use sea_orm::{
+    prelude::*, Condition, FromQueryResult, QueryOrder, QuerySelect, Select, Set, SqlErr,
+    TransactionTrait,
+};
+use std::{collections::HashMap, fmt::Debug};
+
+//? Unique index allowing one review per order
+const REVIEW_ORDER_INDEX: &str = "IDX_review_order_id";
+
+//? Every point of the rolling series covers this many days
+const ROLLING_WINDOW_DAYS: i64 = 30;
 
 #[allow(dead_code)]
 pub struct Service;
@@ -31,6 +52,16 @@ pub enum ServiceError {
     VideoReviewIdNotFound,
     #[error("Review with provided id was not found")]
     ReviewIdNotFound,
+    #[error("Order was not found")]
+    OrderNotFound,
+    #[error("Only succeeded orders can be reviewed")]
+    OrderNotSucceeded,
+    #[error("Order has already been reviewed")]
+    ReviewAlreadyExists,
+    #[error("Review can no longer be edited")]
+    EditWindowClosed,
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
 }
 
 impl From<ServiceError> for AppError {
@@ -44,6 +75,11 @@ impl From<ServiceError> for AppError {
             ServiceError::UrlAlreadyExists => AppError::UrlAlreadyExists,
             ServiceError::VideoReviewIdNotFound => AppError::VideoReviewIdNotFound,
             ServiceError::ReviewIdNotFound => AppError::ReviewWasNotFound,
+            ServiceError::OrderNotFound => AppError::OrderWasNotFound,
+            ServiceError::OrderNotSucceeded => AppError::OrderNotSucceeded,
+            ServiceError::ReviewAlreadyExists => AppError::ReviewAlreadyExists,
+            ServiceError::EditWindowClosed => AppError::ReviewEditWindowClosed,
+            ServiceError::Pagination(cause) => cause.into(),
         }
     }
 }
@@ -51,10 +87,83 @@ impl From<ServiceError> for AppError {
 #[derive(Debug)]
 pub struct AddReviewParameters {
     pub steam_id: i64,
+    pub order_id: i64,
     pub review: String,
     pub stars: i16,
 }
 
+#[derive(Debug)]
+pub struct UpdateReviewParameters {
+    pub steam_id: i64,
+    pub review_id: i64,
+    pub review: Option<String>,
+    pub stars: Option<i16>,
+    //? Counted from the moment the order was finished
+    pub edit_window_seconds: i64,
+}
+
+#[derive(Debug)]
+pub enum ReviewDecision {
+    Publish,
+    Reject(Option<String>),
+}
+
+#[derive(Debug)]
+pub struct ModerateReviewParameters {
+    pub review_id: i64,
+    pub admin_id: i64,
+    pub decision: ReviewDecision,
+}
+
+//? None removes the reply
+#[derive(Debug)]
+pub struct ReplyToReviewParameters {
+    pub review_id: i64,
+    pub reply: Option<String>,
+}
+
+//? Average is None for months without reviews
+#[derive(Debug, FromQueryResult)]
+pub struct MonthlyReviews {
+    pub month: NaiveDateTime,
+    pub count: i64,
+    pub average: Option<Decimal>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct DailyReviews {
+    day: NaiveDateTime,
+    count: i64,
+    stars: i64,
+}
+
+//? Reviews of the 30 days which end with the day
+#[derive(Debug)]
+pub struct RollingReviews {
+    pub day: NaiveDateTime,
+    pub count: i64,
+    pub average: Option<Decimal>,
+}
+
+//? Distribution is indexed by amount of stars
+#[derive(Debug)]
+pub struct ReviewStats {
+    pub total: i64,
+    pub average: Option<Decimal>,
+    pub distribution: [i64; 6],
+    pub monthly: Vec<MonthlyReviews>,
+    pub rolling: Vec<RollingReviews>,
+}
+
+//? Same scale as the averages rounded by the database
+fn average_stars(stars: i64, count: i64) -> Option<Decimal> {
+    (count > 0).then(|| {
+        let mut average = Decimal::from(stars) / Decimal::from(count);
+        average.rescale(2);
+        average
+    })
+}
+
 #[derive(Debug)]
 pub struct AddVideoReviewParameters {
     pub url: String,
@@ -81,64 +190,255 @@ impl UpdateVideoReviewParameters {
     }
 }
 impl Service {
+    //? Published reviews of users who are not blacklisted right now
+    fn visible() -> Select<ReviewEntity> {
+        ReviewEntity::find()
+            .filter(ReviewColumn::Status.eq(ReviewStatus::Published))
+            .filter(
+                Condition::any().add(
+                    ReviewColumn::SteamId.not_in_subquery(BlacklistService::active_subquery()),
+                ),
+            )
+    }
+
+    fn sort() -> Sort<ReviewEntity> {
+        Sort {
+            name: "created_at",
+            column: ReviewColumn::CreatedAt,
+            key: |review| SortKey::Timestamp(review.created_at),
+        }
+    }
+
+    //? Cursor of one queue is rejected by the others
+    fn queue_sort(status: &ReviewStatus) -> Sort<ReviewEntity> {
+        let name = match status {
+            ReviewStatus::Pending => "pending:created_at",
+            ReviewStatus::Published => "published:created_at",
+            ReviewStatus::Rejected => "rejected:created_at",
+        };
+
+        Sort {
+            name,
+            ..Self::sort()
+        }
+    }
+
+    fn check_stars(stars: i16) -> Result<(), ServiceError> {
+        match stars {
+            0..=5 => Ok(()),
+            _ => Err(ServiceError::StarsCheckFailed),
+        }
+    }
+
+    //? Review must point at one of the reviewer's own succeeded orders
+    //? Orders of other users are reported as missing
     #[tracing::instrument(skip(connection))]
     pub async fn add_users_review<T>(
         parameters: impl Into<AddReviewParameters> + Debug,
         connection: &T,
-    ) -> Result<(), ServiceError>
+    ) -> Result<ReviewModel, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
         let review: AddReviewParameters = parameters.into();
 
-        match UserEntity::find_by_id(review.steam_id)
+        if UserEntity::find_by_id(review.steam_id)
             .one(connection)
             .await?
+            .is_none()
         {
-            Some(_) => {
-                if review.stars > 5 || review.stars < 0 {
-                    return Err(ServiceError::StarsCheckFailed);
-                }
+            return Err(ServiceError::UserNotFound(review.steam_id));
+        }
 
-                let review_to_be_inserted = ReviewActiveModel {
-                    steam_id: Set(review.steam_id),
-                    review: Set(review.review),
-                    stars: Set(review.stars),
-                    ..Default::default()
-                };
+        Self::check_stars(review.stars)?;
 
-                review_to_be_inserted.save(connection).await?;
-                Ok(())
+        let order = match OrderEntity::find_by_id(review.order_id)
+            .one(connection)
+            .await?
+        {
+            Some(order) if order.steam_id == review.steam_id => order,
+            _ => return Err(ServiceError::OrderNotFound),
+        };
+
+        if order.status != Status::Succeeded {
+            return Err(ServiceError::OrderNotSucceeded);
+        }
+
+        if ReviewEntity::find()
+            .filter(ReviewColumn::OrderId.eq(order.id))
+            .one(connection)
+            .await?
+            .is_some()
+        {
+            return Err(ServiceError::ReviewAlreadyExists);
+        }
+
+        let review_to_be_inserted = ReviewActiveModel {
+            steam_id: Set(review.steam_id),
+            order_id: Set(Some(order.id)),
+            review: Set(review.review),
+            stars: Set(review.stars),
+            status: Set(ReviewStatus::Pending),
+            ..Default::default()
+        };
+
+        //? Check above misses a review inserted by a concurrent request,
+        //? the unique index on the order catches it
+        match ReviewEntity::insert(review_to_be_inserted)
+            .exec_with_returning(connection)
+            .await
+        {
+            Ok(review) => Ok(review),
+            Err(cause)
+                if matches!(
+                    cause.sql_err(),
+                    Some(SqlErr::UniqueConstraintViolation(ref constraint))
+                        if constraint.contains(REVIEW_ORDER_INDEX)
+                ) =>
+            {
+                Err(ServiceError::ReviewAlreadyExists)
             }
-            None => Err(ServiceError::UserNotFound(review.steam_id)),
+            Err(cause) => Err(cause.into()),
+        }
+    }
+
+    //? Reviews without an order predate verification and are never editable
+    #[tracing::instrument(skip(connection))]
+    pub async fn update_users_review<T>(
+        parameters: UpdateReviewParameters,
+        connection: &T,
+    ) -> Result<ReviewModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let (review, order) = match ReviewEntity::find_by_id(parameters.review_id)
+            .find_also_related(OrderEntity)
+            .one(connection)
+            .await?
+        {
+            Some((review, order)) if review.steam_id == parameters.steam_id => (review, order),
+            _ => return Err(ServiceError::ReviewIdNotFound),
+        };
+
+        let editable_until = order
+            .and_then(|order| order.finished_at)
+            .map(|finished_at| finished_at + Duration::seconds(parameters.edit_window_seconds));
+
+        let now = Utc::now().naive_local();
+        if editable_until.is_none_or(|editable_until| editable_until < now) {
+            return Err(ServiceError::EditWindowClosed);
         }
+
+        let mut review_to_be_updated: ReviewActiveModel = review.into();
+
+        if let Some(stars) = parameters.stars {
+            Self::check_stars(stars)?;
+            review_to_be_updated.stars = Set(stars);
+        }
+        if let Some(text) = parameters.review {
+            review_to_be_updated.review = Set(text);
+        }
+        review_to_be_updated.updated_at = Set(Some(now));
+
+        //? Edited text has to be moderated again
+        review_to_be_updated.status = Set(ReviewStatus::Pending);
+        review_to_be_updated.rejection_reason = Set(None);
+
+        Ok(review_to_be_updated.update(connection).await?)
     }
 
     #[tracing::instrument(skip(connection))]
     pub async fn users_all<T>(
-        limit: u64,
-        offset: u64,
+        page: &PageRequest,
         connection: &T,
-    ) -> Result<Vec<ReviewModel>, ServiceError>
+    ) -> Result<Page<ReviewModel>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        Ok(ReviewEntity::find()
-            .filter(
-                Condition::any().add(
-                    ReviewColumn::SteamId.not_in_subquery(
-                        Query::select()
-                            .column(entity::blacklisted::Column::SteamId)
-                            .from(entity::blacklisted::Entity)
-                            .to_owned(),
-                    ),
-                ),
-            )
-            .order_by_desc(ReviewColumn::CreatedAt)
-            .limit(limit)
-            .offset(offset)
-            .all(connection)
-            .await?)
+        Ok(PaginationService::paginate(
+            Self::visible(),
+            ReviewColumn::Id,
+            Self::sort(),
+            page,
+            connection,
+        )
+        .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn by_status<T>(
+        status: ReviewStatus,
+        page: &PageRequest,
+        connection: &T,
+    ) -> Result<Page<ReviewModel>, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let sort = Self::queue_sort(&status);
+
+        Ok(PaginationService::paginate(
+            ReviewEntity::find().filter(ReviewColumn::Status.eq(status)),
+            ReviewColumn::Id,
+            sort,
+            page,
+            connection,
+        )
+        .await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn moderate<T>(
+        parameters: ModerateReviewParameters,
+        connection: &T,
+    ) -> Result<ReviewModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let review = match ReviewEntity::find_by_id(parameters.review_id)
+            .one(connection)
+            .await?
+        {
+            Some(review) => review,
+            None => return Err(ServiceError::ReviewIdNotFound),
+        };
+
+        let (status, rejection_reason) = match parameters.decision {
+            ReviewDecision::Publish => (ReviewStatus::Published, None),
+            ReviewDecision::Reject(reason) => (ReviewStatus::Rejected, reason),
+        };
+
+        let mut review_to_be_updated: ReviewActiveModel = review.into();
+        review_to_be_updated.status = Set(status);
+        review_to_be_updated.rejection_reason = Set(rejection_reason);
+        review_to_be_updated.moderated_by = Set(Some(parameters.admin_id));
+        review_to_be_updated.moderated_at = Set(Some(Utc::now().naive_local()));
+
+        Ok(review_to_be_updated.update(connection).await?)
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn reply<T>(
+        parameters: ReplyToReviewParameters,
+        connection: &T,
+    ) -> Result<ReviewModel, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let review = match ReviewEntity::find_by_id(parameters.review_id)
+            .one(connection)
+            .await?
+        {
+            Some(review) => review,
+            None => return Err(ServiceError::ReviewIdNotFound),
+        };
+
+        let replied_at = parameters.reply.as_ref().map(|_| Utc::now().naive_local());
+
+        let mut review_to_be_updated: ReviewActiveModel = review.into();
+        review_to_be_updated.reply = Set(parameters.reply);
+        review_to_be_updated.replied_at = Set(replied_at);
+
+        Ok(review_to_be_updated.update(connection).await?)
     }
 
     #[tracing::instrument(skip(connection))]
@@ -257,19 +557,7 @@ impl Service {
         T: ConnectionTrait + TransactionTrait,
     {
         Ok((
-            ReviewEntity::find()
-                .filter(
-                    Condition::any().add(
-                        ReviewColumn::SteamId.not_in_subquery(
-                            Query::select()
-                                .column(entity::blacklisted::Column::SteamId)
-                                .from(entity::blacklisted::Entity)
-                                .to_owned(),
-                        ),
-                    ),
-                )
-                .count(connection)
-                .await?,
+            Self::visible().count(connection).await?,
             VideoReviewEntity::find().count(connection).await?,
         ))
     }
@@ -278,20 +566,126 @@ impl Service {
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        Ok(ReviewEntity::find()
+        Ok(Self::visible()
             .filter(ReviewColumn::Stars.eq(5))
-            .filter(
-                Condition::any().add(
-                    ReviewColumn::SteamId.not_in_subquery(
-                        Query::select()
-                            .column(entity::blacklisted::Column::SteamId)
-                            .from(entity::blacklisted::Entity)
-                            .to_owned(),
-                    ),
-                ),
-            )
             .order_by_desc(ReviewColumn::CreatedAt)
             .all(connection)
             .await?)
     }
+
+    //? Monthly buckets cover the current month and `months - 1` before it
+    //? Rolling series covers today and `days - 1` before it, one point per day
+    //? Months and days without reviews are returned with zero count
+    #[tracing::instrument(skip(connection))]
+    pub async fn stats<T>(
+        months: u32,
+        days: u32,
+        connection: &T,
+    ) -> Result<ReviewStats, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let now = Utc::now().naive_local();
+
+        let mut distribution = [0; 6];
+        for (stars, count) in Self::visible()
+            .select_only()
+            .column(ReviewColumn::Stars)
+            .column_as(ReviewColumn::Id.count(), "count")
+            .group_by(ReviewColumn::Stars)
+            .into_tuple::<(i16, i64)>()
+            .all(connection)
+            .await?
+        {
+            if let Some(slot) = distribution.get_mut(stars as usize) {
+                *slot = count;
+            }
+        }
+
+        let total: i64 = distribution.iter().sum();
+        let average = average_stars(
+            (0..)
+                .zip(distribution)
+                .map(|(stars, count)| stars * count)
+                .sum(),
+            total,
+        );
+
+        let first_month = now
+            .date()
+            .with_day(1)
+            .and_then(|date| date.checked_sub_months(Months::new(months.saturating_sub(1))))
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap_or(now);
+
+        let mut reviewed_months = Self::visible()
+            .filter(ReviewColumn::CreatedAt.gte(first_month))
+            .select_only()
+            .column_as(Expr::cust("date_trunc('month', created_at)"), "month")
+            .column_as(ReviewColumn::Id.count(), "count")
+            .column_as(Expr::cust("round(avg(stars), 2)"), "average")
+            .group_by(Expr::cust("date_trunc('month', created_at)"))
+            .into_model::<MonthlyReviews>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .map(|reviews| (reviews.month, reviews))
+            .collect::<HashMap<_, _>>();
+
+        let monthly = (0..months)
+            .filter_map(|offset| first_month.checked_add_months(Months::new(offset)))
+            .map(|month| {
+                reviewed_months.remove(&month).unwrap_or(MonthlyReviews {
+                    month,
+                    count: 0,
+                    average: None,
+                })
+            })
+            .collect();
+
+        let today = now.date().and_hms_opt(0, 0, 0).unwrap_or(now);
+        let first_day = today - Duration::days(i64::from(days) - 1);
+
+        //? Window of the first day starts before it
+        let reviewed_days = Self::visible()
+            .filter(
+                ReviewColumn::CreatedAt.gte(first_day - Duration::days(ROLLING_WINDOW_DAYS - 1)),
+            )
+            .select_only()
+            .column_as(Expr::cust("date_trunc('day', created_at)"), "day")
+            .column_as(ReviewColumn::Id.count(), "count")
+            .column_as(Expr::cust("sum(stars)::bigint"), "stars")
+            .group_by(Expr::cust("date_trunc('day', created_at)"))
+            .into_model::<DailyReviews>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .map(|reviews| (reviews.day, reviews))
+            .collect::<HashMap<_, _>>();
+
+        let rolling = (0..i64::from(days))
+            .map(|offset| {
+                let day = first_day + Duration::days(offset);
+                let (count, stars) = (0..ROLLING_WINDOW_DAYS)
+                    .filter_map(|back| reviewed_days.get(&(day - Duration::days(back))))
+                    .fold((0, 0), |(count, stars), reviews| {
+                        (count + reviews.count, stars + reviews.stars)
+                    });
+
+                RollingReviews {
+                    day,
+                    count,
+                    average: average_stars(stars, count),
+                }
+            })
+            .collect();
+
+        Ok(ReviewStats {
+            total,
+            average,
+            distribution,
+            monthly,
+            rolling,
+        })
+    }
 }
@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use entity::{
+    requisites::{
+        ActiveModel as RequisitesActiveModel, Column as RequisitesColumn,
+        Entity as RequisitesEntity,
+    },
+    seed_version::{
+        ActiveModel as SeedVersionActiveModel, Column as SeedVersionColumn,
+        Entity as SeedVersionEntity,
+    },
+    social::{ActiveModel as SocialActiveModel, Column as SocialColumn, Entity as SocialEntity},
+};
+use sea_orm::{prelude::*, sea_query::OnConflict, QueryOrder, Set, TransactionTrait};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    TOMLError(#[from] toml::de::Error),
+}
+
+//? Declarative content of the seed file, entries are matched by name
+#[derive(serde::Deserialize, Debug)]
+pub struct Seed {
+    //? Raised whenever entries are added so running deployments pick them up
+    pub version: i64,
+    #[serde(default)]
+    pub requisites: Vec<SeedRequisites>,
+    #[serde(default)]
+    pub social: Vec<SeedSocial>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct SeedRequisites {
+    pub name: String,
+    pub data: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct SeedSocial {
+    pub name: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Applied {
+    //? This or a newer version was applied before
+    UpToDate {
+        version: i64,
+    },
+    Applied {
+        version: i64,
+        requisites: u64,
+        social: u64,
+    },
+}
+
+pub struct Service;
+
+impl Service {
+    #[tracing::instrument]
+    pub fn read(path: &Path) -> Result<Seed, ServiceError> {
+        Ok(toml::from_str::<Seed>(&std::fs::read_to_string(path)?)?)
+    }
+
+    //? Only missing entries are inserted, ones edited by staff are left as they are
+    //? Version is written first in the same transaction so concurrent instances apply it once
+    #[tracing::instrument(skip(connection))]
+    pub async fn apply<T>(seed: Seed, connection: &T) -> Result<Applied, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let applied_version = SeedVersionEntity::find()
+            .order_by_desc(SeedVersionColumn::Version)
+            .one(connection)
+            .await?
+            .map(|applied| applied.version);
+
+        if let Some(applied_version) = applied_version {
+            if applied_version >= seed.version {
+                return Ok(Applied::UpToDate {
+                    version: applied_version,
+                });
+            }
+        }
+
+        let version_to_be_inserted = SeedVersionActiveModel {
+            version: Set(seed.version),
+            ..Default::default()
+        };
+        //? Another instance starting at the same time may have inserted it after the check above
+        let inserted = SeedVersionEntity::insert(version_to_be_inserted)
+            .on_conflict(
+                OnConflict::column(SeedVersionColumn::Version)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(connection)
+            .await?;
+
+        if inserted == 0 {
+            return Ok(Applied::UpToDate {
+                version: seed.version,
+            });
+        }
+
+        let mut requisites = 0;
+        for seeded in seed.requisites {
+            if RequisitesEntity::find()
+                .filter(RequisitesColumn::Name.eq(&seeded.name))
+                .one(connection)
+                .await?
+                .is_none()
+            {
+                let requisites_to_be_inserted = RequisitesActiveModel {
+                    name: Set(seeded.name),
+                    data: Set(seeded.data),
+                    ..Default::default()
+                };
+                requisites_to_be_inserted.insert(connection).await?;
+                requisites += 1;
+            }
+        }
+
+        let mut social = 0;
+        for seeded in seed.social {
+            if SocialEntity::find()
+                .filter(SocialColumn::Name.eq(&seeded.name))
+                .one(connection)
+                .await?
+                .is_none()
+            {
+                let social_to_be_inserted = SocialActiveModel {
+                    name: Set(seeded.name),
+                    url: Set(seeded.url),
+                    ..Default::default()
+                };
+                social_to_be_inserted.insert(connection).await?;
+                social += 1;
+            }
+        }
+
+        Ok(Applied::Applied {
+            version: seed.version,
+            requisites,
+            social,
+        })
+    }
+}
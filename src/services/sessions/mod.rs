@@ -0,0 +1,331 @@
+use chrono::{Duration, Utc};
+use entity::session::{
+    ActiveModel as SessionActiveModel, Column as SessionColumn, Entity as SessionEntity,
+    Model as SessionModel,
+};
+use migration::SimpleExpr;
+use sea_orm::{prelude::*, Set, TransactionTrait};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+
+use crate::errors::AppError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error(transparent)]
+    DbErr(#[from] sea_orm::DbErr),
+    #[error("Session has expired or was revoked")]
+    Revoked,
+}
+
+impl From<ServiceError> for AppError {
+    fn from(value: ServiceError) -> Self {
+        match value {
+            ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
+            ServiceError::Revoked => AppError::SessionRevoked,
+        }
+    }
+}
+
+//? Users and staff have separate login endpoints
+//? so a refresh token is only accepted by the api it was issued by
+//? Access tokens carry it as aud for the same reason
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    User,
+    Staff,
+}
+
+impl Audience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Audience::User => "user",
+            Audience::Staff => "staff",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    User(i64),
+    Staff(i64),
+}
+
+impl Subject {
+    fn of(session: &SessionModel) -> Option<Self> {
+        match (session.steam_id, session.admin_id) {
+            (Some(steam_id), None) => Some(Subject::User(steam_id)),
+            (None, Some(admin_id)) => Some(Subject::Staff(admin_id)),
+            _ => None,
+        }
+    }
+
+    pub fn audience(&self) -> Audience {
+        match self {
+            Subject::User(_) => Audience::User,
+            Subject::Staff(_) => Audience::Staff,
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        match self {
+            Subject::User(id) | Subject::Staff(id) => *id,
+        }
+    }
+
+    fn owns(&self) -> SimpleExpr {
+        match self {
+            Subject::User(steam_id) => SessionColumn::SteamId.eq(*steam_id),
+            Subject::Staff(admin_id) => SessionColumn::AdminId.eq(*admin_id),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OpenSessionParameters {
+    pub subject: Subject,
+    pub ttl_seconds: i64,
+}
+
+pub struct RefreshSessionParameters {
+    pub refresh_token: String,
+    pub audience: Audience,
+    pub ttl_seconds: i64,
+}
+
+//? This is manual for hiding refresh token in logs
+impl Debug for RefreshSessionParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshSessionParameters")
+            .field("audience", &self.audience)
+            .field("ttl_seconds", &self.ttl_seconds)
+            .finish()
+    }
+}
+
+pub struct IssuedSession {
+    pub id: i64,
+    pub subject: Subject,
+    //? Shown once, only its hash is stored
+    pub refresh_token: String,
+}
+
+impl Debug for IssuedSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IssuedSession")
+            .field("id", &self.id)
+            .field("subject", &self.subject)
+            .finish()
+    }
+}
+
+//? Refresh token is `<session id>.<secret>`
+fn split(refresh_token: &str) -> Option<(i64, &str)> {
+    let (id, secret) = refresh_token.split_once('.')?;
+    Some((id.parse().ok()?, secret))
+}
+
+//? Secret is random so a fast hash is enough, argon2 would only
+//? hand out free cpu time to anybody posting made up tokens
+fn hash(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+fn secret() -> (String, String) {
+    let secret = uuid::Uuid::new_v4().simple().to_string();
+    let hashed_secret = hash(&secret);
+    (secret, hashed_secret)
+}
+
+pub struct Service;
+
+impl Service {
+    fn alive() -> SimpleExpr {
+        SessionColumn::RevokedAt
+            .is_null()
+            .and(SessionColumn::ExpiresAt.gt(Utc::now().naive_local()))
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn open<T>(
+        parameters: OpenSessionParameters,
+        connection: &T,
+    ) -> Result<IssuedSession, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let (secret, hashed_secret) = secret();
+
+        let (steam_id, admin_id) = match parameters.subject {
+            Subject::User(steam_id) => (Some(steam_id), None),
+            Subject::Staff(admin_id) => (None, Some(admin_id)),
+        };
+
+        let session_to_be_opened = SessionActiveModel {
+            steam_id: Set(steam_id),
+            admin_id: Set(admin_id),
+            refresh_token: Set(hashed_secret),
+            expires_at: Set(Utc::now().naive_local() + Duration::seconds(parameters.ttl_seconds)),
+            ..Default::default()
+        };
+
+        let session = SessionEntity::insert(session_to_be_opened)
+            .exec_with_returning(connection)
+            .await?;
+
+        Ok(IssuedSession {
+            id: session.id,
+            subject: parameters.subject,
+            refresh_token: format!("{}.{secret}", session.id),
+        })
+    }
+
+    //? Refresh token is rotated and the session is prolonged
+    //? Presenting the token which was just rotated revokes the session
+    //? because either the owner or somebody else holds a stolen copy
+    #[tracing::instrument(skip(connection))]
+    pub async fn refresh<T>(
+        parameters: RefreshSessionParameters,
+        connection: &T,
+    ) -> Result<IssuedSession, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let (id, presented_secret) =
+            split(&parameters.refresh_token).ok_or(ServiceError::Revoked)?;
+
+        let session = SessionEntity::find_by_id(id)
+            .filter(Self::alive())
+            .one(connection)
+            .await?
+            .ok_or(ServiceError::Revoked)?;
+
+        let subject = match Subject::of(&session) {
+            Some(subject) if subject.audience() == parameters.audience => Ok(subject),
+            _ => Err(ServiceError::Revoked),
+        }?;
+
+        //? Made up secrets are simply rejected, otherwise anybody could
+        //? log everyone out by walking through sequential session ids
+        let presented_secret = hash(presented_secret);
+        if presented_secret != session.refresh_token {
+            if session.previous_refresh_token.as_deref() == Some(presented_secret.as_str()) {
+                Self::revoke_session(session.id, connection).await?;
+            }
+            return Err(ServiceError::Revoked);
+        }
+
+        let (secret, hashed_secret) = secret();
+
+        //? Compared with the old hash so only one of two concurrent refreshes wins
+        let rotated = SessionEntity::update_many()
+            .col_expr(SessionColumn::RefreshToken, Expr::value(hashed_secret))
+            .col_expr(
+                SessionColumn::PreviousRefreshToken,
+                Expr::value(Some(session.refresh_token.clone())),
+            )
+            .col_expr(
+                SessionColumn::RefreshedAt,
+                Expr::value(Some(Utc::now().naive_local())),
+            )
+            .col_expr(
+                SessionColumn::ExpiresAt,
+                Expr::value(Utc::now().naive_local() + Duration::seconds(parameters.ttl_seconds)),
+            )
+            .filter(SessionColumn::Id.eq(session.id))
+            .filter(SessionColumn::RefreshToken.eq(session.refresh_token))
+            .exec(connection)
+            .await?;
+
+        match rotated.rows_affected {
+            0 => Err(ServiceError::Revoked),
+            _ => Ok(IssuedSession {
+                id: session.id,
+                subject,
+                refresh_token: format!("{}.{secret}", session.id),
+            }),
+        }
+    }
+
+    #[tracing::instrument(skip(connection))]
+    pub async fn is_active<T>(
+        session_id: i64,
+        subject: Subject,
+        connection: &T,
+    ) -> Result<bool, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(SessionEntity::find_by_id(session_id)
+            .filter(subject.owns())
+            .filter(Self::alive())
+            .count(connection)
+            .await?
+            > 0)
+    }
+
+    //? Logging out of a session which is already gone is not an error
+    #[tracing::instrument(skip(connection, refresh_token))]
+    pub async fn revoke<T>(
+        refresh_token: &str,
+        audience: Audience,
+        connection: &T,
+    ) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        let (id, presented_secret) = split(refresh_token).ok_or(ServiceError::Revoked)?;
+
+        let session = match SessionEntity::find_by_id(id).one(connection).await? {
+            Some(session) => session,
+            None => return Err(ServiceError::Revoked),
+        };
+
+        match Subject::of(&session) {
+            Some(subject) if subject.audience() == audience => {}
+            _ => return Err(ServiceError::Revoked),
+        }
+
+        if hash(presented_secret) != session.refresh_token {
+            return Err(ServiceError::Revoked);
+        }
+
+        Self::revoke_session(session.id, connection).await
+    }
+
+    async fn revoke_session<T>(session_id: i64, connection: &T) -> Result<(), ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        SessionEntity::update_many()
+            .col_expr(
+                SessionColumn::RevokedAt,
+                Expr::value(Some(Utc::now().naive_local())),
+            )
+            .filter(SessionColumn::Id.eq(session_id))
+            .filter(SessionColumn::RevokedAt.is_null())
+            .exec(connection)
+            .await?;
+        Ok(())
+    }
+
+    //? Access tokens of revoked sessions are rejected right away
+    //? not when they expire
+    #[tracing::instrument(skip(connection))]
+    pub async fn revoke_all<T>(subject: Subject, connection: &T) -> Result<u64, ServiceError>
+    where
+        T: ConnectionTrait + TransactionTrait,
+    {
+        Ok(SessionEntity::update_many()
+            .col_expr(
+                SessionColumn::RevokedAt,
+                Expr::value(Some(Utc::now().naive_local())),
+            )
+            .filter(subject.owns())
+            .filter(Self::alive())
+            .exec(connection)
+            .await?
+            .rows_affected)
+    }
+}
@@ -6,13 +6,23 @@ use entity::{
     },
 };
 
-use migration::{Alias, Func, Query, SimpleExpr};
+use migration::{Alias, Func, SimpleExpr};
 use sea_orm::{
     prelude::*, Condition, FromQueryResult, IntoSimpleExpr, JoinType, Order, QueryOrder,
     QuerySelect, Set, TransactionTrait,
 };
 
-use crate::errors::AppError;
+use crate::{
+    errors::AppError,
+    services::{
+        admin::blacklist::Service as BlacklistService,
+        pagination::{
+            Cursor, Direction, Page, PageRequest, ServiceError as PaginationError, SortKey,
+        },
+    },
+};
+
+const TOP_SORT: &str = "amount";
 
 #[allow(dead_code)]
 pub struct Service;
@@ -23,6 +33,8 @@ pub enum ServiceError {
     DbErr(#[from] DbErr),
     #[error("User with id = {0} was not found")]
     UserWasNotFound(i64),
+    #[error(transparent)]
+    Pagination(#[from] PaginationError),
 }
 
 impl From<ServiceError> for AppError {
@@ -30,6 +42,7 @@ impl From<ServiceError> for AppError {
         match value {
             ServiceError::DbErr(cause) => AppError::InternalServerError(Box::new(cause)),
             ServiceError::UserWasNotFound(id) => AppError::UserWasNotFound(id),
+            ServiceError::Pagination(cause) => cause.into(),
         }
     }
 }
@@ -113,33 +126,22 @@ impl Service {
         Ok(())
     }
 
-    pub async fn top<T>(
-        limit: u64,
-        offset: u64,
-        connection: &T,
-    ) -> Result<Vec<TopUser>, ServiceError>
+    pub async fn top<T>(page: &PageRequest, connection: &T) -> Result<Page<TopUser>, ServiceError>
     where
         T: ConnectionTrait + TransactionTrait,
     {
-        Ok(UserEntity::find()
+        let amount = Func::coalesce([
+            entity::order::Column::Amount.sum(),
+            Expr::val(0).into_simple_expr(),
+        ]);
+
+        let select = UserEntity::find()
             .select_only()
             .column(entity::user::Column::SteamId)
-            .column_as(
-                Expr::value(Func::coalesce([
-                    entity::order::Column::Amount.sum(),
-                    Expr::val(0).into_simple_expr(),
-                ])),
-                "amount",
-            )
+            .column_as(Expr::value(amount.clone()), "amount")
             .filter(
-                Condition::any().add(
-                    UserColumn::SteamId.not_in_subquery(
-                        Query::select()
-                            .column(entity::blacklisted::Column::SteamId)
-                            .from(entity::blacklisted::Entity)
-                            .to_owned(),
-                    ),
-                ),
+                Condition::any()
+                    .add(UserColumn::SteamId.not_in_subquery(BlacklistService::active_subquery())),
             )
             .join_as(
                 JoinType::LeftJoin,
@@ -147,13 +149,64 @@ impl Service {
                 Alias::new("order"),
             )
             .filter(entity::order::Column::Status.eq(Status::Succeeded))
-            .group_by(UserColumn::SteamId)
-            .order_by(SimpleExpr::Custom("amount".to_owned()), Order::Desc)
-            .offset(Some(offset))
-            .limit(Some(limit))
+            .group_by(UserColumn::SteamId);
+
+        //? Count runs over the grouped select as a subquery
+        let total = select
+            .clone()
+            .into_model::<TopUser>()
+            .count(connection)
+            .await?;
+
+        //? Sorting by an aggregate so the keyset goes to having
+        let mut select = select;
+        if let Some(cursor) = page.after(TOP_SORT, &ColumnType::Decimal(None))? {
+            let key = Value::from(cursor.key.clone());
+            let amount = Expr::expr(amount.clone());
+            select = select.having(match page.direction {
+                Direction::Asc => Condition::any().add(amount.clone().gt(key.clone())).add(
+                    Condition::all()
+                        .add(amount.eq(key))
+                        .add(UserColumn::SteamId.gt(cursor.id)),
+                ),
+                Direction::Desc => Condition::any().add(amount.clone().lt(key.clone())).add(
+                    Condition::all()
+                        .add(amount.eq(key))
+                        .add(UserColumn::SteamId.lt(cursor.id)),
+                ),
+            });
+        }
+
+        let order = match page.direction {
+            Direction::Asc => Order::Asc,
+            Direction::Desc => Order::Desc,
+        };
+
+        let mut items = select
+            .order_by(SimpleExpr::Custom("amount".to_owned()), order.clone())
+            .order_by(UserColumn::SteamId, order)
+            .limit(Some(page.limit + 1))
             .into_model::<TopUser>()
             .all(connection)
-            .await?)
+            .await?;
+
+        let next_cursor = if items.len() as u64 > page.limit {
+            items.truncate(page.limit as usize);
+            items.last().map(|last| Cursor {
+                sort: String::from(TOP_SORT),
+                direction: page.direction,
+                key: SortKey::Decimal(last.amount),
+                id: last.steam_id,
+            })
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items,
+            next_cursor,
+            total,
+        })
     }
 
     pub async fn registered_in_period<T>(
@@ -1,6 +1,6 @@
 use sea_orm::DatabaseConnection;
 
-use crate::{config::Configuration, openid::SteamOpenId};
+use crate::{config::Configuration, openid::SteamOpenId, services::auth::keys::Keys};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -8,6 +8,7 @@ pub struct AppState {
     configuration: Configuration,
     redis_client: redis::Client,
     steam_openid: SteamOpenId,
+    keys: Keys,
 }
 
 impl AppState {
@@ -16,12 +17,14 @@ impl AppState {
         configuration: Configuration,
         redis_client: redis::Client,
         steam_openid: SteamOpenId,
+        keys: Keys,
     ) -> Self {
         Self {
             database_connection,
             configuration,
             redis_client,
             steam_openid,
+            keys,
         }
     }
 
@@ -40,4 +43,8 @@ impl AppState {
     pub fn steam_openid(&self) -> &SteamOpenId {
         &self.steam_openid
     }
+
+    pub fn keys(&self) -> &Keys {
+        &self.keys
+    }
 }